@@ -1,4 +1,4 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use super::providers::{self, AdapterKind, Provider};
 
@@ -12,9 +12,13 @@ const TERMINAL_SYSTEM_PROMPT_TEMPLATE: &str =
     "You are a terminal command generator for macOS. Given the user's task description and terminal \
      context, output ONLY the exact command(s) to run. No explanations, no markdown, no code fences. \
      Just the raw command(s). If multiple commands are needed, separate them with && or use pipes. \
-     Prefer common POSIX tools (grep, find, sed, awk) over modern alternatives (rg, fd, jq). \
      The user is on macOS with {shell_type} shell.";
 
+/// Appended on macOS when no tool inventory was captured.
+#[cfg(target_os = "macos")]
+const POSIX_TOOLS_RULE: &str =
+    " Prefer common POSIX tools (grep, find, sed, awk) over modern alternatives (rg, fd, jq).";
+
 /// System prompt for terminal mode on Windows: strict command-only output.
 /// Placeholder {shell_type} is replaced at runtime.
 #[cfg(target_os = "windows")]
@@ -46,6 +50,50 @@ const TERMINAL_SYSTEM_PROMPT_TEMPLATE: &str =
      Just the raw command(s). If multiple commands are needed, separate them with && or use pipes. \
      The user has {shell_type} shell.";
 
+/// System prompt for terminal mode when the terminal is logged in to a remote host
+/// (ssh, mosh, et), any platform. Placeholders {os}, {target} and {shell_type} are
+/// replaced at runtime.
+const REMOTE_TERMINAL_SYSTEM_PROMPT_TEMPLATE: &str =
+    "You are a terminal command generator for a remote {os} host. Given the user's task description \
+     and terminal context, output ONLY the exact command(s) to run. No explanations, no markdown, no \
+     code fences. Just the raw command(s). If multiple commands are needed, separate them with && or \
+     use pipes. The terminal is logged in to {target} with {shell_type} shell: the command runs there, \
+     not on the user's local machine, so do not assume local files, paths or tools. Prefer common \
+     POSIX tools (grep, find, sed, awk) unless the context shows others are installed.";
+
+/// System prompt for terminal mode when the shell has entered a container, pod or
+/// toolbox, any platform. Placeholders {target}, {distro} and {shell_type} are
+/// replaced at runtime.
+const CONTAINER_TERMINAL_SYSTEM_PROMPT_TEMPLATE: &str =
+    "You are a terminal command generator for a shell inside {target} ({distro}). Given the user's \
+     task description and terminal context, output ONLY the exact command(s) to run. No explanations, \
+     no markdown, no code fences. Just the raw command(s). If multiple commands are needed, separate \
+     them with && or use pipes. The shell is {shell_type} and the command runs in the container, not \
+     on the host: use the container distribution's package manager and paths, and expect a minimal \
+     image where common tools may be missing.";
+
+/// Appended to the fix prompt in a container. Placeholders {target} and {distro} are replaced at runtime.
+const CONTAINER_FIX_RULE: &str =
+    " The command runs inside {target} ({distro}), not on the host: use its package manager and paths.";
+
+/// System prompt for terminal mode when a language REPL has the foreground, any
+/// platform. Placeholders {language}, {program} and {rules} are replaced at runtime.
+const REPL_SYSTEM_PROMPT_TEMPLATE: &str =
+    "You are a {language} input generator for an interactive {program} session. Given the user's \
+     task description and terminal context, output ONLY the exact input to type at the REPL prompt. \
+     No explanations, no markdown, no code fences. The terminal is running a {language} REPL, not a \
+     shell: never output shell commands, they would be read as {language}. {rules}";
+
+/// Appended to the fix prompt when a REPL has the foreground. Placeholders
+/// {language} and {program} are replaced at runtime.
+const REPL_FIX_RULE: &str =
+    " The terminal is running a {language} REPL ({program}): the fix is {language} input for it, \
+     not a shell command.";
+
+/// Appended to the fix prompt in a remote session. Placeholder {target} is replaced at runtime.
+const REMOTE_FIX_RULE: &str =
+    " The command runs on the remote host {target}, not on the user's local machine.";
+
 /// System prompt for assistant mode: concise conversational responses.
 #[cfg(target_os = "macos")]
 const ASSISTANT_SYSTEM_PROMPT: &str =
@@ -62,8 +110,21 @@ const ASSISTANT_SYSTEM_PROMPT: &str =
     "You are a concise assistant accessed via a desktop overlay. Answer in 2-3 sentences maximum. \
      Be direct and helpful. No markdown formatting, no code fences unless the user explicitly asks for code.";
 
+/// System prompt for "fix the last error" requests (any platform).
+/// Placeholder {shell_type} is replaced at runtime.
+const FIX_SYSTEM_PROMPT_TEMPLATE: &str =
+    "You are fixing a terminal command that just failed in a {shell_type} shell. Given the failing \
+     command, its error output and the project context, reply with exactly one line that starts with \
+     \"# \" and states the cause in plain words, followed by the corrected command(s) on the next line. \
+     No markdown, no code fences, no other text. If no command can fix the problem, reply with the \
+     \"# \" line only.";
+
+/// Query shortcut for diagnosing the last failed command. Text after it is
+/// passed along as extra instructions.
+const FIX_QUERY_PREFIX: &str = "/fix";
+
 /// Represents a previous conversation turn passed from the frontend.
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct ChatMessage {
     pub role: String,
     pub content: String,
@@ -88,15 +149,150 @@ struct TerminalContextView {
     running_process: Option<String>,
     #[serde(default)]
     is_wsl: bool,
+    #[serde(default)]
+    cloud: Option<crate::terminal::cloud::CloudContext>,
+    #[serde(default)]
+    git: Option<crate::terminal::git::GitContext>,
+    #[serde(default)]
+    recent_commands: Vec<crate::terminal::shell_history::ShellCommand>,
+    #[serde(default)]
+    tools: Option<crate::terminal::tools::ToolInventory>,
+    #[serde(default)]
+    aliases: Option<crate::terminal::aliases::ShellAliases>,
+    #[serde(default)]
+    multiplexer: Option<crate::terminal::multiplexer::MultiplexerPane>,
+    #[serde(default)]
+    remote: Option<crate::terminal::remote::RemoteSession>,
+    #[serde(default)]
+    container: Option<crate::terminal::container::ContainerSession>,
+    #[serde(default)]
+    repl: Option<crate::terminal::repl::ReplSession>,
+}
+
+/// Apply the administrator's mandatory redactions and the user's privacy level
+/// to captured text on its way to the model.
+fn scrub(text: &str) -> String {
+    crate::terminal::filter::redact_pii(&super::policy::apply_redactions(text))
+}
+
+/// Extra instructions after the `/fix` shortcut, or None for a regular query.
+fn fix_request(query: &str) -> Option<&str> {
+    let rest = query.trim_start().strip_prefix(FIX_QUERY_PREFIX)?;
+    (rest.is_empty() || rest.starts_with(char::is_whitespace)).then(|| rest.trim())
+}
+
+/// Smart-truncated, redacted terminal output for the prompt, plus any
+/// quarantined instruction-like blocks. None when nothing is left after preparation.
+fn terminal_output_part(output: &str, model: &str) -> Option<(String, Vec<String>)> {
+    let context_window = crate::terminal::context::context_window_for_model(model);
+    let prepared = crate::terminal::context::prepare_terminal_context(output, context_window);
+    if prepared.is_empty() {
+        return None;
+    }
+    let filtered = scrub(&crate::terminal::filter::filter_sensitive(&prepared));
+    let quarantined = crate::terminal::injection::quarantine(&filtered);
+    let line_count = quarantined.text.lines().count();
+    Some((
+        format!("Terminal output ({} lines):\n{}", line_count, quarantined.text),
+        quarantined.render_blocks(),
+    ))
+}
+
+/// Where commands run when it is not the local shell: the remote host or the
+/// container the shell has entered. None for a local shell.
+fn session_part(terminal: &TerminalContextView) -> Option<Vec<String>> {
+    let lines = match (&terminal.remote, &terminal.container) {
+        (Some(remote), _) => remote.describe(),
+        (None, Some(container)) => container.describe(),
+        (None, None) => return None,
+    };
+    Some(lines.iter().map(|line| scrub(line)).collect())
+}
+
+/// "Project:" section listing the manifests and tasks around the terminal's CWD.
+fn project_part(terminal: &TerminalContextView) -> Option<String> {
+    // A WSL CWD is a Linux path the Windows side cannot read; a remote or
+    // container shell's CWD is on another filesystem
+    if terminal.is_wsl || terminal.remote.is_some() || terminal.container.is_some() {
+        return None;
+    }
+    let manifests = crate::terminal::project::detect(terminal.cwd.as_deref()?);
+    crate::terminal::project::describe(&manifests, crate::terminal::project::PROJECT_BUDGET_CHARS)
+        .map(|section| scrub(&section))
+}
+
+/// Build the user message for a `/fix` request: the failed command verbatim, its
+/// error output, and the project manifests and tasks near the CWD.
+///
+/// Falls back to the recent terminal output when no failed command can be isolated.
+fn build_fix_message(notes: &str, ctx: &AppContextView, model: &str) -> (String, bool) {
+    let mut parts: Vec<String> = Vec::new();
+    let mut untrusted: Vec<String> = Vec::new();
+
+    if let Some(terminal) = &ctx.terminal {
+        if let Some(lines) = session_part(terminal) {
+            parts.extend(lines);
+        } else {
+            if let Some(shell) = &terminal.shell_type {
+                parts.push(format!("Shell: {}", shell));
+            }
+            if let Some(cwd) = &terminal.cwd {
+                parts.push(format!("CWD: {}", scrub(cwd)));
+            }
+            if let Some(git) = &terminal.git {
+                parts.extend(git.describe().iter().map(|line| scrub(line)));
+            }
+            parts.extend(project_part(terminal));
+        }
+        if let Some(repl) = &terminal.repl {
+            parts.push(scrub(&repl.describe()));
+        }
+
+        let output = terminal.visible_output.as_deref();
+        match output.and_then(crate::terminal::context::last_failed_command) {
+            Some(failed) => {
+                let status = failed
+                    .exit_code
+                    .map(|code| format!(" (exit status {})", code))
+                    .unwrap_or_default();
+                let redact = |text: &str| scrub(&crate::terminal::filter::filter_sensitive(text));
+                parts.push(format!("Failed command{}:\n{}", status, redact(&failed.command)));
+                if !failed.output.is_empty() {
+                    let quarantined = crate::terminal::injection::quarantine(&redact(&failed.output));
+                    parts.push(format!("Error output:\n{}", quarantined.text));
+                    untrusted.extend(quarantined.render_blocks());
+                }
+            }
+            None => {
+                eprintln!("[ai] /fix: no failed command found, sending recent output");
+                if let Some((part, blocks)) = output.and_then(|o| terminal_output_part(o, model)) {
+                    parts.push(part);
+                    untrusted.extend(blocks);
+                }
+            }
+        }
+    }
+
+    parts.extend(untrusted.iter().cloned());
+    if notes.is_empty() {
+        parts.push("\nTask: Diagnose why the failed command failed and give a corrected command.".to_string());
+    } else {
+        parts.push(format!("\nTask: Fix the failed command. {}", notes));
+    }
+    (parts.join("\n"), !untrusted.is_empty())
 }
 
 /// Build the user message string from the app context and raw query.
 ///
-/// Terminal mode: includes App, Shell, CWD, Running process, Terminal output (smart-truncated),
+/// Terminal mode: includes App, Shell, aliases, CWD, Running process (or the remote host or container), multiplexer pane, git state, cloud CLI targets, project tasks, Terminal output (smart-truncated)
+/// or recent shell history when no output was captured,
 /// Console last line (if browser DevTools open), then the task.
 ///
 /// Assistant mode: includes App name (if available), Console last line (if browser), then the question.
-fn build_user_message(query: &str, ctx: &AppContextView, is_follow_up: bool, model: &str) -> String {
+///
+/// Also returns whether any captured text was quarantined as untrusted, so the
+/// caller can extend the system prompt accordingly.
+fn build_user_message(query: &str, ctx: &AppContextView, is_follow_up: bool, model: &str) -> (String, bool) {
     let is_terminal_mode = ctx
         .terminal
         .as_ref()
@@ -107,13 +303,15 @@ fn build_user_message(query: &str, ctx: &AppContextView, is_follow_up: bool, mod
         // Follow-up: just the query, no terminal context (CTXT-03)
         // System prompt already has shell type from the first message
         if is_terminal_mode {
-            return format!("Task: {}", query);
+            return (format!("Task: {}", query), false);
         } else {
-            return query.to_string();
+            return (query.to_string(), false);
         }
     }
 
     let mut parts: Vec<String> = Vec::new();
+    // Instruction-like captured lines, rendered after the inline context
+    let mut untrusted: Vec<String> = Vec::new();
 
     if is_terminal_mode {
         if let Some(name) = &ctx.app_name {
@@ -123,32 +321,51 @@ fn build_user_message(query: &str, ctx: &AppContextView, is_follow_up: bool, mod
             if terminal.is_wsl {
                 parts.push("OS: WSL on Windows (Linux terminal)".to_string());
             }
-            if let Some(shell) = &terminal.shell_type {
-                parts.push(format!("Shell: {}", shell));
+            if let Some(lines) = session_part(terminal) {
+                // The local shell and CWD do not describe the remote host or container
+                parts.extend(lines);
+            } else {
+                if let Some(shell) = &terminal.shell_type {
+                    parts.push(format!("Shell: {}", shell));
+                }
+                if let Some(aliases) = &terminal.aliases {
+                    parts.extend(aliases.describe().iter().map(|line| scrub(line)));
+                }
+                if let Some(cwd) = &terminal.cwd {
+                    parts.push(format!("CWD: {}", scrub(cwd)));
+                }
+                if let Some(proc) = &terminal.running_process {
+                    parts.push(format!("Running: {}", proc));
+                }
             }
-            if let Some(cwd) = &terminal.cwd {
-                parts.push(format!("CWD: {}", cwd));
+            if let Some(repl) = &terminal.repl {
+                parts.push(scrub(&repl.describe()));
             }
-            if let Some(proc) = &terminal.running_process {
-                parts.push(format!("Running: {}", proc));
+            if let Some(pane) = &terminal.multiplexer {
+                parts.push(scrub(&pane.describe()));
             }
-            if let Some(output) = &terminal.visible_output {
-                let context_window = crate::terminal::context::context_window_for_model(model);
-                let prepared = crate::terminal::context::prepare_terminal_context(output, context_window);
-                if !prepared.is_empty() {
-                    let filtered = crate::terminal::filter::filter_sensitive(&prepared);
-                    let line_count = filtered.lines().count();
-                    parts.push(format!(
-                        "Terminal output ({} lines):\n{}",
-                        line_count,
-                        filtered
-                    ));
-                }
+            if let Some(git) = &terminal.git {
+                parts.extend(git.describe().iter().map(|line| scrub(line)));
+            }
+            if let Some(cloud) = &terminal.cloud {
+                parts.extend(cloud.describe().iter().map(|line| scrub(line)));
+            }
+            parts.extend(project_part(terminal));
+            if let Some((part, blocks)) = terminal
+                .visible_output
+                .as_deref()
+                .and_then(|output| terminal_output_part(output, model))
+            {
+                parts.push(part);
+                untrusted.extend(blocks);
+            } else if terminal.visible_output.is_none() && !terminal.recent_commands.is_empty() {
+                let history = crate::terminal::shell_history::render(&terminal.recent_commands);
+                parts.push(format!("Recent commands (shell history, oldest first):\n{}", scrub(&history)));
             }
         }
         if ctx.console_detected {
             if let Some(line) = &ctx.console_last_line {
-                parts.push(format!("Console last line: {}", line));
+                parts.push(format!("Console last line: {}", scrub(line)));
             }
         }
         parts.push(format!("\nTask: {}", query));
@@ -159,72 +376,63 @@ fn build_user_message(query: &str, ctx: &AppContextView, is_follow_up: bool, mod
         }
         if ctx.console_detected {
             if let Some(line) = &ctx.console_last_line {
-                parts.push(format!("Console last line: {}", line));
+                parts.push(format!("Console last line: {}", scrub(line)));
             }
         }
         if let Some(text) = &ctx.visible_text {
             let lines: Vec<&str> = text.lines().collect();
             let start = lines.len().saturating_sub(50);
             let slice = &lines[start..];
+            let redacted = scrub(&slice.join("\n"));
+            let quarantined = crate::terminal::injection::quarantine(&redacted);
             parts.push(format!(
                 "Screen content ({} lines):\n{}",
                 slice.len(),
-                slice.join("\n")
+                quarantined.text
             ));
+            untrusted.extend(quarantined.render_blocks());
         }
         parts.push(query.to_string());
     }
 
-    parts.join("\n")
+    let has_untrusted = !untrusted.is_empty();
+    if has_untrusted {
+        // Keep the task as the final line so the model reads it last
+        let task = parts.pop().unwrap_or_default();
+        parts.extend(untrusted);
+        parts.push(task);
+    }
+
+    (parts.join("\n"), has_untrusted)
 }
 
-/// Stream AI response tokens to the frontend via a Tauri IPC Channel.
+/// Chat completion URL the request for `provider` is sent to.
 ///
-/// - Accepts a `provider` parameter to dispatch to the correct streaming adapter.
-/// - Reads the API key from the OS Keychain using the provider's account name.
-/// - Determines terminal vs assistant mode from context_json.
-/// - Builds the system prompt (two modes) and user message with context.
-/// - Includes session history (pre-capped by frontend via configurable turnLimit) in the messages array.
-/// - Dispatches to the appropriate adapter based on provider.adapter_kind().
-#[tauri::command]
-pub async fn stream_ai_response(
-    app_handle: tauri::AppHandle,
-    state: tauri::State<'_, crate::state::AppState>,
-    provider: Provider,
-    query: String,
-    model: String,
-    context_json: String,
-    history: Vec<ChatMessage>,
-    on_token: tauri::ipc::Channel<String>,
-) -> Result<(), String> {
-    eprintln!(
-        "[ai] stream_ai_response called, provider={}, model={}",
-        provider.display_name(),
-        model
-    );
-
-    // 1. Resolve API URL and key based on provider type
-    let (api_url, api_key) = if provider.is_local() {
-        let base = super::providers::get_provider_base_url(
-            &app_handle,
-            &provider,
-        );
-        let url = format!("{}/v1/chat/completions", base.trim_end_matches('/'));
-        (url, String::new())
+/// Gemini appends the model and API key at call time, so its base URL is returned as-is.
+fn endpoint_url(app_handle: &tauri::AppHandle, provider: &Provider) -> String {
+    if provider.is_local() {
+        let base = super::providers::get_provider_base_url(app_handle, provider);
+        format!("{}/v1/chat/completions", base.trim_end_matches('/'))
     } else {
-        let entry = keyring::Entry::new(SERVICE, provider.keychain_account())
-            .map_err(|e| format!("Keyring error: {}", e))?;
-        let key = entry.get_password().map_err(|_| {
-            format!(
-                "No {} API key configured. Open Settings to add one.",
-                provider.display_name()
-            )
-        })?;
-        (provider.api_url().to_string(), key)
-    };
+        provider.api_url().to_string()
+    }
+}
+
+/// Everything built from the captured context before a request is dispatched.
+struct PreparedRequest {
+    ctx: AppContextView,
+    system_prompt: String,
+    /// System prompt + scrubbed history + current user message, in OpenAI format
+    messages: Vec<serde_json::Value>,
+}
 
-    // 2. Parse the context JSON into a lightweight view struct
-    let ctx: AppContextView = serde_json::from_str(&context_json).unwrap_or_else(|e| {
+/// Build the system prompt and messages array for a query.
+///
+/// Shared by `stream_ai_response` and `preview_request` so the preview shows
+/// byte-for-byte what would be sent.
+fn prepare_request(query: &str, context_json: &str, history: &[ChatMessage], model: &str) -> PreparedRequest {
+    // Parse the context JSON into a lightweight view struct
+    let ctx: AppContextView = serde_json::from_str(context_json).unwrap_or_else(|e| {
         eprintln!("[ai] Failed to parse context_json: {}", e);
         // Fallback: assistant mode with no context
         AppContextView {
@@ -236,7 +444,7 @@ pub async fn stream_ai_response(
         }
     });
 
-    // 3. Determine mode and build system prompt
+    // Determine mode and build system prompt
     let is_terminal_mode = ctx
         .terminal
         .as_ref()
@@ -249,15 +457,81 @@ pub async fn stream_ai_response(
         .map(|t| t.is_wsl)
         .unwrap_or(false);
 
-    let system_prompt = if is_terminal_mode {
-        let default_shell = if is_wsl { "bash" } else { "zsh" };
-        let shell_type = ctx
+    // `/fix` only applies to the first turn; follow-ups refine the answer normally
+    let fix_notes = if is_terminal_mode && history.is_empty() {
+        fix_request(query)
+    } else {
+        None
+    };
+
+    let remote = ctx.terminal.as_ref().and_then(|t| t.remote.as_ref());
+    // A remote session takes precedence; detection skips containers there
+    let container = ctx
+        .terminal
+        .as_ref()
+        .and_then(|t| t.container.as_ref())
+        .filter(|_| remote.is_none());
+    // Whatever is typed goes to the REPL, wherever it runs
+    let repl = ctx
+        .terminal
+        .as_ref()
+        .and_then(|t| t.repl.as_ref())
+        .filter(|_| is_terminal_mode);
+
+    let default_shell = if is_wsl { "bash" } else { "zsh" };
+    let shell_type = match (remote, container) {
+        (Some(remote), _) => remote.shell.as_deref().unwrap_or("bash"),
+        (None, Some(container)) => container.shell.as_deref().unwrap_or("sh"),
+        (None, None) => ctx
             .terminal
             .as_ref()
             .and_then(|t| t.shell_type.as_deref())
-            .unwrap_or(default_shell);
+            .unwrap_or(default_shell),
+    };
 
-        if is_wsl {
+    let mut system_prompt = if fix_notes.is_some() {
+        let mut prompt = FIX_SYSTEM_PROMPT_TEMPLATE.replace("{shell_type}", shell_type);
+        if let Some(remote) = remote {
+            prompt.push_str(&REMOTE_FIX_RULE.replace("{target}", &scrub(&remote.target())));
+        }
+        if let Some(repl) = repl {
+            prompt.push_str(
+                &REPL_FIX_RULE
+                    .replace("{language}", repl.kind.language())
+                    .replace("{program}", &scrub(&repl.program)),
+            );
+        }
+        if let Some(container) = container {
+            prompt.push_str(
+                &CONTAINER_FIX_RULE
+                    .replace("{target}", &scrub(&container.target()))
+                    .replace(
+                        "{distro}",
+                        container.distro_label().unwrap_or("unknown distribution"),
+                    ),
+            );
+        }
+        prompt
+    } else if is_terminal_mode {
+        if let Some(repl) = repl {
+            REPL_SYSTEM_PROMPT_TEMPLATE
+                .replace("{language}", repl.kind.language())
+                .replace("{program}", &scrub(&repl.program))
+                .replace("{rules}", repl.kind.input_rules())
+        } else if let Some(remote) = remote {
+            REMOTE_TERMINAL_SYSTEM_PROMPT_TEMPLATE
+                .replace("{os}", remote.os.as_deref().unwrap_or("Unix-like"))
+                .replace("{target}", &scrub(&remote.target()))
+                .replace("{shell_type}", shell_type)
+        } else if let Some(container) = container {
+            CONTAINER_TERMINAL_SYSTEM_PROMPT_TEMPLATE
+                .replace("{target}", &scrub(&container.target()))
+                .replace(
+                    "{distro}",
+                    container.distro_label().unwrap_or("unknown distribution"),
+                )
+                .replace("{shell_type}", shell_type)
+        } else if is_wsl {
             #[cfg(target_os = "windows")]
             { WSL_TERMINAL_SYSTEM_PROMPT_TEMPLATE.replace("{shell_type}", shell_type) }
             #[cfg(not(target_os = "windows"))]
@@ -269,21 +543,49 @@ pub async fn stream_ai_response(
         ASSISTANT_SYSTEM_PROMPT.to_string()
     };
 
+    // Installed tools of the local shell; the inventory does not describe a WSL
+    // shell, a remote host or a container, and a REPL does not run programs
+    if is_terminal_mode && !is_wsl && remote.is_none() && container.is_none() && repl.is_none() {
+        match ctx.terminal.as_ref().and_then(|t| t.tools.as_ref()) {
+            Some(tools) => system_prompt.push_str(&tools.describe()),
+            None => {
+                #[cfg(target_os = "macos")]
+                system_prompt.push_str(POSIX_TOOLS_RULE);
+            }
+        }
+    }
+
     eprintln!(
-        "[ai] mode={} wsl={}",
-        if is_terminal_mode {
+        "[ai] mode={} wsl={} remote={} container={}",
+        if fix_notes.is_some() {
+            "fix"
+        } else if repl.is_some() {
+            "repl"
+        } else if is_terminal_mode {
             "terminal"
         } else {
             "assistant"
         },
-        is_wsl
+        is_wsl,
+        remote.is_some(),
+        container.is_some()
     );
 
-    // 4. Build the user message with context (follow-ups omit terminal context)
+    // Build the user message with context (follow-ups omit terminal context)
     let is_follow_up = !history.is_empty();
-    let user_message = build_user_message(&query, &ctx, is_follow_up, &model);
+    let (user_message, has_untrusted) = match fix_notes {
+        Some(notes) => build_fix_message(notes, &ctx, model),
+        None => build_user_message(query, &ctx, is_follow_up, model),
+    };
+    if has_untrusted {
+        eprintln!("[ai] quarantined instruction-like text from captured context");
+        system_prompt.push_str(crate::terminal::injection::UNTRUSTED_CONTEXT_RULE);
+    }
+    if crate::terminal::filter::has_placeholders(&user_message) {
+        system_prompt.push_str(crate::terminal::filter::SECRET_PLACEHOLDER_RULE);
+    }
 
-    // 5. Build messages array: system prompt + history (pre-capped by frontend via turnLimit) + current user msg
+    // Build messages array: system prompt + history (pre-capped by frontend via turnLimit) + current user msg
     let mut messages: Vec<serde_json::Value> = Vec::new();
 
     messages.push(serde_json::json!({
@@ -292,10 +594,10 @@ pub async fn stream_ai_response(
     }));
 
     // Frontend sends pre-capped history via turnLimit -- no Rust-side capping needed
-    for msg in &history {
+    for msg in history {
         messages.push(serde_json::json!({
             "role": msg.role,
-            "content": msg.content
+            "content": scrub(&msg.content)
         }));
     }
 
@@ -304,9 +606,80 @@ pub async fn stream_ai_response(
         "content": user_message
     }));
 
+    PreparedRequest {
+        ctx,
+        system_prompt,
+        messages,
+    }
+}
+
+/// Stream AI response tokens to the frontend via a Tauri IPC Channel.
+///
+/// - Accepts a `provider` parameter to dispatch to the correct streaming adapter.
+/// - Reads the API key from the OS Keychain using the provider's account name.
+/// - Determines terminal vs assistant mode from context_json.
+/// - Builds the system prompt (two modes) and user message with context.
+/// - Includes session history (pre-capped by frontend via configurable turnLimit) in the messages array.
+/// - Dispatches to the appropriate adapter based on provider.adapter_kind().
+#[tauri::command]
+pub async fn stream_ai_response(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, crate::state::AppState>,
+    provider: Provider,
+    query: String,
+    model: String,
+    context_json: String,
+    history: Vec<ChatMessage>,
+    on_token: tauri::ipc::Channel<String>,
+) -> Result<(), String> {
+    eprintln!(
+        "[ai] stream_ai_response called, provider={}, model={}",
+        provider.display_name(),
+        model
+    );
+
+    // Administrator policy may restrict providers and models
+    super::policy::policy().check_request(&provider, &model)?;
+
+    // 1. Resolve API URL and key based on provider type
+    let api_url = endpoint_url(&app_handle, &provider);
+    let api_key = if provider.is_local() {
+        String::new()
+    } else {
+        let entry = keyring::Entry::new(SERVICE, provider.keychain_account())
+            .map_err(|e| format!("Keyring error: {}", e))?;
+        entry.get_password().map_err(|_| {
+            format!(
+                "No {} API key configured. Open Settings to add one.",
+                provider.display_name()
+            )
+        })?
+    };
+
+    // 2. Build the system prompt and messages exactly as they will be sent
+    let PreparedRequest { ctx, system_prompt, messages } =
+        prepare_request(&query, &context_json, &history, &model);
+
+    // Remember where this request came from for the audit record of the resulting command
+    if let Ok(mut audit) = state.audit.lock() {
+        audit.request = super::audit::AuditRequestContext {
+            app: ctx.app_name.clone(),
+            cwd: ctx.terminal.as_ref().and_then(|t| t.cwd.clone()),
+            provider: Some(provider.display_name().to_string()),
+            model: Some(model.clone()),
+            query: Some(query.clone()),
+            cloud: ctx.terminal.as_ref().and_then(|t| t.cloud.clone()),
+            repl: ctx
+                .terminal
+                .as_ref()
+                .and_then(|t| t.repl.as_ref())
+                .map(|r| r.kind),
+        };
+    }
+
     eprintln!("[ai] messages count={}", messages.len());
 
-    // 6. Dispatch to the correct adapter based on provider
+    // 3. Dispatch to the correct adapter based on provider
     let timeout = tokio::time::Duration::from_secs(provider.default_timeout_secs());
 
     let token_usage = match provider.adapter_kind() {
@@ -359,3 +732,95 @@ pub async fn stream_ai_response(
 
     Ok(())
 }
+
+/// What `stream_ai_response` would send for a query, without sending it.
+#[derive(Serialize)]
+pub struct RequestPreview {
+    pub provider: String,
+    pub model: String,
+    /// Request URL; for Gemini the API key query parameter is omitted
+    pub endpoint: String,
+    pub api_key_configured: bool,
+    pub system_prompt: String,
+    /// History as sent, after redaction
+    pub history: Vec<ChatMessage>,
+    pub user_message: String,
+    /// Placeholders substituted into the outgoing text (values are never included)
+    pub redactions: Vec<crate::terminal::filter::Redaction>,
+    pub estimated_input_tokens: u32,
+    /// Input cost in USD, when pricing for the model is known
+    pub estimated_cost: Option<f64>,
+}
+
+/// Run the prompt-building pipeline of `stream_ai_response` and return the
+/// result instead of making the HTTP call. Used by the prompt inspector.
+#[tauri::command]
+pub fn preview_request(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, crate::state::AppState>,
+    provider: Provider,
+    query: String,
+    model: String,
+    context_json: String,
+    history: Vec<ChatMessage>,
+) -> Result<RequestPreview, String> {
+    super::policy::policy().check_request(&provider, &model)?;
+
+    let mut endpoint = endpoint_url(&app_handle, &provider);
+    if provider.adapter_kind() == AdapterKind::Gemini {
+        endpoint = format!("{}{}:streamGenerateContent?alt=sse", endpoint, model);
+    }
+    let api_key_configured = provider.is_local()
+        || keyring::Entry::new(SERVICE, provider.keychain_account())
+            .and_then(|entry| entry.get_password())
+            .is_ok();
+
+    let PreparedRequest { system_prompt, mut messages, .. } =
+        prepare_request(&query, &context_json, &history, &model);
+
+    let content = |m: &serde_json::Value| m["content"].as_str().unwrap_or_default().to_string();
+    let user_message = messages.pop().map(|m| content(&m)).unwrap_or_default();
+    let history: Vec<ChatMessage> = messages
+        .iter()
+        .skip(1)
+        .map(|m| ChatMessage {
+            role: m["role"].as_str().unwrap_or_default().to_string(),
+            content: content(m),
+        })
+        .collect();
+
+    let mut outgoing = system_prompt.clone();
+    for msg in &history {
+        outgoing.push('\n');
+        outgoing.push_str(&msg.content);
+    }
+    outgoing.push('\n');
+    outgoing.push_str(&user_message);
+
+    let estimated_input_tokens = crate::terminal::context::estimate_tokens(&outgoing);
+    let pricing = super::models::curated_models_pricing()
+        .get(model.as_str())
+        .copied()
+        .or_else(|| {
+            state
+                .openrouter_pricing
+                .lock()
+                .ok()
+                .and_then(|p| p.get(model.as_str()).copied())
+        });
+    let estimated_cost =
+        pricing.map(|(input_price, _)| estimated_input_tokens as f64 * input_price / 1_000_000.0);
+
+    Ok(RequestPreview {
+        provider: provider.display_name().to_string(),
+        model,
+        endpoint,
+        api_key_configured,
+        redactions: crate::terminal::filter::list_redactions(&outgoing),
+        system_prompt,
+        history,
+        user_message,
+        estimated_input_tokens,
+        estimated_cost,
+    })
+}
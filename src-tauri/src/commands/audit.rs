@@ -0,0 +1,450 @@
+//! Append-only audit log of commands cmd-k put into a terminal.
+//!
+//! Every auto-paste, confirm, and clipboard-only fallback is written as one JSON
+//! line to `<app_data_dir>/audit/audit.jsonl`. Each record carries the SHA-256 of
+//! the previous record (`prev_hash`) and of itself (`hash`), so deleting or
+//! editing a line breaks the chain and is detectable on export.
+//!
+//! The file rotates at MAX_AUDIT_FILE_BYTES; the chain continues across rotated
+//! files (`audit.1.jsonl` is older than `audit.jsonl`). When the oldest file is
+//! dropped, its last hash is kept in `audit.anchor` so the head of the retained
+//! chain is still checked.
+
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager};
+
+use crate::state::AppState;
+
+/// Active audit file name inside the audit directory.
+const AUDIT_FILE_NAME: &str = "audit.jsonl";
+
+/// Rotate the active file once it grows past this size.
+const MAX_AUDIT_FILE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Number of rotated files kept (`audit.1.jsonl` .. `audit.N.jsonl`).
+const MAX_ROTATED_FILES: usize = 5;
+
+/// Last hash of the newest file dropped by rotation.
+const ANCHOR_FILE_NAME: &str = "audit.anchor";
+
+/// `prev_hash` of the very first record in a fresh log.
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// How the command reached the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    /// Pasted into the terminal automatically (not yet executed)
+    AutoPasted,
+    /// User pressed Enter in the overlay and cmd-k sent Return to the terminal
+    Confirmed,
+    /// Auto-paste unavailable; command was only placed on the clipboard
+    Clipboard,
+}
+
+/// Context of the AI request that produced the command being audited.
+/// Set by stream_ai_response from the request context, provider, model and query.
+#[derive(Debug, Clone, Default)]
+pub struct AuditRequestContext {
+    pub app: Option<String>,
+    pub cwd: Option<String>,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub query: Option<String>,
+    /// Cloud CLI targets of the terminal, so the risk score matches what the user saw.
+    pub cloud: Option<crate::terminal::cloud::CloudContext>,
+    /// REPL the command was written for, so it is scored by that language's rules.
+    pub repl: Option<crate::terminal::repl::ReplKind>,
+}
+
+/// Session state for the audit chain, held in AppState.
+#[derive(Debug, Default)]
+pub struct AuditState {
+    /// Hash of the last written record. None until loaded from disk on first write.
+    last_hash: Option<String>,
+    /// Sequence number of the last written record.
+    last_seq: u64,
+    /// Context of the most recent AI request.
+    pub request: AuditRequestContext,
+    /// The last pasted command, so confirm_terminal_command can audit what it executed.
+    pub pending_command: Option<String>,
+}
+
+/// The hashed body of an audit record (everything except `hash`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub seq: u64,
+    /// Unix milliseconds
+    pub timestamp: u64,
+    pub window_key: Option<String>,
+    pub app: Option<String>,
+    pub cwd: Option<String>,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub query: Option<String>,
+    pub command: String,
+    /// Safety severity at the time of the action ("none", "low", "medium", "high", "critical")
+    pub risk: String,
+    pub action: AuditAction,
+    pub prev_hash: String,
+}
+
+/// One line of the audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    #[serde(flatten)]
+    pub entry: AuditEntry,
+    pub hash: String,
+}
+
+/// Result of export_audit_log.
+#[derive(Debug, Serialize)]
+pub struct AuditExport {
+    /// Matching records as JSONL, oldest first
+    pub jsonl: String,
+    pub count: usize,
+    /// False if any record in the retained files fails hash verification or
+    /// does not link to its predecessor.
+    pub chain_intact: bool,
+}
+
+/// Hex-encoded SHA-256 over the previous hash and the serialized entry.
+fn compute_hash(entry: &AuditEntry) -> String {
+    let body = serde_json::to_string(entry).unwrap_or_default();
+    let mut hasher = Sha256::new();
+    hasher.update(entry.prev_hash.as_bytes());
+    hasher.update(body.as_bytes());
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Seal an entry into a record by computing its hash.
+fn seal(entry: AuditEntry) -> AuditRecord {
+    let hash = compute_hash(&entry);
+    AuditRecord { entry, hash }
+}
+
+/// Check that every record hashes correctly and links to the one before it.
+/// The first record must link to `anchor`.
+fn verify_chain(records: &[AuditRecord], anchor: &str) -> bool {
+    let mut prev = anchor;
+    for record in records {
+        if compute_hash(&record.entry) != record.hash || record.entry.prev_hash != prev {
+            return false;
+        }
+        prev = &record.hash;
+    }
+    true
+}
+
+/// Hash the oldest retained record must link to: the last hash of the files
+/// rotated out, or GENESIS_HASH if none were.
+fn chain_anchor(dir: &Path) -> String {
+    std::fs::read_to_string(dir.join(ANCHOR_FILE_NAME))
+        .map(|s| s.trim().to_string())
+        .unwrap_or_else(|_| GENESIS_HASH.to_string())
+}
+
+/// Audit files in chronological order (oldest rotated file first, active file last).
+fn audit_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = (1..=MAX_ROTATED_FILES)
+        .rev()
+        .map(|n| dir.join(format!("audit.{}.jsonl", n)))
+        .collect();
+    files.push(dir.join(AUDIT_FILE_NAME));
+    files.into_iter().filter(|p| p.exists()).collect()
+}
+
+/// Read all records from the given files. Unparseable lines are returned as None
+/// so the caller can treat them as chain breaks.
+fn read_records(files: &[PathBuf]) -> Vec<Option<AuditRecord>> {
+    let mut out = Vec::new();
+    for path in files {
+        let Ok(file) = std::fs::File::open(path) else {
+            continue;
+        };
+        for line in std::io::BufReader::new(file).lines().map_while(Result::ok) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            out.push(serde_json::from_str::<AuditRecord>(&line).ok());
+        }
+    }
+    out
+}
+
+/// Shift audit.N.jsonl -> audit.N+1.jsonl and move the active file to audit.1.jsonl
+/// when it has grown past MAX_AUDIT_FILE_BYTES.
+fn rotate_if_needed(dir: &Path) -> Result<(), String> {
+    let active = dir.join(AUDIT_FILE_NAME);
+    let size = std::fs::metadata(&active).map(|m| m.len()).unwrap_or(0);
+    if size < MAX_AUDIT_FILE_BYTES {
+        return Ok(());
+    }
+
+    let oldest = dir.join(format!("audit.{}.jsonl", MAX_ROTATED_FILES));
+    if let Some(last) = read_records(std::slice::from_ref(&oldest)).into_iter().flatten().last() {
+        std::fs::write(dir.join(ANCHOR_FILE_NAME), &last.hash)
+            .map_err(|e| format!("audit rotation failed: {}", e))?;
+    }
+    let _ = std::fs::remove_file(&oldest);
+    for n in (1..MAX_ROTATED_FILES).rev() {
+        let from = dir.join(format!("audit.{}.jsonl", n));
+        if from.exists() {
+            std::fs::rename(&from, dir.join(format!("audit.{}.jsonl", n + 1)))
+                .map_err(|e| format!("audit rotation failed: {}", e))?;
+        }
+    }
+    std::fs::rename(&active, dir.join("audit.1.jsonl"))
+        .map_err(|e| format!("audit rotation failed: {}", e))?;
+    eprintln!("[audit] rotated audit log ({} bytes)", size);
+    Ok(())
+}
+
+/// Append one record to the log in `dir`, chaining it to the previous record.
+fn append(dir: &Path, audit: &mut AuditState, mut entry: AuditEntry) -> Result<AuditRecord, String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("failed to create audit dir: {}", e))?;
+
+    // Resume the chain from disk on the first write of this session
+    if audit.last_hash.is_none() {
+        let last = read_records(&audit_files(dir)).into_iter().flatten().last();
+        audit.last_seq = last.as_ref().map(|r| r.entry.seq).unwrap_or(0);
+        audit.last_hash = Some(last.map(|r| r.hash).unwrap_or_else(|| GENESIS_HASH.to_string()));
+    }
+
+    rotate_if_needed(dir)?;
+
+    entry.seq = audit.last_seq + 1;
+    entry.prev_hash = audit.last_hash.clone().unwrap_or_else(|| GENESIS_HASH.to_string());
+    let record = seal(entry);
+    let line = serde_json::to_string(&record).map_err(|e| format!("failed to serialize audit record: {}", e))?;
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(AUDIT_FILE_NAME))
+        .map_err(|e| format!("failed to open audit log: {}", e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("failed to write audit log: {}", e))?;
+
+    audit.last_seq = record.entry.seq;
+    audit.last_hash = Some(record.hash.clone());
+    Ok(record)
+}
+
+/// Resolve the audit directory inside the app data dir.
+fn audit_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|d| d.join("audit"))
+        .map_err(|e| format!("no app data dir: {}", e))
+}
+
+/// Write an audit record for a command that reached the terminal (or clipboard).
+///
+/// Failures are logged, never propagated: auditing must not block paste.
+pub fn record(app: &AppHandle, command: &str, action: AuditAction) {
+    let Some(state) = app.try_state::<AppState>() else {
+        eprintln!("[audit] AppState not found");
+        return;
+    };
+    let dir = match audit_dir(app) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("[audit] {}", e);
+            return;
+        }
+    };
+    let window_key = state.current_window_key.lock().ok().and_then(|g| g.clone());
+    let Ok(mut audit) = state.audit.lock() else {
+        eprintln!("[audit] audit mutex poisoned");
+        return;
+    };
+    let request = audit.request.clone();
+    let actx = super::safety::AnalysisContext {
+        cloud: request.cloud,
+        production_patterns: super::safety::production_patterns(app),
+        cwd: None,
+        search_path: None,
+        aliases: None,
+        repl: request.repl,
+    };
+    let report = super::safety::analyze(command, &actx);
+    let risk = serde_json::to_value(report.severity)
+        .ok()
+        .and_then(|v| v.as_str().map(String::from))
+        .unwrap_or_default();
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let entry = AuditEntry {
+        seq: 0,
+        timestamp,
+        window_key,
+        app: request.app,
+        cwd: request.cwd,
+        provider: request.provider,
+        model: request.model,
+        query: request.query,
+        command: command.to_string(),
+        risk,
+        action,
+        prev_hash: String::new(),
+    };
+    match append(&dir, &mut audit, entry) {
+        Ok(r) => eprintln!("[audit] recorded seq={} action={:?}", r.entry.seq, action),
+        Err(e) => eprintln!("[audit] {}", e),
+    }
+}
+
+/// Collect records in `dir` whose timestamp falls within [from, to] (unix ms, inclusive).
+fn export_from(dir: &Path, from: Option<u64>, to: Option<u64>) -> AuditExport {
+    let all = read_records(&audit_files(dir));
+    let parsed: Vec<AuditRecord> = all.iter().flatten().cloned().collect();
+    let chain_intact = parsed.len() == all.len() && verify_chain(&parsed, &chain_anchor(dir));
+
+    let lines: Vec<String> = parsed
+        .iter()
+        .filter(|r| from.is_none_or(|f| r.entry.timestamp >= f))
+        .filter(|r| to.is_none_or(|t| r.entry.timestamp <= t))
+        .filter_map(|r| serde_json::to_string(r).ok())
+        .collect();
+
+    AuditExport {
+        count: lines.len(),
+        jsonl: lines.join("\n"),
+        chain_intact,
+    }
+}
+
+/// Export audit records as JSONL, optionally filtered by date range (unix ms, inclusive).
+///
+/// Also verifies the hash chain across all retained files and reports whether it is intact.
+#[tauri::command]
+pub fn export_audit_log(app: AppHandle, from: Option<u64>, to: Option<u64>) -> Result<AuditExport, String> {
+    let dir = audit_dir(&app)?;
+    let export = export_from(&dir, from, to);
+    eprintln!(
+        "[audit] export: {} records, chain_intact={}",
+        export.count, export.chain_intact
+    );
+    Ok(export)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cmdk-audit-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn entry(command: &str, timestamp: u64) -> AuditEntry {
+        AuditEntry {
+            seq: 0,
+            timestamp,
+            window_key: Some("kitty:1234".to_string()),
+            app: Some("kitty".to_string()),
+            cwd: Some("/home/u".to_string()),
+            provider: Some("OpenAI".to_string()),
+            model: Some("gpt-4o".to_string()),
+            query: Some("list files".to_string()),
+            command: command.to_string(),
+            risk: "none".to_string(),
+            action: AuditAction::AutoPasted,
+            prev_hash: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_records_are_chained() {
+        let dir = temp_dir("chain");
+        let mut state = AuditState::default();
+        let first = append(&dir, &mut state, entry("ls", 1)).unwrap();
+        let second = append(&dir, &mut state, entry("pwd", 2)).unwrap();
+        assert_eq!(first.entry.prev_hash, GENESIS_HASH);
+        assert_eq!(second.entry.prev_hash, first.hash);
+        assert_eq!(second.entry.seq, 2);
+        assert!(export_from(&dir, None, None).chain_intact);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_chain_resumes_from_disk() {
+        let dir = temp_dir("resume");
+        let first = append(&dir, &mut AuditState::default(), entry("ls", 1)).unwrap();
+        // New session: state starts empty, chain must continue from the file
+        let second = append(&dir, &mut AuditState::default(), entry("pwd", 2)).unwrap();
+        assert_eq!(second.entry.prev_hash, first.hash);
+        assert_eq!(second.entry.seq, 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_tampering_detected() {
+        let dir = temp_dir("tamper");
+        let mut state = AuditState::default();
+        append(&dir, &mut state, entry("ls", 1)).unwrap();
+        append(&dir, &mut state, entry("pwd", 2)).unwrap();
+        let path = dir.join(AUDIT_FILE_NAME);
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, content.replace("\"pwd\"", "\"rm -rf /\"")).unwrap();
+        assert!(!export_from(&dir, None, None).chain_intact);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_deleted_line_detected() {
+        let dir = temp_dir("delete");
+        let mut state = AuditState::default();
+        for (i, cmd) in ["ls", "pwd", "whoami"].iter().enumerate() {
+            append(&dir, &mut state, entry(cmd, i as u64)).unwrap();
+        }
+        let path = dir.join(AUDIT_FILE_NAME);
+        let content = std::fs::read_to_string(&path).unwrap();
+        let kept: Vec<&str> = content.lines().enumerate().filter(|(i, _)| *i != 1).map(|(_, l)| l).collect();
+        std::fs::write(&path, kept.join("\n")).unwrap();
+        assert!(!export_from(&dir, None, None).chain_intact);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_deleted_head_detected() {
+        let dir = temp_dir("head");
+        let mut state = AuditState::default();
+        let first = append(&dir, &mut state, entry("ls", 1)).unwrap();
+        append(&dir, &mut state, entry("pwd", 2)).unwrap();
+        let path = dir.join(AUDIT_FILE_NAME);
+        let content = std::fs::read_to_string(&path).unwrap();
+        let rest: Vec<&str> = content.lines().skip(1).collect();
+        std::fs::write(&path, rest.join("\n")).unwrap();
+        assert!(!export_from(&dir, None, None).chain_intact);
+        // The same head is legitimate once a rotated-out file anchors it
+        std::fs::write(dir.join(ANCHOR_FILE_NAME), &first.hash).unwrap();
+        assert!(export_from(&dir, None, None).chain_intact);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_export_date_filter() {
+        let dir = temp_dir("filter");
+        let mut state = AuditState::default();
+        for ts in [100, 200, 300] {
+            append(&dir, &mut state, entry("ls", ts)).unwrap();
+        }
+        let export = export_from(&dir, Some(150), Some(300));
+        assert_eq!(export.count, 2);
+        assert!(export.chain_intact);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
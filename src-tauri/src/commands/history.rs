@@ -93,6 +93,12 @@ pub fn add_history_entry(
         entries.pop_front(); // evict oldest entry
     }
 
+    // Snapshots are captured context too: keep them under the active privacy level
+    let terminal_context = terminal_context.map(|mut snap| {
+        snap.cwd = snap.cwd.map(|c| crate::terminal::filter::redact_pii(&c));
+        snap.visible_output = snap.visible_output.map(|o| crate::terminal::filter::redact_pii(&o));
+        snap
+    });
     let entry = HistoryEntry::new(query, response, terminal_context, is_error);
     entries.push_back(entry);
     eprintln!(
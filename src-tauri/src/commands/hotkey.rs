@@ -82,14 +82,33 @@ fn get_foreground_hwnd() -> Option<isize> {
     None
 }
 
-/// Capture the PID of the active X11 window using EWMH properties.
+/// Capture the PID of the active window.
 ///
 /// This MUST be called BEFORE showing the overlay because after the overlay
 /// appears, the active window changes to our own overlay.
-/// Fails gracefully on pure Wayland (no DISPLAY set) -- users should set
-/// GDK_BACKEND=x11 for XWayland support.
+/// On Wayland the compositor is asked over its IPC (sway, Hyprland, KWin,
+/// GNOME Shell); when that fails, XWayland's EWMH properties are the fallback.
 #[cfg(target_os = "linux")]
 fn get_active_window_pid() -> Option<i32> {
+    use crate::terminal::wayland::{self, DisplayServer};
+
+    if let DisplayServer::Wayland(compositor) = wayland::detect_display_server() {
+        match wayland::active_window_pid(&compositor) {
+            Ok(pid) => {
+                eprintln!("[hotkey] Wayland ({:?}) active window PID {}", compositor, pid);
+                return Some(pid);
+            }
+            Err(e) => eprintln!("[hotkey] Wayland ({:?}) active window lookup failed: {}", compositor, e),
+        }
+    }
+    get_active_window_pid_x11()
+}
+
+/// Capture the PID of the active X11 window using EWMH properties.
+///
+/// Fails gracefully on pure Wayland (no DISPLAY set).
+#[cfg(target_os = "linux")]
+fn get_active_window_pid_x11() -> Option<i32> {
     use x11rb::connection::Connection;
     use x11rb::protocol::xproto::*;
 
@@ -167,7 +186,18 @@ fn get_active_window_pid() -> Option<i32> {
     None
 }
 
-/// Linux-specific window key computation from X11 active window PID.
+/// Window key for a terminal shell. When the shell runs tmux, screen or zellij the
+/// active pane is appended ("kitty:4100:tmux:main:%3"), so each pane keeps its
+/// own history.
+#[cfg(not(target_os = "windows"))]
+fn shell_window_key(app: &str, shell_pid: i32) -> String {
+    match terminal::multiplexer::detect(shell_pid) {
+        Some(pane) => format!("{}:{}:{}", app, shell_pid, pane.window_key()),
+        None => format!("{}:{}", app, shell_pid),
+    }
+}
+
+/// Linux-specific window key computation from the active window PID.
 ///
 /// Derives exe name from PID via /proc, then resolves shell child PID for
 /// terminals and IDEs. Key format: "exe_name:shell_pid" or "exe_name:pid" fallback.
@@ -180,7 +210,7 @@ fn compute_window_key_linux(pid: i32) -> String {
 
     let key = if is_terminal || is_ide {
         match terminal::process::find_shell_pid(pid, None, None) {
-            Some(shell_pid) => format!("{}:{}", exe_str, shell_pid),
+            Some(shell_pid) => shell_window_key(exe_str, shell_pid),
             None => format!("{}:{}", exe_str, pid),
         }
     } else {
@@ -267,7 +297,8 @@ pub fn restore_focus(_target_hwnd: isize) -> bool {
 ///
 /// Key format:
 /// - Terminals and IDEs with integrated terminals: "bundle_id:shell_pid"
-///   (gives each terminal tab its own history bucket)
+///   (gives each terminal tab its own history bucket), plus the multiplexer pane
+///   when the shell runs tmux, screen or zellij
 /// - Other apps (Finder, Safari, etc.): "bundle_id:app_pid"
 ///   (gives each app its own per-process history bucket)
 ///
@@ -289,6 +320,9 @@ fn compute_window_key(pid: i32, focused_cwd: Option<String>) -> String {
         #[cfg(not(target_os = "windows"))]
         let shell_pid = terminal::process::find_shell_pid(pid, focused_cwd.as_deref(), None);
         match shell_pid {
+            #[cfg(not(target_os = "windows"))]
+            Some(shell_pid) => shell_window_key(bundle_str, shell_pid),
+            #[cfg(target_os = "windows")]
             Some(shell_pid) => format!("{}:{}", bundle_str, shell_pid),
             None => format!("{}:{}", bundle_str, pid),
         }
@@ -519,7 +553,7 @@ fn hotkey_handler_inner(app_handle: &AppHandle) {
                         }
                     }
 
-                    // Linux: capture active window PID via X11, compute window key.
+                    // Linux: capture active window PID via X11 or the Wayland compositor, compute window key.
                     #[cfg(target_os = "linux")]
                     {
                         let pid = get_active_window_pid();
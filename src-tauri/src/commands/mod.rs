@@ -1,14 +1,18 @@
 pub mod ai;
+pub mod audit;
 pub mod history;
 pub mod hotkey;
 pub mod keychain;
 pub mod paste;
 pub mod permissions;
+pub mod policy;
 pub mod providers;
 pub mod safety;
 pub mod terminal;
 pub mod tray;
 pub mod updater;
 pub mod window;
+#[cfg(target_os = "linux")]
+pub mod wayland_input;
 pub mod models;
 pub mod usage;
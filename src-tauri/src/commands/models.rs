@@ -419,6 +419,10 @@ pub async fn fetch_models(
         }
     }
 
+    // Hide models the administrator policy does not allow
+    let policy = super::policy::policy();
+    result.retain(|m| policy.model_allowed(&m.id));
+
     Ok(result)
 }
 
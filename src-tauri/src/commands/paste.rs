@@ -1,5 +1,4 @@
 use tauri::AppHandle;
-#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
 use tauri::Manager;
 
 #[cfg(target_os = "macos")]
@@ -8,10 +7,14 @@ use tauri_nspanel::ManagerExt;
 #[cfg(target_os = "windows")]
 use crate::commands::hotkey::restore_focus;
 
-#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+use crate::commands::audit::{self, AuditAction};
 use crate::state::AppState;
 #[cfg(target_os = "macos")]
 use crate::terminal::detect::get_bundle_id;
+#[cfg(target_os = "linux")]
+use crate::commands::wayland_input::{self, Chord, InputBackend};
+#[cfg(target_os = "linux")]
+use crate::terminal::wayland::{self, detect_display_server, Compositor, DisplayServer};
 
 // ---------------------------------------------------------------------------
 // CGEventPost FFI -- post synthetic keyboard events via Accessibility permission
@@ -157,41 +160,9 @@ fn write_to_clipboard(command: &str) {
 }
 
 // ---------------------------------------------------------------------------
-// Linux: display server detection, clipboard, xdotool keystroke simulation
+// Linux: clipboard, xdotool / Wayland keystroke simulation
 // ---------------------------------------------------------------------------
 
-#[cfg(target_os = "linux")]
-#[derive(Debug)]
-enum DisplayServer {
-    X11,
-    Wayland,
-    Unknown,
-}
-
-#[cfg(target_os = "linux")]
-fn detect_display_server() -> DisplayServer {
-    // GDK_BACKEND=x11 overrides everything (XWayland path)
-    if std::env::var("GDK_BACKEND")
-        .map(|v| v == "x11")
-        .unwrap_or(false)
-    {
-        return DisplayServer::X11;
-    }
-    // Check for Wayland indicators
-    let wayland_display = std::env::var("WAYLAND_DISPLAY").is_ok();
-    let session_wayland = std::env::var("XDG_SESSION_TYPE")
-        .map(|v| v == "wayland")
-        .unwrap_or(false);
-    if wayland_display && session_wayland {
-        return DisplayServer::Wayland;
-    }
-    // Default to X11 if DISPLAY is set
-    if std::env::var("DISPLAY").is_ok() {
-        return DisplayServer::X11;
-    }
-    DisplayServer::Unknown
-}
-
 /// Write command to system clipboard on Linux via xclip or wl-copy.
 #[cfg(target_os = "linux")]
 #[allow(dead_code)]
@@ -213,7 +184,7 @@ fn write_to_clipboard_linux(
     use std::io::Write;
 
     let (cmd, args): (&str, Vec<&str>) = match display {
-        DisplayServer::Wayland if tools.has_wl_copy => ("wl-copy", vec![]),
+        DisplayServer::Wayland(_) if tools.has_wl_copy => ("wl-copy", vec![]),
         _ if tools.has_xclip => ("xclip", vec!["-selection", "clipboard"]),
         _ => {
             eprintln!("[paste] no clipboard tool available (install xclip or wl-copy)");
@@ -247,6 +218,7 @@ fn write_to_clipboard(_command: &str) {
 
 /// Paste `command` into the terminal that was frontmost before the overlay opened.
 ///
+/// - Inside tmux, screen or zellij: typed into the active pane by the multiplexer.
 /// - iTerm2: uses native `write text` via osascript (direct AppleEvent, no System Events).
 ///   Falls back to CGEventPost Cmd+V if write text fails.
 /// - All other terminals: activates via simplified AppleScript, then CGEventPost
@@ -254,12 +226,52 @@ fn write_to_clipboard(_command: &str) {
 ///
 /// Returns `Ok("auto")` when paste was automatic, `Ok("clipboard_hint")` when
 /// the command was copied to clipboard and the user needs to paste manually.
+/// Either outcome is written to the audit log.
+///
+/// Secret placeholders (`<SECRET_n:kind>`) are substituted with the real values
+/// only here, at the last moment; the audit log keeps the placeholder form.
 #[tauri::command]
 pub fn paste_to_terminal(app: AppHandle, command: String) -> Result<String, String> {
+    let restored = crate::terminal::filter::restore_secrets(&command);
+    let result = paste_to_terminal_inner(app.clone(), restored);
+    if let Ok(mode) = &result {
+        let action = if mode == "clipboard_hint" {
+            AuditAction::Clipboard
+        } else {
+            AuditAction::AutoPasted
+        };
+        audit::record(&app, &command, action);
+        if let Some(state) = app.try_state::<AppState>() {
+            if let Ok(mut a) = state.audit.lock() {
+                a.pending_command = Some(command);
+            }
+        }
+    }
+    result
+}
+
+/// The multiplexer pane detected for this overlay invocation, if any.
+fn multiplexer_pane(app: &AppHandle) -> Option<crate::terminal::multiplexer::MultiplexerPane> {
+    app.try_state::<AppState>()?.multiplexer_pane.lock().ok()?.clone()
+}
+
+fn paste_to_terminal_inner(app: AppHandle, command: String) -> Result<String, String> {
+    // Multiplexer: type into the pane the context came from, whichever pane has
+    // focus now. Keystroke paste remains the fallback.
+    if let Some(pane) = multiplexer_pane(&app) {
+        match pane.send_text(&command) {
+            Ok(()) => {
+                eprintln!("[paste] pasted into {} | chars={}", pane.window_key(), command.len());
+                return Ok("auto".to_string());
+            }
+            Err(e) => eprintln!("[paste] multiplexer paste failed, falling back: {}", e),
+        }
+    }
+
     // Windows: early path — does not need bundle_id (uses HWND-based approach)
     #[cfg(target_os = "windows")]
     {
-        eprintln!("[paste] paste_to_terminal called (Windows): command={:?}", command);
+        eprintln!("[paste] paste_to_terminal called (Windows): chars={}", command.len());
         paste_to_terminal_windows(&app, &command)?;
     }
 
@@ -282,8 +294,10 @@ pub fn paste_to_terminal(app: AppHandle, command: String) -> Result<String, Stri
             .ok_or_else(|| format!("could not resolve bundle ID for pid {}", pid))?;
 
         eprintln!(
-            "[paste] paste_to_terminal called: pid={}, bundle_id={}, command={:?}",
-            pid, bundle_id, command
+            "[paste] paste_to_terminal called: pid={}, bundle_id={}, chars={}",
+            pid,
+            bundle_id,
+            command.len()
         );
 
         write_to_clipboard(&command);
@@ -312,11 +326,12 @@ pub fn paste_to_terminal(app: AppHandle, command: String) -> Result<String, Stri
         };
 
         eprintln!(
-            "[paste] paste_to_terminal called (Linux): pid={}, command={:?}",
-            pid, command
+            "[paste] paste_to_terminal called (Linux): pid={}, chars={}",
+            pid,
+            command.len()
         );
 
-        return paste_to_terminal_linux(&command, pid, &state.linux_tools);
+        return paste_to_terminal_linux(&app, &command, pid, &state.linux_tools);
     }
 
     // Other platforms: not yet implemented
@@ -541,10 +556,14 @@ end tell"#
 /// 5. Wait 50ms for shell processing
 /// 6. Paste via `xdotool key ctrl+shift+v`
 ///
-/// On Wayland or without xdotool: clipboard is written, returns "clipboard_hint"
+/// On Wayland, the same Ctrl+U / Ctrl+Shift+V sequence goes through the input
+/// backend chosen for the compositor (wtype, RemoteDesktop portal, ydotool).
+///
+/// Without a way to send keys: clipboard is written, returns "clipboard_hint"
 /// so the frontend can show an inline hint.
 #[cfg(target_os = "linux")]
 fn paste_to_terminal_linux(
+    app: &AppHandle,
     command: &str,
     pid: i32,
     tools: &crate::state::LinuxToolAvailability,
@@ -600,6 +619,20 @@ fn paste_to_terminal_linux(
         );
         Ok("auto".to_string())
     } else {
+        if let Some((compositor, backend)) = wayland_backend(&display, tools) {
+            match send_chords_wayland(app, compositor, &backend, Some(pid), &[Chord::ClearLine, Chord::Paste]) {
+                Ok(()) => {
+                    eprintln!(
+                        "[paste] Linux Wayland auto-paste succeeded via {:?} | pid={} | chars={}",
+                        backend,
+                        pid,
+                        command.len()
+                    );
+                    return Ok("auto".to_string());
+                }
+                Err(e) => eprintln!("[paste] Wayland {:?} paste failed: {}", backend, e),
+            }
+        }
         // Fallback: clipboard already written, return hint
         eprintln!(
             "[paste] Linux fallback: clipboard written, showing hint | display={:?}",
@@ -609,6 +642,56 @@ fn paste_to_terminal_linux(
     }
 }
 
+/// The compositor and input backend for a Wayland session, if any backend was
+/// detected at startup.
+#[cfg(target_os = "linux")]
+fn wayland_backend<'a>(
+    display: &'a DisplayServer,
+    tools: &crate::state::LinuxToolAvailability,
+) -> Option<(&'a Compositor, InputBackend)> {
+    match display {
+        DisplayServer::Wayland(compositor) => {
+            wayland_input::choose(tools, compositor).map(|backend| (compositor, backend))
+        }
+        _ => None,
+    }
+}
+
+/// Wayland: bring the terminal forward, then send `chords` through `backend`.
+///
+/// Wayland clients cannot activate each other's windows. sway and Hyprland
+/// focus by PID over their IPC; elsewhere hiding the overlay hands focus back
+/// to the window it was opened over.
+#[cfg(target_os = "linux")]
+fn send_chords_wayland(
+    app: &AppHandle,
+    compositor: &Compositor,
+    backend: &InputBackend,
+    pid: Option<i32>,
+    chords: &[Chord],
+) -> Result<(), String> {
+    backend.prepare(app)?;
+
+    let focused = pid
+        .ok_or_else(|| "no previous app PID recorded".to_string())
+        .and_then(|pid| wayland::focus_window(compositor, pid));
+    if let Err(e) = focused {
+        eprintln!("[paste] Wayland focus by PID unavailable ({}), hiding overlay", e);
+        crate::commands::window::hide_overlay(app.clone())?;
+    }
+    // Wait for the compositor to move keyboard focus
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    for (i, chord) in chords.iter().enumerate() {
+        if i > 0 {
+            // 50ms delay for shell to process the previous chord (Ctrl+U)
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        backend.send(app, *chord)?;
+    }
+    Ok(())
+}
+
 /// Windows: paste command into the terminal via clipboard + Ctrl+V.
 ///
 /// 1. Write command to clipboard via arboard
@@ -768,10 +851,43 @@ fn send_return() -> Result<(), String> {
 /// Send a Return keystroke to the terminal that was frontmost before the overlay
 /// opened, executing whatever command is currently on the shell input line.
 ///
+/// - Inside tmux, screen or zellij: Return is sent to the active pane by the multiplexer
 /// - iTerm2: uses `write text ""` (sends newline via direct AppleEvent)
 /// - All others: activates terminal, then CGEventPost Return
+///
+/// When Return was actually sent (`Ok("auto")`), the last pasted command is
+/// written to the audit log as confirmed.
 #[tauri::command]
 pub fn confirm_terminal_command(app: AppHandle) -> Result<String, String> {
+    let result = confirm_terminal_command_inner(app.clone());
+    if matches!(result.as_deref(), Ok("auto")) {
+        let pending = app
+            .try_state::<AppState>()
+            .and_then(|state| state.audit.lock().ok().and_then(|mut a| a.pending_command.take()));
+        match pending {
+            Some(command) => audit::record(&app, &command, AuditAction::Confirmed),
+            None => eprintln!("[paste] confirm: no pasted command to audit"),
+        }
+    }
+    result
+}
+
+fn confirm_terminal_command_inner(app: AppHandle) -> Result<String, String> {
+    if super::policy::policy().disable_auto_confirm {
+        eprintln!("[paste] confirm blocked by policy");
+        return Err("auto-confirm is disabled by policy".to_string());
+    }
+
+    if let Some(pane) = multiplexer_pane(&app) {
+        match pane.send_enter() {
+            Ok(()) => {
+                eprintln!("[paste] confirmed in {}", pane.window_key());
+                return Ok("auto".to_string());
+            }
+            Err(e) => eprintln!("[paste] multiplexer confirm failed, falling back: {}", e),
+        }
+    }
+
     // Windows: early path — does not need bundle_id
     #[cfg(target_os = "windows")]
     {
@@ -813,8 +929,10 @@ pub fn confirm_terminal_command(app: AppHandle) -> Result<String, String> {
             .try_state::<AppState>()
             .ok_or_else(|| "AppState not found".to_string())?;
 
-        eprintln!("[paste] confirm_terminal_command called (Linux)");
-        return confirm_command_linux(&state.linux_tools);
+        let pid = state.previous_app_pid.lock().ok().and_then(|g| *g);
+
+        eprintln!("[paste] confirm_terminal_command called (Linux): pid={:?}", pid);
+        return confirm_command_linux(&app, pid, &state.linux_tools);
     }
 
     // Other platforms: not yet implemented
@@ -828,9 +946,12 @@ pub fn confirm_terminal_command(app: AppHandle) -> Result<String, String> {
     Ok("auto".to_string())
 }
 
-/// Linux-specific confirm implementation: xdotool Return or hint fallback.
+/// Linux-specific confirm implementation: xdotool Return, the Wayland input
+/// backend, or hint fallback.
 #[cfg(target_os = "linux")]
 fn confirm_command_linux(
+    app: &AppHandle,
+    pid: Option<i32>,
     tools: &crate::state::LinuxToolAvailability,
 ) -> Result<String, String> {
     let display = detect_display_server();
@@ -843,6 +964,15 @@ fn confirm_command_linux(
         eprintln!("[paste] Linux confirm succeeded via xdotool");
         Ok("auto".to_string())
     } else {
+        if let Some((compositor, backend)) = wayland_backend(&display, tools) {
+            match send_chords_wayland(app, compositor, &backend, pid, &[Chord::Return]) {
+                Ok(()) => {
+                    eprintln!("[paste] Linux confirm succeeded via {:?}", backend);
+                    return Ok("auto".to_string());
+                }
+                Err(e) => eprintln!("[paste] Wayland {:?} confirm failed: {}", backend, e),
+            }
+        }
         eprintln!("[paste] Linux confirm fallback: showing hint | display={:?}", display);
         Ok("confirm_hint".to_string())
     }
@@ -0,0 +1,397 @@
+//! Administrator-managed policy file.
+//!
+//! A machine-wide JSON policy deployed by IT overrides user settings:
+//! - Linux:   /etc/cmd-k/policy.json
+//! - macOS:   /Library/Application Support/cmd-k/policy.json
+//! - Windows: %ProgramData%\cmd-k\policy.json
+//!
+//! The file is read once at first use. A missing file means "unmanaged" (no
+//! restrictions). An unreadable or malformed file, including one with a
+//! redaction rule that does not compile, fails closed: only local providers, no
+//! auto-confirm, explanations forced, no updates. The parse error is shown in
+//! Settings so a typo cannot silently lift restrictions.
+
+use std::path::{Path, PathBuf};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tauri_plugin_store::StoreExt;
+
+use super::providers::Provider;
+
+/// Every provider, in the order used when picking a compliant fallback.
+const ALL_PROVIDERS: [Provider; 7] = [
+    Provider::OpenAI,
+    Provider::Anthropic,
+    Provider::Gemini,
+    Provider::XAI,
+    Provider::OpenRouter,
+    Provider::Ollama,
+    Provider::LMStudio,
+];
+
+/// Machine-wide policy as deployed by an administrator. All fields are optional.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Policy {
+    /// Provider IDs users may select (e.g. ["anthropic", "ollama"]). None = all.
+    pub allowed_providers: Option<Vec<Provider>>,
+    /// Model IDs users may select. A trailing `*` matches a prefix
+    /// (e.g. "claude-*"). None = all.
+    pub allowed_models: Option<Vec<String>>,
+    /// Only local providers (Ollama, LM Studio) may be used.
+    pub local_only: bool,
+    /// Never send Return to the terminal from the overlay; the user must run commands themselves.
+    pub disable_auto_confirm: bool,
+    /// Destructive-command detection cannot be turned off and explanations are always shown.
+    pub force_explanations: bool,
+    /// Extra regexes redacted from all captured text before it leaves the machine.
+    pub redaction_rules: Vec<String>,
+    /// `redaction_rules`, compiled when the file is parsed. Not read from the file.
+    #[serde(skip)]
+    redaction_regexes: Vec<Regex>,
+    /// Disable the background and manual update checks.
+    pub disable_updater: bool,
+    /// Set when a policy file was found. Not read from the file.
+    #[serde(skip)]
+    pub managed: bool,
+    /// Why the policy file could not be applied, when the restrictive fallback is active.
+    #[serde(skip)]
+    pub error: Option<String>,
+}
+
+/// Policy state reported to the frontend so locked settings render read-only.
+#[derive(Debug, Clone, Serialize)]
+pub struct PolicyView {
+    pub managed: bool,
+    /// Providers the user may select, after applying `local_only`. None = all.
+    pub allowed_providers: Option<Vec<Provider>>,
+    pub allowed_models: Option<Vec<String>>,
+    pub disable_auto_confirm: bool,
+    pub force_explanations: bool,
+    /// Settings store keys fixed by the policy, with their enforced values.
+    pub locked: serde_json::Map<String, serde_json::Value>,
+    /// Parse or read error of the policy file, if the restrictive fallback is active.
+    pub error: Option<String>,
+}
+
+/// Location of the machine-wide policy file for this platform.
+fn policy_path() -> PathBuf {
+    #[cfg(target_os = "windows")]
+    {
+        let base = std::env::var("ProgramData").unwrap_or_else(|_| "C:\\ProgramData".to_string());
+        PathBuf::from(base).join("cmd-k").join("policy.json")
+    }
+    #[cfg(target_os = "macos")]
+    {
+        PathBuf::from("/Library/Application Support/cmd-k/policy.json")
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        PathBuf::from("/etc/cmd-k/policy.json")
+    }
+}
+
+static POLICY: Lazy<Policy> = Lazy::new(|| load_policy(&policy_path()));
+
+/// Read the policy at `path`: unmanaged when absent, restrictive when unusable.
+fn load_policy(path: &Path) -> Policy {
+    let content = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Policy::default(),
+        Err(e) => {
+            eprintln!("[policy] cannot read policy {}: {}", path.display(), e);
+            return Policy::restrictive(format!("Cannot read {}: {}", path.display(), e));
+        }
+    };
+    match Policy::from_json(&content) {
+        Ok(policy) => {
+            eprintln!("[policy] loaded managed policy from {}", path.display());
+            policy
+        }
+        Err(e) => {
+            eprintln!("[policy] malformed policy {}, applying restrictive fallback: {}", path.display(), e);
+            Policy::restrictive(format!("Invalid {}: {}", path.display(), e))
+        }
+    }
+}
+
+/// The active machine-wide policy.
+pub fn policy() -> &'static Policy {
+    &POLICY
+}
+
+impl Policy {
+    /// Parse a policy file's contents. An invalid redaction rule rejects the
+    /// whole file, since mandatory redaction must not silently fail open.
+    pub fn from_json(content: &str) -> Result<Self, String> {
+        let mut policy: Policy = serde_json::from_str(content).map_err(|e| e.to_string())?;
+        policy.redaction_regexes = policy
+            .redaction_rules
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|e| format!("invalid redaction rule {:?}: {}", pattern, e))
+            })
+            .collect::<Result<_, _>>()?;
+        policy.managed = true;
+        Ok(policy)
+    }
+
+    /// Fallback for a policy file that exists but cannot be applied: the
+    /// strictest values of every restriction.
+    fn restrictive(error: String) -> Self {
+        Policy {
+            local_only: true,
+            disable_auto_confirm: true,
+            force_explanations: true,
+            disable_updater: true,
+            managed: true,
+            error: Some(error),
+            ..Policy::default()
+        }
+    }
+
+    /// Whether the provider may be used under this policy.
+    pub fn provider_allowed(&self, provider: &Provider) -> bool {
+        if self.local_only && !provider.is_local() {
+            return false;
+        }
+        self.allowed_providers
+            .as_ref()
+            .is_none_or(|allowed| allowed.contains(provider))
+    }
+
+    /// Whether the model may be used under this policy.
+    pub fn model_allowed(&self, model: &str) -> bool {
+        self.allowed_models.as_ref().is_none_or(|allowed| {
+            allowed.iter().any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => model.starts_with(prefix),
+                None => model == pattern,
+            })
+        })
+    }
+
+    /// Reject requests to a disallowed provider or model with a user-facing message.
+    pub fn check_request(&self, provider: &Provider, model: &str) -> Result<(), String> {
+        if !self.provider_allowed(provider) {
+            return Err(format!(
+                "{} is disabled by your administrator's policy.",
+                provider.display_name()
+            ));
+        }
+        if !self.model_allowed(model) {
+            return Err(format!(
+                "Model {} is not allowed by your administrator's policy.",
+                model
+            ));
+        }
+        Ok(())
+    }
+
+    /// Providers the user may select, or None when unrestricted.
+    fn effective_providers(&self) -> Option<Vec<Provider>> {
+        if self.allowed_providers.is_none() && !self.local_only {
+            return None;
+        }
+        Some(
+            ALL_PROVIDERS
+                .iter()
+                .filter(|p| self.provider_allowed(p))
+                .copied()
+                .collect(),
+        )
+    }
+
+    /// Settings store keys whose values are fixed by this policy.
+    fn locked_settings(&self) -> serde_json::Map<String, serde_json::Value> {
+        let mut locked = serde_json::Map::new();
+        if self.force_explanations {
+            locked.insert("destructiveDetectionEnabled".to_string(), serde_json::json!(true));
+        }
+        if self.disable_updater {
+            locked.insert("autoUpdate".to_string(), serde_json::json!(false));
+        }
+        if let Some([only]) = self.effective_providers().as_deref() {
+            locked.insert("selectedProvider".to_string(), serde_json::json!(only));
+        }
+        locked
+    }
+
+    /// Frontend view of this policy.
+    pub fn view(&self) -> PolicyView {
+        PolicyView {
+            managed: self.managed,
+            allowed_providers: self.effective_providers(),
+            allowed_models: self.allowed_models.clone(),
+            disable_auto_confirm: self.disable_auto_confirm,
+            force_explanations: self.force_explanations,
+            locked: self.locked_settings(),
+            error: self.error.clone(),
+        }
+    }
+}
+
+/// Apply the policy's mandatory redaction rules to captured text.
+pub fn apply_redactions(text: &str) -> String {
+    policy().redaction_regexes.iter().fold(text.to_string(), |acc, re| {
+        re.replace_all(&acc, "[REDACTED]").into_owned()
+    })
+}
+
+/// Bring the persisted settings store into compliance with the policy.
+///
+/// Called once at startup, before the frontend loads settings, so the UI only
+/// ever sees compliant values.
+pub fn enforce_settings(app: &tauri::App) {
+    let policy = policy();
+    if !policy.managed {
+        return;
+    }
+    let store = match app.store("settings.json") {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("[policy] Failed to open settings store: {}", e);
+            return;
+        }
+    };
+
+    for (key, value) in policy.locked_settings() {
+        if store.get(&key).as_ref() != Some(&value) {
+            eprintln!("[policy] enforcing {}={}", key, value);
+            store.set(key, value);
+        }
+    }
+
+    // Replace a disallowed provider with the first allowed one
+    let selected: Option<Provider> = store
+        .get("selectedProvider")
+        .and_then(|v| serde_json::from_value(v).ok());
+    if let Some(provider) = selected {
+        if !policy.provider_allowed(&provider) {
+            if let Some(fallback) = policy.effective_providers().and_then(|p| p.first().copied()) {
+                eprintln!(
+                    "[policy] provider {} not allowed, switching to {}",
+                    provider.display_name(),
+                    fallback.display_name()
+                );
+                store.set("selectedProvider", serde_json::json!(fallback));
+            }
+        }
+    }
+
+    // Drop disallowed per-provider model selections
+    if let Some(serde_json::Value::Object(models)) = store.get("selectedModels") {
+        let kept: serde_json::Map<String, serde_json::Value> = models
+            .iter()
+            .filter(|(_, m)| m.as_str().is_some_and(|m| policy.model_allowed(m)))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        if kept.len() != models.len() {
+            eprintln!("[policy] removed {} disallowed model selection(s)", models.len() - kept.len());
+            store.set("selectedModels", serde_json::Value::Object(kept));
+        }
+    }
+    if let Some(model) = store.get("selectedModel").and_then(|v| v.as_str().map(String::from)) {
+        if !policy.model_allowed(&model) {
+            store.delete("selectedModel");
+        }
+    }
+}
+
+/// Return the active policy so the frontend can show locked settings as read-only.
+#[tauri::command]
+pub fn get_policy() -> PolicyView {
+    policy().view()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unmanaged_allows_everything() {
+        let policy = Policy::default();
+        assert!(!policy.managed);
+        assert!(policy.check_request(&Provider::OpenAI, "gpt-4o").is_ok());
+        assert!(policy.view().allowed_providers.is_none());
+        assert!(policy.view().locked.is_empty());
+    }
+
+    #[test]
+    fn test_allowed_providers() {
+        let policy = Policy::from_json(r#"{"allowed_providers": ["anthropic", "ollama"]}"#).unwrap();
+        assert!(policy.managed);
+        assert!(policy.provider_allowed(&Provider::Anthropic));
+        assert!(!policy.provider_allowed(&Provider::XAI));
+        assert!(policy.check_request(&Provider::OpenAI, "gpt-4o").is_err());
+    }
+
+    #[test]
+    fn test_local_only_locks_single_provider() {
+        let policy = Policy::from_json(r#"{"local_only": true, "allowed_providers": ["ollama", "openai"]}"#).unwrap();
+        assert!(!policy.provider_allowed(&Provider::OpenAI));
+        assert_eq!(policy.view().allowed_providers, Some(vec![Provider::Ollama]));
+        assert_eq!(policy.view().locked.get("selectedProvider"), Some(&serde_json::json!("ollama")));
+    }
+
+    #[test]
+    fn test_model_wildcard() {
+        let policy = Policy::from_json(r#"{"allowed_models": ["claude-*", "llama3"]}"#).unwrap();
+        assert!(policy.model_allowed("claude-sonnet-4"));
+        assert!(policy.model_allowed("llama3"));
+        assert!(!policy.model_allowed("llama3.1"));
+        assert!(!policy.model_allowed("gpt-4o"));
+    }
+
+    #[test]
+    fn test_locked_settings() {
+        let policy = Policy::from_json(r#"{"force_explanations": true, "disable_updater": true}"#).unwrap();
+        let locked = policy.view().locked;
+        assert_eq!(locked.get("destructiveDetectionEnabled"), Some(&serde_json::json!(true)));
+        assert_eq!(locked.get("autoUpdate"), Some(&serde_json::json!(false)));
+    }
+
+    #[test]
+    fn test_malformed_policy_rejected() {
+        assert!(Policy::from_json(r#"{"allowed_providers": ["nope"]}"#).is_err());
+        assert!(Policy::from_json("not json").is_err());
+    }
+
+    #[test]
+    fn test_invalid_redaction_rule_rejects_policy() {
+        let policy = Policy::from_json(r#"{"redaction_rules": ["ACME-\\d+"]}"#).unwrap();
+        assert_eq!(policy.redaction_regexes.len(), 1);
+        let err = Policy::from_json(r#"{"redaction_rules": ["ACME-\\d+", "(unclosed"]}"#).unwrap_err();
+        assert!(err.contains("(unclosed"));
+
+        let dir = std::env::temp_dir().join(format!("cmdk-policy-rule-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("policy.json");
+        std::fs::write(&path, r#"{"redaction_rules": ["(unclosed"]}"#).unwrap();
+        let policy = load_policy(&path);
+        assert!(policy.error.is_some());
+        assert!(policy.local_only);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_malformed_policy_file_fails_closed() {
+        let dir = std::env::temp_dir().join(format!("cmdk-policy-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        assert!(!load_policy(&dir.join("missing.json")).managed);
+
+        let path = dir.join("policy.json");
+        std::fs::write(&path, r#"{"allowed_providers": ["anthropic",]}"#).unwrap();
+        let policy = load_policy(&path);
+        assert!(policy.managed);
+        assert!(policy.error.is_some());
+        assert!(!policy.provider_allowed(&Provider::Anthropic));
+        assert!(policy.provider_allowed(&Provider::Ollama));
+        assert!(policy.disable_auto_confirm);
+        assert!(policy.view().error.is_some());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
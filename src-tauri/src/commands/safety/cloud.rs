@@ -0,0 +1,326 @@
+//! Cloud-target-aware safety analysis.
+//!
+//! `kubectl delete` against a scratch cluster and against production look the
+//! same to the pattern list. This analyzer works out which Kubernetes context and
+//! namespace, AWS profile, or GCP project a `kubectl`/`oc`/`helm`/`aws`/`gcloud`
+//! invocation targets -- explicit flags first, then the shell's active context --
+//! and raises a finding when a mutating command targets a name matching one of the
+//! user's production patterns.
+
+use crate::terminal::cloud::CloudContext;
+
+use super::shell::{shell_words, split_pipeline};
+use super::tools::command_index;
+use super::{SafetyFinding, Severity};
+
+/// Used when the user has not configured any production patterns.
+pub const DEFAULT_PRODUCTION_PATTERNS: &[&str] = &["*prod*"];
+
+/// What a CLI invocation does to its target.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Impact {
+    /// Deletes or tears down resources
+    Destructive,
+    /// Changes resources or runs code in them
+    Mutating,
+}
+
+/// Case-insensitive glob match supporting `*` and `?`.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.to_lowercase().chars().collect();
+    let t: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            backtrack = Some((pi, ti));
+            pi += 1;
+        } else if let Some((bp, bt)) = backtrack {
+            pi = bp + 1;
+            ti = bt + 1;
+            backtrack = Some((bp, bt + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+/// Value of `--flag value` or `--flag=value` among the args.
+fn flag_value<'a>(args: &'a [String], names: &[&str]) -> Option<&'a str> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        for name in names {
+            if arg == name {
+                return iter.next().map(String::as_str);
+            }
+            if let Some(v) = arg.strip_prefix(name).and_then(|r| r.strip_prefix('=')) {
+                return Some(v);
+            }
+        }
+    }
+    None
+}
+
+/// Non-flag arguments, skipping the values of flags that take one.
+fn positional<'a>(args: &'a [String], valued_flags: &[&str]) -> Vec<&'a str> {
+    let mut out = Vec::new();
+    let mut skip = false;
+    for arg in args {
+        if skip {
+            skip = false;
+        } else if arg.starts_with('-') {
+            skip = !arg.contains('=') && valued_flags.contains(&arg.as_str());
+        } else {
+            out.push(arg.as_str());
+        }
+    }
+    out
+}
+
+const KUBECTL_VALUED: &[&str] = &[
+    "--context", "-n", "--namespace", "--kubeconfig", "--cluster", "--user", "-s", "--server",
+    "--as", "--token", "--kube-context",
+];
+
+fn kubectl_impact(verb: &str) -> Option<Impact> {
+    match verb {
+        "delete" | "drain" => Some(Impact::Destructive),
+        "apply" | "create" | "replace" | "patch" | "edit" | "scale" | "autoscale" | "cordon"
+        | "taint" | "label" | "annotate" | "set" | "rollout" | "exec" | "cp" | "run" | "expose" => {
+            Some(Impact::Mutating)
+        }
+        _ => None,
+    }
+}
+
+fn helm_impact(verb: &str) -> Option<Impact> {
+    match verb {
+        "uninstall" | "delete" | "rollback" => Some(Impact::Destructive),
+        "install" | "upgrade" => Some(Impact::Mutating),
+        _ => None,
+    }
+}
+
+fn aws_impact(operation: &str) -> Option<Impact> {
+    const DESTRUCTIVE: &[&str] = &["delete-", "terminate-", "remove-", "deregister-", "purge-"];
+    const MUTATING: &[&str] = &[
+        "put-", "update-", "create-", "modify-", "stop-", "reboot-", "attach-", "detach-",
+        "run-", "start-", "invoke", "restore-",
+    ];
+    if matches!(operation, "rm" | "rb") || DESTRUCTIVE.iter().any(|p| operation.starts_with(p)) {
+        Some(Impact::Destructive)
+    } else if matches!(operation, "cp" | "mv" | "sync") || MUTATING.iter().any(|p| operation.starts_with(p)) {
+        Some(Impact::Mutating)
+    } else {
+        None
+    }
+}
+
+fn gcloud_impact(words: &[&str]) -> Option<Impact> {
+    if words.contains(&"delete") {
+        return Some(Impact::Destructive);
+    }
+    const MUTATING: &[&str] = &[
+        "create", "update", "deploy", "resize", "stop", "reset", "patch", "import",
+        "set-iam-policy", "add-iam-policy-binding", "remove-iam-policy-binding",
+    ];
+    words.iter().any(|w| MUTATING.contains(w)).then_some(Impact::Mutating)
+}
+
+/// Target of one CLI invocation: (tool, verb shown to the user, impact, names to match).
+type Target = (&'static str, String, Impact, Vec<(&'static str, String)>);
+
+/// Work out what a single pipeline segment targets, if it is a mutating cloud CLI call.
+fn segment_target(words: &[String], ctx: &CloudContext) -> Option<Target> {
+    // Leading VAR=value assignments override the shell's context for this command
+    let env = |key: &str| {
+        words
+            .iter()
+            .take_while(|w| w.contains('=') && !w.starts_with('-'))
+            .find_map(|w| w.strip_prefix(key).and_then(|r| r.strip_prefix('=')))
+            .map(String::from)
+    };
+    // Only the word in command position: `echo kubectl delete ...` runs echo
+    let idx = command_index(words, 0);
+    let program = words.get(idx)?;
+    let name = program.rsplit(['/', '\\']).next().unwrap_or(program);
+    let tool = name.strip_suffix(".exe").unwrap_or(name);
+    if !matches!(tool, "kubectl" | "oc" | "helm" | "aws" | "gcloud") {
+        return None;
+    }
+    let args = &words[idx + 1..];
+
+    match tool {
+        "kubectl" | "oc" | "helm" => {
+            let pos = positional(args, KUBECTL_VALUED);
+            let verb = *pos.first()?;
+            let impact = if tool == "helm" { helm_impact(verb) } else { kubectl_impact(verb) }?;
+            let context = flag_value(args, &["--context", "--kube-context"])
+                .map(String::from)
+                .or_else(|| ctx.kube_context.clone());
+            let namespace = flag_value(args, &["-n", "--namespace"])
+                .map(String::from)
+                .or_else(|| ctx.kube_namespace.clone());
+            let mut names = Vec::new();
+            if let Some(c) = context {
+                names.push(("context", c));
+            }
+            if let Some(ns) = namespace {
+                names.push(("namespace", ns));
+            }
+            let tool = if tool == "helm" { "helm" } else { "kubectl" };
+            Some((tool, verb.to_string(), impact, names))
+        }
+        "aws" => {
+            let pos = positional(args, &["--profile", "--region", "--output", "--endpoint-url", "--query"]);
+            let operation = *pos.get(1)?;
+            let impact = aws_impact(operation)?;
+            let profile = flag_value(args, &["--profile"])
+                .map(String::from)
+                .or_else(|| env("AWS_PROFILE"))
+                .or_else(|| ctx.aws_profile.clone());
+            let names = profile.map(|p| vec![("profile", p)]).unwrap_or_default();
+            Some(("aws", format!("{} {}", pos[0], operation), impact, names))
+        }
+        "gcloud" => {
+            let pos = positional(args, &["--project", "--region", "--zone", "--format", "--account"]);
+            let impact = gcloud_impact(&pos)?;
+            let project = flag_value(args, &["--project"])
+                .map(String::from)
+                .or_else(|| env("CLOUDSDK_CORE_PROJECT"))
+                .or_else(|| ctx.gcloud_project.clone());
+            let names = project.map(|p| vec![("project", p)]).unwrap_or_default();
+            Some(("gcloud", pos.join(" "), impact, names))
+        }
+        _ => None,
+    }
+}
+
+/// Flag mutating cloud CLI commands whose target matches a production pattern.
+pub fn analyze(command: &str, ctx: Option<&CloudContext>, patterns: &[String]) -> Vec<SafetyFinding> {
+    let default_ctx = CloudContext::default();
+    let ctx = ctx.unwrap_or(&default_ctx);
+    let mut findings = Vec::new();
+
+    for line in command.lines() {
+        for segment in split_pipeline(line) {
+            let words = shell_words(segment);
+            let Some((tool, verb, impact, names)) = segment_target(&words, ctx) else {
+                continue;
+            };
+            let Some((kind, name)) = names
+                .iter()
+                .find(|(_, name)| patterns.iter().any(|p| glob_match(p, name)))
+            else {
+                continue;
+            };
+            let (severity, what) = match impact {
+                Impact::Destructive => (Severity::Critical, "deletes resources in"),
+                Impact::Mutating => (Severity::High, "modifies"),
+            };
+            findings.push(SafetyFinding {
+                category: "cloud",
+                severity,
+                message: format!(
+                    "`{} {}` {} production {} {}",
+                    tool, verb, what, kind, name
+                ),
+            });
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prod() -> Vec<String> {
+        DEFAULT_PRODUCTION_PATTERNS.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn kube(context: &str) -> CloudContext {
+        CloudContext {
+            kube_context: Some(context.to_string()),
+            kube_namespace: Some("default".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*prod*", "eks-Production-eu"));
+        assert!(glob_match("prd-??", "prd-eu"));
+        assert!(!glob_match("prd-??", "prd-eu1"));
+        assert!(!glob_match("*prod*", "staging"));
+    }
+
+    #[test]
+    fn test_kubectl_delete_on_prod_context_is_critical() {
+        let findings = analyze("kubectl delete pod web-1", Some(&kube("prod-eu")), &prod());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Critical);
+        assert!(findings[0].message.contains("prod-eu"));
+    }
+
+    #[test]
+    fn test_kubectl_on_staging_not_flagged() {
+        assert!(analyze("kubectl delete pod web-1", Some(&kube("staging")), &prod()).is_empty());
+        assert!(analyze("kubectl get pods", Some(&kube("prod-eu")), &prod()).is_empty());
+    }
+
+    #[test]
+    fn test_explicit_context_flag_overrides_active() {
+        let findings = analyze("kubectl --context=prod-us apply -f app.yaml", Some(&kube("dev")), &prod());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::High);
+        assert!(analyze("kubectl --context dev delete ns x", Some(&kube("prod")), &prod()).is_empty());
+    }
+
+    #[test]
+    fn test_namespace_match() {
+        let findings = analyze("kubectl -n production scale deploy/api --replicas=0", Some(&kube("eks")), &prod());
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].message.contains("namespace production"));
+    }
+
+    #[test]
+    fn test_aws_profile() {
+        let ctx = CloudContext { aws_profile: Some("prod-admin".into()), ..Default::default() };
+        let findings = analyze("aws s3 rm s3://bucket --recursive", Some(&ctx), &prod());
+        assert_eq!(findings[0].severity, Severity::Critical);
+        assert!(analyze("aws s3 ls", Some(&ctx), &prod()).is_empty());
+        assert_eq!(
+            analyze("AWS_PROFILE=prod aws ec2 terminate-instances --instance-ids i-1", None, &prod()).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_gcloud_project_flag() {
+        let findings = analyze("gcloud compute instances delete vm-1 --project my-prod", None, &prod());
+        assert_eq!(findings[0].severity, Severity::Critical);
+        assert!(analyze("gcloud compute instances list --project my-prod", None, &prod()).is_empty());
+    }
+
+    #[test]
+    fn test_cli_only_in_command_position() {
+        let ctx = CloudContext { aws_profile: Some("prod".into()), ..kube("prod-eu") };
+        assert!(analyze("echo kubectl delete ns prod", Some(&ctx), &prod()).is_empty());
+        assert!(analyze("grep aws history.log | grep delete-", Some(&ctx), &prod()).is_empty());
+        assert!(analyze("man helm uninstall", Some(&ctx), &prod()).is_empty());
+        assert_eq!(analyze("sudo -E kubectl delete ns prod", Some(&ctx), &prod()).len(), 1);
+        assert_eq!(analyze("env KUBECONFIG=/tmp/k kubectl delete ns prod", Some(&ctx), &prod()).len(), 1);
+    }
+
+    #[test]
+    fn test_custom_patterns() {
+        let patterns = vec!["live-*".to_string()];
+        assert_eq!(analyze("helm uninstall api", Some(&kube("live-eu")), &patterns).len(), 1);
+        assert!(analyze("helm uninstall api", Some(&kube("prod")), &patterns).is_empty());
+    }
+}
@@ -0,0 +1,516 @@
+//! Git-aware safety analysis.
+//!
+//! For commands that discard work or rewrite history (`git reset --hard`,
+//! `git clean -f`, `git checkout -- .`, `git restore`, `git stash drop/clear`,
+//! `git push --force`), inspects the repository at the terminal's CWD and reports
+//! exactly what would be lost. All git queries are read-only and time-bounded
+//! (see `terminal::git`). Force-push analysis uses the remote-tracking refs as of
+//! the last fetch; it never contacts the remote.
+
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::terminal::git::{current_branch, git_lines, repo_root, run_git};
+
+use super::shell::{shell_words, split_pipeline};
+use super::{SafetyFinding, Severity};
+
+/// Cap on the entries kept per list; totals are still counted.
+const MAX_LISTED: usize = 100;
+
+/// What a git command would destroy, attached to the safety report.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GitLossReport {
+    /// Repository top-level directory
+    pub repo: String,
+    /// Tracked files whose uncommitted changes would be discarded
+    pub uncommitted: Vec<String>,
+    /// Untracked (or ignored) files that would be deleted
+    pub untracked: Vec<String>,
+    /// Commits ("abc1234 subject") no longer reachable from any branch or tag
+    pub unreachable_commits: Vec<String>,
+    /// Remote commits a force push would overwrite ("abc1234 subject")
+    pub overwritten_remote_commits: Vec<String>,
+    /// Remote branch a force push targets, e.g. "origin/main"
+    pub remote_branch: Option<String>,
+    /// Stash entries that would be dropped
+    pub dropped_stashes: Vec<String>,
+}
+
+impl GitLossReport {
+    pub fn is_empty(&self) -> bool {
+        self.uncommitted.is_empty()
+            && self.untracked.is_empty()
+            && self.unreachable_commits.is_empty()
+            && self.overwritten_remote_commits.is_empty()
+            && self.dropped_stashes.is_empty()
+    }
+}
+
+/// A work-discarding git operation recognised in the command.
+#[derive(Debug, Clone, PartialEq)]
+enum GitOp {
+    ResetHard { target: Option<String> },
+    /// Arguments for the equivalent `git clean -n` dry run
+    Clean { dry_run_args: Vec<String> },
+    /// `checkout -- paths` / `restore paths`; `include_staged` when the index is reset too
+    Discard { paths: Vec<String>, include_staged: bool },
+    StashDrop { stash: String },
+    StashClear,
+    ForcePush { remote: Option<String>, refspec: Option<String> },
+}
+
+/// Non-flag arguments, skipping the values of flags that take one.
+fn positionals<'a>(args: &'a [String], valued: &[&str]) -> Vec<&'a str> {
+    let mut out = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            break;
+        }
+        if arg.starts_with('-') {
+            if valued.contains(&arg.as_str()) {
+                iter.next();
+            }
+        } else {
+            out.push(arg.as_str());
+        }
+    }
+    out
+}
+
+fn has_flag(args: &[String], long: &str, short: Option<char>) -> bool {
+    args.iter().take_while(|a| *a != "--").any(|a| {
+        a == long
+            || a.starts_with(&format!("{}=", long))
+            || short.is_some_and(|c| {
+                a.len() > 1 && a.starts_with('-') && !a.starts_with("--") && a[1..].contains(c)
+            })
+    })
+}
+
+/// Parse one `git <subcommand> ...` invocation (words after `git` and its global options).
+fn parse_op(sub: &str, args: &[String]) -> Option<GitOp> {
+    match sub {
+        "reset" if has_flag(args, "--hard", None) => Some(GitOp::ResetHard {
+            target: positionals(args, &[]).first().map(|s| s.to_string()),
+        }),
+        "clean" => {
+            let forced = has_flag(args, "--force", Some('f'));
+            let dry = has_flag(args, "--dry-run", Some('n')) || has_flag(args, "--interactive", Some('i'));
+            if !forced || dry {
+                return None;
+            }
+            let dry_run_args = args
+                .iter()
+                .filter_map(|a| {
+                    if a == "--force" {
+                        None
+                    } else if a.starts_with('-') && !a.starts_with("--") {
+                        let rest: String = a[1..].chars().filter(|&c| c != 'f').collect();
+                        (!rest.is_empty()).then(|| format!("-{}", rest))
+                    } else {
+                        Some(a.clone())
+                    }
+                })
+                .collect();
+            Some(GitOp::Clean { dry_run_args })
+        }
+        "checkout" => {
+            if let Some(sep) = args.iter().position(|a| a == "--") {
+                let paths: Vec<String> = args[sep + 1..].to_vec();
+                let treeish = !positionals(&args[..sep], &["-b", "-B"]).is_empty();
+                return Some(GitOp::Discard { paths, include_staged: treeish });
+            }
+            let pos = positionals(args, &["-b", "-B"]);
+            if pos == ["."] {
+                return Some(GitOp::Discard { paths: vec![".".into()], include_staged: false });
+            }
+            if has_flag(args, "--force", Some('f')) {
+                return Some(GitOp::Discard { paths: Vec::new(), include_staged: true });
+            }
+            None
+        }
+        "restore" => {
+            let staged = has_flag(args, "--staged", Some('S'));
+            let worktree = has_flag(args, "--worktree", Some('W'));
+            if staged && !worktree {
+                return None;
+            }
+            let mut paths: Vec<String> = positionals(args, &["-s", "--source"])
+                .into_iter()
+                .map(String::from)
+                .collect();
+            if let Some(sep) = args.iter().position(|a| a == "--") {
+                paths.extend(args[sep + 1..].iter().cloned());
+            }
+            Some(GitOp::Discard { paths, include_staged: staged })
+        }
+        "stash" => match args.first().map(String::as_str) {
+            Some("drop") => {
+                let stash = positionals(&args[1..], &[])
+                    .first()
+                    .map(|s| {
+                        if s.chars().all(|c| c.is_ascii_digit()) {
+                            format!("stash@{{{}}}", s)
+                        } else {
+                            s.to_string()
+                        }
+                    })
+                    .unwrap_or_else(|| "stash@{0}".to_string());
+                Some(GitOp::StashDrop { stash })
+            }
+            Some("clear") => Some(GitOp::StashClear),
+            _ => None,
+        },
+        "push" => {
+            let pos = positionals(args, &["-o", "--push-option", "--repo", "--receive-pack", "--exec"]);
+            let forced = has_flag(args, "--force", Some('f'))
+                || has_flag(args, "--force-with-lease", None)
+                || pos.iter().skip(1).any(|r| r.starts_with('+'));
+            if !forced || has_flag(args, "--all", None) || has_flag(args, "--mirror", None) {
+                return None;
+            }
+            Some(GitOp::ForcePush {
+                remote: pos.first().map(|s| s.to_string()),
+                refspec: pos.get(1).map(|s| s.trim_start_matches('+').to_string()),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Find work-discarding git operations, each with the directory it runs in
+/// (`git -C <dir>` relative to `cwd`).
+fn parse_ops(command: &str, cwd: &str) -> Vec<(String, GitOp)> {
+    let mut ops = Vec::new();
+    for line in command.lines() {
+        for segment in split_pipeline(line) {
+            let words = shell_words(segment);
+            let Some(git_idx) = words.iter().position(|w| {
+                let name = w.rsplit(['/', '\\']).next().unwrap_or(w);
+                name == "git" || name == "git.exe"
+            }) else {
+                continue;
+            };
+
+            // Global options before the subcommand
+            let mut dir = cwd.to_string();
+            let mut i = git_idx + 1;
+            while i < words.len() && words[i].starts_with('-') {
+                if words[i] == "-C" {
+                    if let Some(path) = words.get(i + 1) {
+                        dir = Path::new(&dir).join(path).to_string_lossy().into_owned();
+                    }
+                    i += 1;
+                } else if words[i] == "-c" {
+                    i += 1;
+                }
+                i += 1;
+            }
+            let Some(sub) = words.get(i) else { continue };
+            if let Some(op) = parse_op(sub, &words[i + 1..]) {
+                ops.push((dir, op));
+            }
+        }
+    }
+    ops
+}
+
+/// Paths from `git status --porcelain` lines ("XY path", "XY old -> new").
+fn porcelain_paths(lines: Vec<String>) -> Vec<String> {
+    lines
+        .into_iter()
+        .filter_map(|l| {
+            let path = l.get(3..)?;
+            Some(path.rsplit(" -> ").next().unwrap_or(path).to_string())
+        })
+        .collect()
+}
+
+fn extend_capped(list: &mut Vec<String>, items: Vec<String>) {
+    for item in items {
+        if list.len() >= MAX_LISTED {
+            break;
+        }
+        if !list.contains(&item) {
+            list.push(item);
+        }
+    }
+}
+
+/// Commits in `range_end` not reachable from `base` or any other ref.
+fn unreachable_commits(dir: &str, base: &str, range_end: &str, exclude_branch: Option<&str>) -> Vec<String> {
+    let range = format!("{}..{}", base, range_end);
+    let exclude = exclude_branch.map(|b| format!("--exclude={}", b));
+    let mut args = vec!["log", "--format=%h %s", range.as_str(), "--not"];
+    if let Some(ref e) = exclude {
+        args.push(e);
+    }
+    args.extend(["--branches", "--tags", "--remotes"]);
+    git_lines(dir, &args).unwrap_or_default()
+}
+
+fn collect(dir: &str, op: &GitOp, report: &mut GitLossReport) {
+    match op {
+        GitOp::ResetHard { target } => {
+            let status = git_lines(dir, &["status", "--porcelain", "--untracked-files=no"]).unwrap_or_default();
+            extend_capped(&mut report.uncommitted, porcelain_paths(status));
+            if let Some(target) = target {
+                let branch = current_branch(dir);
+                let lost = unreachable_commits(dir, target, "HEAD", branch.as_deref());
+                extend_capped(&mut report.unreachable_commits, lost);
+            }
+        }
+        GitOp::Clean { dry_run_args } => {
+            let mut args = vec!["clean", "-n"];
+            args.extend(dry_run_args.iter().map(String::as_str));
+            let lines = git_lines(dir, &args).unwrap_or_default();
+            let removed = lines
+                .into_iter()
+                .filter_map(|l| l.strip_prefix("Would remove ").map(String::from))
+                .collect();
+            extend_capped(&mut report.untracked, removed);
+        }
+        GitOp::Discard { paths, include_staged } => {
+            let mut args = vec!["diff", "--name-only"];
+            if *include_staged {
+                args.push("HEAD");
+            }
+            args.push("--");
+            args.extend(paths.iter().map(String::as_str));
+            extend_capped(&mut report.uncommitted, git_lines(dir, &args).unwrap_or_default());
+        }
+        GitOp::StashDrop { stash } => {
+            let entries = git_lines(dir, &["stash", "list", "--format=%gd: %s"]).unwrap_or_default();
+            let prefix = format!("{}:", stash);
+            extend_capped(
+                &mut report.dropped_stashes,
+                entries.into_iter().filter(|e| e.starts_with(&prefix)).collect(),
+            );
+        }
+        GitOp::StashClear => {
+            let entries = git_lines(dir, &["stash", "list", "--format=%gd: %s"]).unwrap_or_default();
+            extend_capped(&mut report.dropped_stashes, entries);
+        }
+        GitOp::ForcePush { remote, refspec } => {
+            // Upstream of the current branch, e.g. "origin/main"
+            let upstream = run_git(dir, &["rev-parse", "--abbrev-ref", "--symbolic-full-name", "@{u}"])
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty());
+            let upstream_split = upstream.as_deref().and_then(|u| u.split_once('/'));
+
+            let (src, dst) = match refspec.as_deref().map(|r| r.split_once(':').unwrap_or((r, r))) {
+                Some((src, dst)) => (src.to_string(), Some(dst.trim_start_matches("refs/heads/").to_string())),
+                None => {
+                    let dst = upstream_split
+                        .filter(|(r, _)| remote.as_deref().is_none_or(|given| given == *r))
+                        .map(|(_, b)| b.to_string())
+                        .or_else(|| current_branch(dir));
+                    ("HEAD".to_string(), dst)
+                }
+            };
+            let Some(dst) = dst else { return };
+            let remote = remote
+                .clone()
+                .or_else(|| upstream_split.map(|(r, _)| r.to_string()))
+                .unwrap_or_else(|| "origin".to_string());
+            let remote_ref = format!("refs/remotes/{}/{}", remote, dst);
+            let range = format!("{}..{}", src, remote_ref);
+            let overwritten = git_lines(dir, &["log", "--format=%h %s", &range]).unwrap_or_default();
+            report.remote_branch = Some(format!("{}/{}", remote, dst));
+            extend_capped(&mut report.overwritten_remote_commits, overwritten);
+        }
+    }
+}
+
+/// Inspect the repository at `cwd` for what the command would lose.
+///
+/// Returns None when the command has no work-discarding git operation or `cwd`
+/// is not inside a repository.
+pub fn inspect(command: &str, cwd: &str) -> Option<GitLossReport> {
+    let ops = parse_ops(command, cwd);
+    if ops.is_empty() {
+        return None;
+    }
+    let mut report = GitLossReport::default();
+    for (dir, op) in &ops {
+        let Some(root) = repo_root(dir) else { continue };
+        if report.repo.is_empty() {
+            report.repo = root;
+        }
+        collect(dir, op, &mut report);
+    }
+    (!report.repo.is_empty()).then_some(report)
+}
+
+/// "a, b, c and 4 more"
+fn preview(items: &[String]) -> String {
+    let shown: Vec<&str> = items.iter().take(3).map(String::as_str).collect();
+    match items.len().saturating_sub(3) {
+        0 => shown.join(", "),
+        n => format!("{} and {} more", shown.join(", "), n),
+    }
+}
+
+fn plural(n: usize, one: &str, many: &str) -> String {
+    format!("{} {}", n, if n == 1 { one } else { many })
+}
+
+/// Findings describing a loss report.
+pub fn findings(report: &GitLossReport) -> Vec<SafetyFinding> {
+    let mut out = Vec::new();
+    let mut push = |severity, message: String| {
+        out.push(SafetyFinding { category: "git", severity, message });
+    };
+    if !report.uncommitted.is_empty() {
+        push(
+            Severity::High,
+            format!(
+                "Discards uncommitted changes to {}: {}",
+                plural(report.uncommitted.len(), "file", "files"),
+                preview(&report.uncommitted)
+            ),
+        );
+    }
+    if !report.untracked.is_empty() {
+        push(
+            Severity::High,
+            format!(
+                "Permanently deletes {}: {}",
+                plural(report.untracked.len(), "untracked file", "untracked files"),
+                preview(&report.untracked)
+            ),
+        );
+    }
+    if !report.unreachable_commits.is_empty() {
+        push(
+            Severity::High,
+            format!(
+                "Leaves {} unreachable from any branch: {}",
+                plural(report.unreachable_commits.len(), "commit", "commits"),
+                preview(&report.unreachable_commits)
+            ),
+        );
+    }
+    if !report.overwritten_remote_commits.is_empty() {
+        push(
+            Severity::Critical,
+            format!(
+                "Overwrites {} on {} that your branch does not contain: {}",
+                plural(report.overwritten_remote_commits.len(), "commit", "commits"),
+                report.remote_branch.as_deref().unwrap_or("the remote"),
+                preview(&report.overwritten_remote_commits)
+            ),
+        );
+    }
+    if !report.dropped_stashes.is_empty() {
+        push(
+            Severity::High,
+            format!(
+                "Drops {}: {}",
+                plural(report.dropped_stashes.len(), "stash entry", "stash entries"),
+                preview(&report.dropped_stashes)
+            ),
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(command: &str) -> Option<GitOp> {
+        parse_ops(command, "/repo").into_iter().next().map(|(_, op)| op)
+    }
+
+    #[test]
+    fn test_parse_reset_and_clean() {
+        assert_eq!(op("git reset --hard HEAD~2"), Some(GitOp::ResetHard { target: Some("HEAD~2".into()) }));
+        assert_eq!(op("git reset --soft HEAD~1"), None);
+        assert_eq!(op("git clean -fdx"), Some(GitOp::Clean { dry_run_args: vec!["-dx".into()] }));
+        assert_eq!(op("git clean -n -d"), None);
+        assert_eq!(op("git clean -d"), None);
+    }
+
+    #[test]
+    fn test_parse_discard() {
+        assert_eq!(
+            op("git checkout -- ."),
+            Some(GitOp::Discard { paths: vec![".".into()], include_staged: false })
+        );
+        assert_eq!(
+            op("git checkout HEAD -- src/a.rs"),
+            Some(GitOp::Discard { paths: vec!["src/a.rs".into()], include_staged: true })
+        );
+        assert_eq!(op("git checkout main"), None);
+        assert_eq!(op("git restore --staged a.rs"), None);
+        assert_eq!(
+            op("git restore a.rs"),
+            Some(GitOp::Discard { paths: vec!["a.rs".into()], include_staged: false })
+        );
+    }
+
+    #[test]
+    fn test_parse_stash_and_push() {
+        assert_eq!(op("git stash drop 2"), Some(GitOp::StashDrop { stash: "stash@{2}".into() }));
+        assert_eq!(op("git stash clear"), Some(GitOp::StashClear));
+        assert_eq!(op("git push origin main"), None);
+        assert_eq!(
+            op("git push --force-with-lease origin feature"),
+            Some(GitOp::ForcePush { remote: Some("origin".into()), refspec: Some("feature".into()) })
+        );
+        assert_eq!(
+            op("git push origin +main"),
+            Some(GitOp::ForcePush { remote: Some("origin".into()), refspec: Some("main".into()) })
+        );
+    }
+
+    #[test]
+    fn test_global_dir_option() {
+        let ops = parse_ops("cd x && git -C sub -c core.x=1 stash clear", "/repo");
+        assert_eq!(ops.len(), 1);
+        assert_eq!(Path::new(&ops[0].0), Path::new("/repo/sub"));
+    }
+
+    #[test]
+    fn test_inspect_real_repo() {
+        let dir = std::env::temp_dir().join(format!("cmdk-git-safety-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let d = dir.to_str().unwrap();
+        let git = |args: &[&str]| {
+            std::process::Command::new("git")
+                .arg("-C")
+                .arg(d)
+                .args(["-c", "user.name=t", "-c", "user.email=t@t", "-c", "commit.gpgsign=false"])
+                .args(args)
+                .output()
+                .is_ok_and(|o| o.status.success())
+        };
+        if !git(&["init", "-q"]) {
+            return; // git not installed
+        }
+        std::fs::write(dir.join("a.txt"), "1").unwrap();
+        assert!(git(&["add", "a.txt"]));
+        assert!(git(&["commit", "-qm", "first"]));
+        std::fs::write(dir.join("a.txt"), "2").unwrap();
+        assert!(git(&["commit", "-qam", "second"]));
+        std::fs::write(dir.join("a.txt"), "3").unwrap();
+        std::fs::write(dir.join("new.txt"), "x").unwrap();
+
+        let report = inspect("git reset --hard HEAD~1", d).unwrap();
+        assert_eq!(report.uncommitted, vec!["a.txt"]);
+        assert_eq!(report.unreachable_commits.len(), 1);
+        assert!(report.unreachable_commits[0].ends_with("second"));
+
+        let report = inspect("git clean -f", d).unwrap();
+        assert_eq!(report.untracked, vec!["new.txt"]);
+        assert_eq!(findings(&report)[0].severity, Severity::High);
+
+        assert!(inspect("git status", d).is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,6 +1,20 @@
+mod cloud;
+mod git;
+mod repl;
+mod shell;
+mod sql;
+mod tools;
+
 use once_cell::sync::Lazy;
 use regex::RegexSet;
+use serde::Serialize;
+use tauri::AppHandle;
 use tauri_plugin_http::reqwest;
+use tauri_plugin_store::StoreExt;
+
+use crate::terminal::aliases::ShellAliases;
+use crate::terminal::cloud::CloudContext;
+use crate::terminal::repl::ReplKind;
 
 // Keychain constants must match keychain.rs exactly
 const SERVICE: &str = "com.lakshmanturlapati.cmd-k";
@@ -197,13 +211,258 @@ static DESTRUCTIVE_PATTERNS: Lazy<RegexSet> = Lazy::new(|| {
     .expect("DESTRUCTIVE_PATTERNS regex set failed to compile")
 });
 
-/// Check whether a command string matches any known destructive patterns.
+/// How dangerous a command is. Ordered so the report severity is the max of its findings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    None,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+/// A single reason a command was flagged.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SafetyFinding {
+    /// Analyzer that produced the finding ("pattern", "sql", ...)
+    pub category: &'static str,
+    pub severity: Severity,
+    /// Plain-English description, specific enough to show to the user as-is
+    pub message: String,
+}
+
+/// Structured safety result for a command.
+#[derive(Debug, Clone, Serialize)]
+pub struct SafetyReport {
+    /// Whether the command should be badged and withheld from auto-paste
+    pub destructive: bool,
+    /// Highest severity across all findings
+    pub severity: Severity,
+    pub findings: Vec<SafetyFinding>,
+    /// What a git command would destroy in the repository at the terminal's CWD
+    pub git_loss: Option<git::GitLossReport>,
+}
+
+/// Environment a command will run in, for analyzers that depend on it.
+#[derive(Debug, Clone, Default)]
+pub struct AnalysisContext {
+    /// Cloud CLI targets active in the terminal
+    pub cloud: Option<CloudContext>,
+    /// Glob patterns naming production contexts, profiles and projects
+    pub production_patterns: Vec<String>,
+    /// Terminal working directory; enables the git loss inspection when set
+    pub cwd: Option<String>,
+    /// PATH of a POSIX-style shell; enables the missing-program check when set
+    pub search_path: Option<String>,
+    /// The shell's aliases, expanded before analysis, and functions
+    pub aliases: Option<ShellAliases>,
+    /// REPL the input goes to; it is then code in that language, not a shell command
+    pub repl: Option<ReplKind>,
+}
+
+/// Run every analyzer over the command and combine the results.
+pub fn analyze(command: &str, actx: &AnalysisContext) -> SafetyReport {
+    if let Some(kind) = actx.repl {
+        return analyze_repl_input(command, kind, actx);
+    }
+
+    // Judge both what was typed and what the shell will run: `k delete ns prod`
+    // runs `kubectl delete ns prod`, but `alias rm='rm -i'` must not hide the
+    // `rm -rf` the user typed
+    let expanded = match actx.aliases {
+        Some(ref aliases) => shell::expand_aliases(command, &aliases.aliases),
+        None => command.to_string(),
+    };
+    let mut texts = vec![command];
+    if expanded != command {
+        texts.push(&expanded);
+    }
+    let functions = actx.aliases.as_ref().map(|a| a.functions.as_slice()).unwrap_or_default();
+
+    let mut findings: Vec<SafetyFinding> = Vec::new();
+    for text in &texts {
+        let mut found = Vec::new();
+        if DESTRUCTIVE_PATTERNS.is_match(text) {
+            found.push(SafetyFinding {
+                category: "pattern",
+                severity: Severity::High,
+                message: "Matches a known destructive command pattern".to_string(),
+            });
+        }
+        found.extend(sql::analyze(text));
+        found.extend(cloud::analyze(
+            text,
+            actx.cloud.as_ref(),
+            &actx.production_patterns,
+        ));
+        for finding in found {
+            if !findings.contains(&finding) {
+                findings.push(finding);
+            }
+        }
+    }
+    // PATH lookup only applies to the programs the shell actually runs
+    if let Some(ref search_path) = actx.search_path {
+        findings.extend(tools::analyze(&expanded, search_path, functions));
+    }
+    let git_loss = actx
+        .cwd
+        .as_deref()
+        .and_then(|cwd| texts.iter().rev().find_map(|text| git::inspect(text, cwd)));
+    if let Some(ref report) = git_loss {
+        findings.extend(git::findings(report));
+    }
+
+    report(findings, git_loss)
+}
+
+/// Analyze input typed into a REPL: the language's own rules, SQL in the
+/// client's dialect, and any shell command the input runs.
+fn analyze_repl_input(input: &str, kind: ReplKind, actx: &AnalysisContext) -> SafetyReport {
+    let mut findings = repl::analyze(input, kind);
+    findings.extend(sql::analyze_repl(input, kind));
+
+    // The REPL's working directory, PATH and aliases are not the terminal shell's
+    let shell_ctx = AnalysisContext {
+        cloud: actx.cloud.clone(),
+        production_patterns: actx.production_patterns.clone(),
+        ..AnalysisContext::default()
+    };
+    for shell_command in repl::shell_commands(input, kind) {
+        findings.extend(analyze(&shell_command, &shell_ctx).findings.into_iter().map(|f| {
+            SafetyFinding {
+                message: format!("Runs `{}`: {}", shell_command, f.message),
+                ..f
+            }
+        }));
+    }
+    report(findings, None)
+}
+
+/// Combine findings into a report whose severity is the highest finding's.
+fn report(findings: Vec<SafetyFinding>, git_loss: Option<git::GitLossReport>) -> SafetyReport {
+    let severity = findings
+        .iter()
+        .map(|f| f.severity)
+        .max()
+        .unwrap_or(Severity::None);
+    SafetyReport {
+        destructive: severity >= Severity::Medium,
+        severity,
+        findings,
+        git_loss,
+    }
+}
+
+/// User-configured production name patterns (settings key "productionPatterns").
+pub(crate) fn production_patterns(app: &AppHandle) -> Vec<String> {
+    let configured: Option<Vec<String>> = app
+        .store("settings.json")
+        .ok()
+        .and_then(|store| store.get("productionPatterns"))
+        .and_then(|v| serde_json::from_value(v).ok());
+    configured.unwrap_or_else(|| {
+        cloud::DEFAULT_PRODUCTION_PATTERNS
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    })
+}
+
+/// Check whether a command string is potentially destructive.
 ///
 /// Returns `true` if the command is potentially destructive, `false` otherwise.
-/// Uses a compiled RegexSet for zero-allocation pattern matching at call time.
+/// Kept for callers that only need a yes/no answer; see `analyze_command` for details.
 #[tauri::command]
 pub fn check_destructive(command: String) -> bool {
-    DESTRUCTIVE_PATTERNS.is_match(&command)
+    analyze(&command, &AnalysisContext::default()).destructive
+}
+
+/// Analyze a command and return the structured safety report (severity and findings).
+///
+/// `cloud` is the terminal's detected cloud context, used to escalate commands
+/// that target a production cluster, profile or project. `cwd` enables the git
+/// loss report; the git queries run on a blocking thread so the UI stays responsive.
+/// `search_path` is the PATH from the terminal's tool inventory; programs missing
+/// from it are reported for bash, zsh and fish, whose builtins are known.
+/// `aliases` are expanded before any analyzer runs. With `repl` set, the command
+/// is input for that REPL and is judged by the language's rules instead.
+#[tauri::command]
+pub async fn analyze_command(
+    app: AppHandle,
+    command: String,
+    cloud: Option<CloudContext>,
+    cwd: Option<String>,
+    search_path: Option<String>,
+    shell: Option<String>,
+    aliases: Option<ShellAliases>,
+    repl: Option<ReplKind>,
+) -> Result<SafetyReport, String> {
+    let posix_shell = matches!(shell.as_deref(), Some("bash" | "zsh" | "fish" | "sh" | "dash" | "ksh"));
+    let actx = AnalysisContext {
+        cloud,
+        production_patterns: production_patterns(&app),
+        cwd,
+        search_path: search_path.filter(|_| posix_shell),
+        aliases,
+        repl,
+    };
+    let report = tauri::async_runtime::spawn_blocking(move || analyze(&command, &actx))
+        .await
+        .map_err(|e| format!("Safety analysis failed: {}", e))?;
+    if report.destructive {
+        eprintln!(
+            "[safety] severity={:?} findings={}",
+            report.severity,
+            report.findings.len()
+        );
+    }
+    Ok(report)
+}
+
+/// Result of checking where a generated command came from.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CommandProvenance {
+    /// The command appears verbatim in the captured terminal/screen text.
+    pub copied_from_context: bool,
+    /// The captured line the command was found in, if any.
+    pub matched_line: Option<String>,
+    /// The matching line was flagged as instruction-like (possible prompt injection).
+    pub in_untrusted_span: bool,
+}
+
+/// Check whether a generated command was copied from captured context rather than
+/// derived from the user's query.
+///
+/// `context_text` is the raw terminal output or screen text that was sent with the request.
+#[tauri::command]
+pub fn check_command_provenance(
+    command: String,
+    query: String,
+    context_text: Option<String>,
+) -> CommandProvenance {
+    let found = context_text
+        .as_deref()
+        .and_then(|ctx| crate::terminal::injection::command_copied_from(&command, ctx, &query));
+    match found {
+        Some(m) => {
+            eprintln!(
+                "[safety] command matches captured text (untrusted={})",
+                m.in_flagged_line
+            );
+            CommandProvenance {
+                copied_from_context: true,
+                in_untrusted_span: m.in_flagged_line,
+                matched_line: Some(m.line),
+            }
+        }
+        None => CommandProvenance {
+            copied_from_context: false,
+            matched_line: None,
+            in_untrusted_span: false,
+        },
+    }
 }
 
 /// Get a plain-English explanation of why a command is destructive via the xAI API.
@@ -219,6 +478,15 @@ pub async fn get_destructive_explanation(
 ) -> Result<(), String> {
     eprintln!("[safety] get_destructive_explanation called, model={}", model);
 
+    // The explanation call goes to xAI; respect an administrator policy that disallows it
+    let policy = super::policy::policy();
+    if !policy.provider_allowed(&super::providers::Provider::XAI) || !policy.model_allowed(&model) {
+        eprintln!("[safety] explanation provider disallowed by policy, using fallback");
+        return on_result
+            .send("This command makes irreversible changes.".to_string())
+            .map_err(|e| format!("Channel error: {}", e));
+    }
+
     // Read API key from Keychain
     let entry = keyring::Entry::new(SERVICE, ACCOUNT)
         .map_err(|e| format!("Keyring error: {}", e))?;
@@ -278,3 +546,46 @@ pub async fn get_destructive_explanation(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_aliases(aliases: &[(&str, &str)]) -> AnalysisContext {
+        AnalysisContext {
+            aliases: Some(ShellAliases {
+                aliases: aliases.iter().map(|(n, v)| (n.to_string(), v.to_string())).collect(),
+                ..ShellAliases::default()
+            }),
+            production_patterns: cloud::DEFAULT_PRODUCTION_PATTERNS.iter().map(|s| s.to_string()).collect(),
+            ..AnalysisContext::default()
+        }
+    }
+
+    #[test]
+    fn test_alias_cannot_hide_typed_command() {
+        let report = analyze("rm -rf ~/x", &with_aliases(&[("rm", "rm -i")]));
+        assert!(report.destructive);
+        assert_eq!(report.findings.len(), 1);
+
+        let mut actx = with_aliases(&[("kubectl", "kubecolor")]);
+        actx.cloud = Some(CloudContext {
+            kube_context: Some("prod-eu".to_string()),
+            ..CloudContext::default()
+        });
+        let report = analyze("kubectl delete ns prod", &actx);
+        assert_eq!(report.severity, Severity::Critical);
+        assert_eq!(report.findings.iter().filter(|f| f.category == "cloud").count(), 1);
+    }
+
+    #[test]
+    fn test_alias_expansion_is_analyzed() {
+        let mut actx = with_aliases(&[("k", "kubectl")]);
+        actx.cloud = Some(CloudContext {
+            kube_context: Some("prod-eu".to_string()),
+            ..CloudContext::default()
+        });
+        assert_eq!(analyze("k delete ns web", &actx).severity, Severity::Critical);
+        assert!(analyze("k get pods", &actx).findings.is_empty());
+    }
+}
@@ -0,0 +1,162 @@
+//! Safety analysis for input typed into a language REPL.
+//!
+//! Shell patterns do not fit `shutil.rmtree("build")` or `FLUSHALL`. Each REPL
+//! language has its own calls that delete files or data. Shell commands the
+//! input hands to a shell (`os.system(...)`, Ruby backticks, psql `\!`) are
+//! extracted so the shell analyzers can judge them too.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::terminal::repl::ReplKind;
+
+use super::{SafetyFinding, Severity};
+
+/// A call or command that is dangerous in the REPLs of `kinds`.
+struct Rule {
+    kinds: &'static [ReplKind],
+    pattern: Regex,
+    severity: Severity,
+    message: &'static str,
+}
+
+static RULES: Lazy<Vec<Rule>> = Lazy::new(|| {
+    use ReplKind::*;
+    let rule = |kinds: &'static [ReplKind], pattern: &str, severity, message| Rule {
+        kinds,
+        pattern: Regex::new(pattern).expect("REPL safety rule failed to compile"),
+        severity,
+        message,
+    };
+    vec![
+        // === Python ===
+        rule(&[Python], r"\bshutil\.rmtree\s*\(", Severity::High, "Deletes a directory tree (shutil.rmtree)"),
+        rule(&[Python], r"\bos\.(?:remove|unlink|rmdir|removedirs)\s*\(", Severity::Medium, "Deletes files or directories (os.remove)"),
+        rule(&[Python], r"\)\.(?:unlink|rmdir)\s*\(", Severity::Medium, "Deletes a file or directory (Path.unlink)"),
+        rule(&[Python], r"\.objects\.all\(\)\.delete\s*\(", Severity::High, "Deletes every row of the model's table"),
+        rule(&[Python], r#"\bopen\s*\([^)]*,\s*(?:mode\s*=\s*)?['"]w"#, Severity::Low, "Overwrites a file (open in \"w\" mode)"),
+        // === JavaScript (Node.js) ===
+        rule(&[Node], r"\b(?:fs|fsp|promises)\.rm(?:Sync)?\s*\([^)]*recursive\s*:\s*true", Severity::High, "Deletes a directory tree (fs.rm with recursive)"),
+        rule(&[Node], r"\brimraf(?:\.sync)?\s*\(", Severity::High, "Deletes a directory tree (rimraf)"),
+        rule(&[Node], r"\b(?:fs|fsp|promises)\.(?:unlink|rm|rmdir)(?:Sync)?\s*\(", Severity::Medium, "Deletes files or directories (fs.unlink/fs.rm)"),
+        // === Ruby ===
+        rule(&[Ruby], r"\bFileUtils\.(?:rm_rf|rm_r|rmtree|remove_dir|remove_entry(?:_secure)?)\b", Severity::High, "Deletes a directory tree (FileUtils.rm_rf)"),
+        rule(&[Ruby], r"\b(?:File\.(?:delete|unlink)|FileUtils\.rm\b|Dir\.(?:rmdir|delete|unlink))", Severity::Medium, "Deletes files or directories (File.delete)"),
+        rule(&[Ruby], r"\.(?:destroy_all|delete_all)\b", Severity::High, "Deletes records in bulk (destroy_all/delete_all)"),
+        rule(&[Ruby], r"\.update_all\b", Severity::Medium, "Updates records in bulk, skipping validations (update_all)"),
+        // === Haskell ===
+        rule(&[Haskell], r"\b(?:removeDirectoryRecursive|removePathForcibly)\b", Severity::High, "Deletes a directory tree (removeDirectoryRecursive)"),
+        rule(&[Haskell], r"\bremove(?:File|Directory)\b", Severity::Medium, "Deletes files or directories (removeFile)"),
+        // === Redis ===
+        rule(&[Redis], r"(?im)^\s*FLUSH(?:ALL|DB)\b", Severity::Critical, "Deletes every key (FLUSHALL/FLUSHDB)"),
+        rule(&[Redis], r"(?im)^\s*(?:REPLICAOF|SLAVEOF)\s+\S+\s+\d+", Severity::Critical, "Replaces the whole dataset with a copy of another server (REPLICAOF)"),
+        rule(&[Redis], r"(?im)^\s*(?:SCRIPT|FUNCTION)\s+FLUSH\b", Severity::High, "Deletes all loaded scripts or functions"),
+        rule(&[Redis], r"(?im)^\s*(?:SHUTDOWN|SWAPDB)\b", Severity::High, "Stops the server or swaps whole databases"),
+        rule(&[Redis], r"(?im)^\s*CONFIG\s+(?:SET|REWRITE|RESETSTAT)\b", Severity::Medium, "Changes the server configuration"),
+        rule(&[Redis], r"(?im)^\s*KEYS\b", Severity::Low, "Scans every key and blocks the server (use SCAN)"),
+    ]
+});
+
+/// Calls that hand a string or argument list to the shell: `os.system("...")`,
+/// `subprocess.run([...])`, `execSync("...")`, Ruby `system "..."`,
+/// Haskell `callCommand "..."`.
+static SHELL_CALL_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"\b(?:os\.system|os\.popen|subprocess\.\w+|execSync|exec|spawnSync|spawn|system|callCommand|spawnCommand)\s*\(?\s*\[?\s*(?P<args>(?:'[^']*'|"[^"]*")(?:\s*,\s*(?:'[^']*'|"[^"]*"))*)"#,
+    )
+    .unwrap()
+});
+
+/// A single- or double-quoted string literal.
+static QUOTED_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"'([^']*)'|"([^"]*)""#).unwrap());
+
+/// Ruby backticks and `%x(...)`.
+static RUBY_SUBSHELL_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"`([^`]+)`|%x\(([^)]*)\)").unwrap());
+
+/// Shell escapes of the database clients: psql and mysql `\! cmd`, mysql
+/// `system cmd`, sqlite3 `.shell cmd` / `.system cmd`.
+static SQL_SHELL_ESCAPE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?im)^\s*(?:\\!|system\s|\.shell\s|\.system\s)\s*(.+)$").unwrap());
+
+/// Flag dangerous calls in REPL input. One finding per matching rule.
+pub(super) fn analyze(input: &str, kind: ReplKind) -> Vec<SafetyFinding> {
+    RULES
+        .iter()
+        .filter(|rule| rule.kinds.contains(&kind))
+        .filter_map(|rule| {
+            let found = rule.pattern.find(input)?;
+            Some(SafetyFinding {
+                category: "repl",
+                severity: rule.severity,
+                message: format!("{}: `{}`", rule.message, found.as_str().trim()),
+            })
+        })
+        .collect()
+}
+
+/// Shell commands the REPL input would run, for the shell analyzers.
+pub(super) fn shell_commands(input: &str, kind: ReplKind) -> Vec<String> {
+    if kind.is_sql() {
+        return SQL_SHELL_ESCAPE_RE
+            .captures_iter(input)
+            .map(|caps| caps[1].trim().to_string())
+            .collect();
+    }
+    let mut commands: Vec<String> = SHELL_CALL_RE
+        .captures_iter(input)
+        .map(|caps| {
+            // subprocess.run(["rm", "-rf", "build"]) -> rm -rf build
+            QUOTED_RE
+                .captures_iter(&caps["args"])
+                .filter_map(|q| q.get(1).or_else(|| q.get(2)))
+                .map(|m| m.as_str())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect();
+    if kind == ReplKind::Ruby {
+        commands.extend(
+            RUBY_SUBSHELL_RE
+                .captures_iter(input)
+                .filter_map(|caps| caps.get(1).or_else(|| caps.get(2)))
+                .map(|m| m.as_str().to_string()),
+        );
+    }
+    commands.retain(|c| !c.trim().is_empty());
+    commands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn severities(input: &str, kind: ReplKind) -> Vec<Severity> {
+        analyze(input, kind).into_iter().map(|f| f.severity).collect()
+    }
+
+    #[test]
+    fn test_language_rules() {
+        assert_eq!(severities("shutil.rmtree('build')", ReplKind::Python), vec![Severity::High]);
+        assert_eq!(severities("os.remove(path)", ReplKind::Python), vec![Severity::Medium]);
+        assert_eq!(severities("fs.rmSync('dist', { recursive: true })", ReplKind::Node)[0], Severity::High);
+        assert_eq!(severities("User.where(active: false).destroy_all", ReplKind::Ruby), vec![Severity::High]);
+        assert_eq!(severities("flushall", ReplKind::Redis), vec![Severity::Critical]);
+        assert_eq!(severities("GET session:42", ReplKind::Redis), vec![]);
+        // Rules are per language
+        assert_eq!(severities("FileUtils.rm_rf('tmp')", ReplKind::Python), vec![]);
+        assert_eq!(severities("[x for x in os.listdir('.')]", ReplKind::Python), vec![]);
+    }
+
+    #[test]
+    fn test_shell_commands() {
+        assert_eq!(shell_commands("os.system('rm -rf /tmp/x')", ReplKind::Python), vec!["rm -rf /tmp/x"]);
+        assert_eq!(
+            shell_commands(r#"subprocess.run(["git", "push", "--force"], check=True)"#, ReplKind::Python),
+            vec!["git push --force"]
+        );
+        assert_eq!(shell_commands("require('child_process').execSync(\"kubectl delete ns dev\")", ReplKind::Node), vec!["kubectl delete ns dev"]);
+        assert_eq!(shell_commands("puts `ls -la`", ReplKind::Ruby), vec!["ls -la"]);
+        assert_eq!(shell_commands("\\! rm -rf dump\nSELECT 1;", ReplKind::Postgres), vec!["rm -rf dump"]);
+        assert!(shell_commands("print('hello')", ReplKind::Python).is_empty());
+    }
+}
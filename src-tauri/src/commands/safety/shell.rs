@@ -0,0 +1,208 @@
+//! Minimal shell command-line tokenizing shared by the safety analyzers.
+
+/// Split a shell command line into words, honouring single quotes, double quotes
+/// and backslash escapes. Operators are not special-cased; callers split pipelines first.
+pub(super) fn shell_words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                for q in chars.by_ref() {
+                    if q == '\'' {
+                        break;
+                    }
+                    current.push(q);
+                }
+            }
+            '"' => {
+                in_word = true;
+                while let Some(q) = chars.next() {
+                    match q {
+                        '"' => break,
+                        '\\' => {
+                            if let Some(&next) = chars.peek() {
+                                if matches!(next, '"' | '\\' | '$' | '`') {
+                                    current.push(next);
+                                    chars.next();
+                                    continue;
+                                }
+                            }
+                            current.push(q);
+                        }
+                        _ => current.push(q),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            _ => {
+                in_word = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(current);
+    }
+    words
+}
+
+/// Split a command line on unquoted `|`, `;`, `&`, `&&` and `||`. The `&` of a
+/// redirection (`2>&1`, `<&3`, `&>log`, `|&`) is not a separator.
+pub(super) fn split_pipeline(line: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quote: Option<char> = None;
+    let bytes: Vec<(usize, char)> = line.char_indices().collect();
+    let mut i = 0;
+    while i < bytes.len() {
+        let (idx, c) = bytes[i];
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None => match c {
+                '\'' | '"' => quote = Some(c),
+                '\\' => i += 1,
+                '&' if is_redirection_amp(&bytes, i) => {}
+                '|' | ';' | '&' => {
+                    parts.push(&line[start..idx]);
+                    // Swallow the second char of `||` / `&&` / `|&`
+                    if i + 1 < bytes.len()
+                        && (bytes[i + 1].1 == c || (c == '|' && bytes[i + 1].1 == '&'))
+                    {
+                        i += 1;
+                    }
+                    start = bytes.get(i + 1).map(|(j, _)| *j).unwrap_or(line.len());
+                }
+                _ => {}
+            },
+        }
+        i += 1;
+    }
+    parts.push(&line[start..]);
+    parts
+}
+
+/// Whether the `&` at `i` belongs to a redirection: `>&`, `<&` (including
+/// `2>&1`) or `&>` / `&>>`.
+fn is_redirection_amp(chars: &[(usize, char)], i: usize) -> bool {
+    let prev = i.checked_sub(1).map(|p| chars[p].1);
+    let next = chars.get(i + 1).map(|(_, c)| *c);
+    matches!(prev, Some('>' | '<')) || next == Some('>')
+}
+
+/// Nested alias expansions followed before giving up, as with `a=b`, `b=a`.
+const MAX_ALIAS_DEPTH: usize = 5;
+
+/// Replace an alias in command position of each pipeline stage with its
+/// expansion, as the shell would before running it. An alias whose expansion
+/// starts with its own name (`ls='ls -G'`) is expanded once.
+pub(super) fn expand_aliases(command: &str, aliases: &[(String, String)]) -> String {
+    if aliases.is_empty() {
+        return command.to_string();
+    }
+    let lookup = |word: &str| {
+        aliases
+            .iter()
+            .find(|(name, _)| name == word)
+            .map(|(_, v)| v.as_str())
+    };
+
+    let expand_stage = |stage: &str| -> String {
+        let body = stage.trim_start();
+        let indent = &stage[..stage.len() - body.len()];
+        let mut expanded = body.to_string();
+        let mut seen: Vec<String> = Vec::new();
+        for _ in 0..MAX_ALIAS_DEPTH {
+            let word_end = expanded.find(char::is_whitespace).unwrap_or(expanded.len());
+            let word = &expanded[..word_end];
+            if seen.iter().any(|s| s == word) {
+                break;
+            }
+            let Some(value) = lookup(word) else { break };
+            seen.push(word.to_string());
+            expanded = format!("{}{}", value, &expanded[word_end..]);
+        }
+        format!("{}{}", indent, expanded)
+    };
+
+    command
+        .lines()
+        .map(|line| {
+            let mut out = String::with_capacity(line.len());
+            let mut last = 0;
+            for stage in split_pipeline(line) {
+                // Stages are subslices of `line`; copy the separators between them
+                let start = stage.as_ptr() as usize - line.as_ptr() as usize;
+                out.push_str(&line[last..start]);
+                out.push_str(&expand_stage(stage));
+                last = start + stage.len();
+            }
+            out.push_str(&line[last..]);
+            out
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_words_quotes() {
+        assert_eq!(
+            shell_words(r#"psql -c "a \"b\" c" 'd e'"#),
+            vec!["psql", "-c", r#"a "b" c"#, "d e"]
+        );
+    }
+
+    #[test]
+    fn test_split_pipeline() {
+        assert_eq!(
+            split_pipeline("echo 'a|b' | psql && ls; pwd"),
+            vec!["echo 'a|b' ", " psql ", " ls", " pwd"]
+        );
+        assert_eq!(
+            split_pipeline("kubectl delete ns prod 2>&1 | tee log &> /dev/null & sleep 1"),
+            vec![
+                "kubectl delete ns prod 2>&1 ",
+                " tee log &> /dev/null ",
+                " sleep 1"
+            ]
+        );
+        assert_eq!(
+            split_pipeline("make |& grep error"),
+            vec!["make ", " grep error"]
+        );
+    }
+
+    #[test]
+    fn test_expand_aliases() {
+        let aliases = vec![
+            ("k".to_string(), "kubectl".to_string()),
+            ("ls".to_string(), "ls -G".to_string()),
+            ("kd".to_string(), "k delete".to_string()),
+        ];
+        assert_eq!(
+            expand_aliases("k delete ns prod && ls | grep k; kd pod x", &aliases),
+            "kubectl delete ns prod && ls -G | grep k; kubectl delete pod x"
+        );
+        assert_eq!(expand_aliases("echo k", &aliases), "echo k");
+    }
+}
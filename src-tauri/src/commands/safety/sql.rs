@@ -0,0 +1,525 @@
+//! SQL-aware safety analysis.
+//!
+//! Extracts SQL embedded in database CLI invocations (`psql -c`, `mysql -e`,
+//! `sqlite3 db "..."`, heredocs, `echo ... |` pipes) or typed directly, parses
+//! it with sqlparser, and flags statements that destroy or expose data:
+//! - UPDATE/DELETE without WHERE, or with an always-true WHERE (`1=1`, `true`)
+//! - DROP / TRUNCATE / destructive ALTER TABLE
+//! - GRANT / REVOKE
+//!
+//! Findings name the affected tables so the explanation is specific.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sqlparser::ast::{
+    AlterTableOperation, BinaryOperator, Expr, FromTable, GrantObjects, ObjectName, ObjectType,
+    Privileges, Statement, TableFactor, TableWithJoins, UnaryOperator, Value,
+};
+use sqlparser::dialect::{Dialect, GenericDialect, MySqlDialect, PostgreSqlDialect, SQLiteDialect};
+use sqlparser::parser::Parser;
+
+use crate::terminal::repl::ReplKind;
+
+use super::shell::{shell_words, split_pipeline};
+use super::tools::command_index;
+use super::{SafetyFinding, Severity};
+
+/// Database CLIs we know how to extract SQL from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SqlClient {
+    Postgres,
+    MySql,
+    Sqlite,
+    Generic,
+}
+
+impl SqlClient {
+    fn from_program(program: &str) -> Option<Self> {
+        let name = program.rsplit(['/', '\\']).next().unwrap_or(program);
+        let name = name.strip_suffix(".exe").unwrap_or(name);
+        match name {
+            "psql" | "pgcli" => Some(Self::Postgres),
+            "mysql" | "mariadb" | "mycli" => Some(Self::MySql),
+            "sqlite3" | "sqlite" | "litecli" => Some(Self::Sqlite),
+            "duckdb" | "clickhouse-client" | "cockroach" => Some(Self::Generic),
+            _ => None,
+        }
+    }
+
+    fn dialect(self) -> Box<dyn Dialect> {
+        match self {
+            Self::Postgres => Box::new(PostgreSqlDialect {}),
+            Self::MySql => Box::new(MySqlDialect {}),
+            Self::Sqlite => Box::new(SQLiteDialect {}),
+            Self::Generic => Box::new(GenericDialect {}),
+        }
+    }
+}
+
+/// Command text that starts with a mutating SQL keyword (typed directly, e.g. into a REPL).
+static BARE_SQL_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^\s*(UPDATE|DELETE|DROP|TRUNCATE|ALTER|GRANT|REVOKE|WITH)\b").unwrap()
+});
+
+/// Heredoc opener: `<<EOF`, `<<-'SQL'`, `<< "END"`.
+static HEREDOC_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"<<-?\s*['"]?([A-Za-z_][A-Za-z0-9_]*)['"]?"#).unwrap());
+
+/// Options of `docker exec` / `kubectl exec` that take a value.
+const EXEC_VALUE_OPTIONS: &[&str] = &[
+    "-e", "--env", "--env-file", "-u", "--user", "-w", "--workdir", "--detach-keys",
+    "-n", "--namespace", "-c", "--container", "--context",
+];
+
+/// Index of the database client in a segment's words, if the client is the
+/// command being run. Skips env assignments, wrappers such as
+/// `sudo -u postgres` or `PGPASSWORD=x`, and `docker exec -it <ctr>`-style
+/// container wrappers. A client name anywhere else (`echo psql`,
+/// `grep mysql log`) is an argument, not a client.
+fn client_index(words: &[String]) -> Option<(usize, SqlClient)> {
+    let mut i = command_index(words, 0);
+    while let Some(next) = words.get(i).and_then(|_| container_exec_command(words, i)) {
+        i = command_index(words, next);
+    }
+    SqlClient::from_program(words.get(i)?).map(|c| (i, c))
+}
+
+/// For `docker exec -it db`, `docker compose exec db` or
+/// `kubectl exec -n prod db --`, the index of the command run inside the container.
+fn container_exec_command(words: &[String], start: usize) -> Option<usize> {
+    let program = words[start].rsplit('/').next().unwrap_or(&words[start]);
+    let word = |i: usize| words.get(i).map(String::as_str);
+    let mut i = start + 1;
+    match program {
+        "docker" | "podman" | "nerdctl" if word(i) == Some("compose") => i += 1,
+        "docker" | "podman" | "nerdctl" | "docker-compose" | "kubectl" | "oc" => {}
+        _ => return None,
+    }
+    if word(i) != Some("exec") {
+        return None;
+    }
+    i += 1;
+    while let Some(opt) = word(i).filter(|o| o.starts_with('-') && *o != "--") {
+        i += if EXEC_VALUE_OPTIONS.contains(&opt) { 2 } else { 1 };
+    }
+    // The container, service or pod
+    i += 1;
+    // kubectl separates its own options from the command with `--`
+    if let Some(dashes) = (i..words.len()).find(|&j| word(j) == Some("--")) {
+        if matches!(program, "kubectl" | "oc") || dashes == i {
+            i = dashes + 1;
+        }
+    }
+    Some(i)
+}
+
+/// Extract the inline SQL argument from a CLI invocation's words.
+fn inline_sql(client: SqlClient, args: &[String]) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut positional: Vec<&String> = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        let takes_sql = match client {
+            SqlClient::Postgres => matches!(arg.as_str(), "-c" | "--command"),
+            SqlClient::MySql => matches!(arg.as_str(), "-e" | "--execute"),
+            SqlClient::Generic => matches!(arg.as_str(), "-c" | "-q" | "--query" | "-e" | "--execute"),
+            SqlClient::Sqlite => arg == "-cmd",
+        };
+        if takes_sql {
+            if let Some(sql) = args.get(i + 1) {
+                out.push(sql.clone());
+            }
+            i += 2;
+            continue;
+        }
+        for prefix in ["--command=", "--execute=", "--query="] {
+            if let Some(sql) = arg.strip_prefix(prefix) {
+                out.push(sql.to_string());
+            }
+        }
+        // Attached short form: mysql -e"DELETE ..."
+        if let Some(sql) = arg
+            .strip_prefix("-e")
+            .filter(|s| client == SqlClient::MySql && !s.is_empty())
+        {
+            out.push(sql.to_string());
+        }
+        if !arg.starts_with('-') {
+            positional.push(arg);
+        }
+        i += 1;
+    }
+
+    // sqlite3/duckdb: `sqlite3 db.sqlite "SQL"` -- the second positional argument
+    if matches!(client, SqlClient::Sqlite | SqlClient::Generic) && positional.len() >= 2 {
+        out.push(positional[1].clone());
+    }
+    out
+}
+
+/// Body of a heredoc starting on `line_idx` of `lines`, if that line opens one.
+fn heredoc_body(lines: &[&str], line_idx: usize) -> Option<String> {
+    let caps = HEREDOC_RE.captures(lines[line_idx])?;
+    let delim = caps.get(1)?.as_str();
+    let body: Vec<&str> = lines[line_idx + 1..]
+        .iter()
+        .take_while(|l| l.trim() != delim)
+        .copied()
+        .collect();
+    Some(body.join("\n"))
+}
+
+/// Find every SQL snippet in a shell command, paired with the dialect to parse it with.
+fn extract_sql(command: &str) -> Vec<(SqlClient, String)> {
+    let mut out = Vec::new();
+    let lines: Vec<&str> = command.lines().collect();
+
+    for (idx, line) in lines.iter().enumerate() {
+        let segments = split_pipeline(line);
+        for (seg_idx, segment) in segments.iter().enumerate() {
+            let words = shell_words(segment);
+            let Some((prog, client)) = client_index(&words) else {
+                continue;
+            };
+            out.extend(inline_sql(client, &words[prog + 1..]).into_iter().map(|s| (client, s)));
+
+            // Heredoc fed to the client: psql <<EOF ... EOF
+            if segment.contains("<<") {
+                if let Some(body) = heredoc_body(&lines, idx) {
+                    out.push((client, body));
+                }
+            }
+
+            // echo/printf "SQL" | client
+            if seg_idx > 0 {
+                let prev = shell_words(segments[seg_idx - 1]);
+                if matches!(prev.first().map(String::as_str), Some("echo" | "printf")) {
+                    out.extend(prev.into_iter().skip(1).filter(|w| !w.starts_with('-')).map(|s| (client, s)));
+                }
+            }
+        }
+    }
+
+    // SQL typed directly (database REPL or a pasted statement)
+    if out.is_empty() && BARE_SQL_RE.is_match(command) {
+        out.push((SqlClient::Generic, command.to_string()));
+    }
+    out
+}
+
+/// Parse SQL, falling back to statement-by-statement parsing so one unsupported
+/// statement does not hide the others.
+fn parse_statements(client: SqlClient, sql: &str) -> Vec<Statement> {
+    let dialect = client.dialect();
+    if let Ok(stmts) = Parser::parse_sql(dialect.as_ref(), sql) {
+        return stmts;
+    }
+    sql.split(';')
+        .filter(|s| !s.trim().is_empty())
+        .filter_map(|s| Parser::parse_sql(dialect.as_ref(), s).ok())
+        .flatten()
+        .collect()
+}
+
+/// True for predicates that match every row: `1=1`, `true`, `'a'='a'`, `x OR 1=1`, `1`.
+fn is_tautology(expr: &Expr) -> bool {
+    match expr {
+        Expr::Value(Value::Boolean(b)) => *b,
+        Expr::Value(Value::Number(n, _)) => n.parse::<f64>().map(|v| v != 0.0).unwrap_or(false),
+        Expr::Nested(inner) => is_tautology(inner),
+        Expr::UnaryOp { op: UnaryOperator::Not, expr } => {
+            matches!(expr.as_ref(), Expr::Value(Value::Boolean(false)))
+        }
+        Expr::BinaryOp { left, op, right } => match op {
+            BinaryOperator::Or => is_tautology(left) || is_tautology(right),
+            BinaryOperator::And => is_tautology(left) && is_tautology(right),
+            BinaryOperator::Eq | BinaryOperator::GtEq | BinaryOperator::LtEq => {
+                matches!(left.as_ref(), Expr::Value(_)) && left.to_string() == right.to_string()
+            }
+            BinaryOperator::NotEq => {
+                matches!((left.as_ref(), right.as_ref()), (Expr::Value(_), Expr::Value(_)))
+                    && left.to_string() != right.to_string()
+            }
+            _ => false,
+        },
+        Expr::IsNotNull(inner) => matches!(inner.as_ref(), Expr::Value(v) if *v != Value::Null),
+        _ => false,
+    }
+}
+
+fn table_name(table: &TableWithJoins) -> String {
+    match &table.relation {
+        TableFactor::Table { name, .. } => name.to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn join_names(names: &[ObjectName]) -> String {
+    names.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+/// Describe why a WHERE clause does not bound the statement, or None if it does.
+fn unbounded_reason(selection: &Option<Expr>) -> Option<&'static str> {
+    match selection {
+        None => Some("no WHERE clause"),
+        Some(expr) if is_tautology(expr) => Some("a WHERE clause that matches every row"),
+        Some(_) => None,
+    }
+}
+
+fn finding(severity: Severity, message: String) -> SafetyFinding {
+    SafetyFinding {
+        category: "sql",
+        severity,
+        message,
+    }
+}
+
+/// Flag a single parsed statement.
+fn analyze_statement(stmt: &Statement) -> Option<SafetyFinding> {
+    match stmt {
+        Statement::Update { table, selection, .. } => unbounded_reason(selection).map(|why| {
+            finding(
+                Severity::High,
+                format!("UPDATE on {} has {} and will modify every row", table_name(table), why),
+            )
+        }),
+        Statement::Delete(delete) => unbounded_reason(&delete.selection).map(|why| {
+            let tables = match &delete.from {
+                FromTable::WithFromKeyword(t) | FromTable::WithoutKeyword(t) => {
+                    t.iter().map(table_name).collect::<Vec<_>>().join(", ")
+                }
+            };
+            let tables = if delete.tables.is_empty() {
+                tables
+            } else {
+                join_names(&delete.tables)
+            };
+            finding(
+                Severity::High,
+                format!("DELETE from {} has {} and will remove every row", tables, why),
+            )
+        }),
+        Statement::Drop { object_type, names, cascade, .. } => {
+            let severity = match object_type {
+                ObjectType::Database | ObjectType::Schema => Severity::Critical,
+                _ if *cascade => Severity::Critical,
+                ObjectType::Table => Severity::High,
+                _ => Severity::Medium,
+            };
+            let note = if *cascade { " and everything that depends on it (CASCADE)" } else { "" };
+            Some(finding(
+                severity,
+                format!("DROP {} permanently removes {}{}", object_type, join_names(names), note),
+            ))
+        }
+        Statement::Truncate { table_names, .. } => {
+            let names: Vec<String> = table_names.iter().map(|t| t.name.to_string()).collect();
+            Some(finding(
+                Severity::High,
+                format!("TRUNCATE removes every row from {}", names.join(", ")),
+            ))
+        }
+        Statement::AlterTable { name, operations, .. } => {
+            let destructive = operations.iter().find_map(|op| match op {
+                AlterTableOperation::DropColumn { column_name, .. } => {
+                    Some(format!("drops column {}", column_name))
+                }
+                AlterTableOperation::DropConstraint { name, .. } => {
+                    Some(format!("drops constraint {}", name))
+                }
+                AlterTableOperation::DropPrimaryKey => Some("drops the primary key".to_string()),
+                AlterTableOperation::AlterColumn { column_name, .. } => {
+                    Some(format!("changes column {}", column_name))
+                }
+                AlterTableOperation::RenameTable { table_name } => {
+                    Some(format!("renames it to {}", table_name))
+                }
+                _ => None,
+            });
+            Some(match destructive {
+                Some(what) => finding(Severity::High, format!("ALTER TABLE {} {}", name, what)),
+                None => finding(Severity::Medium, format!("ALTER TABLE changes the schema of {}", name)),
+            })
+        }
+        Statement::Grant { privileges, objects, grantees, .. } => {
+            let all = matches!(privileges, Privileges::All { .. });
+            let grantees = grantees.iter().map(|g| g.to_string()).collect::<Vec<_>>().join(", ");
+            Some(finding(
+                if all { Severity::High } else { Severity::Medium },
+                format!("GRANT {} on {} to {}", privileges, describe_objects(objects), grantees),
+            ))
+        }
+        Statement::Revoke { privileges, objects, grantees, .. } => {
+            let grantees = grantees.iter().map(|g| g.to_string()).collect::<Vec<_>>().join(", ");
+            Some(finding(
+                Severity::Medium,
+                format!("REVOKE {} on {} from {}", privileges, describe_objects(objects), grantees),
+            ))
+        }
+        _ => None,
+    }
+}
+
+fn describe_objects(objects: &GrantObjects) -> String {
+    match objects {
+        GrantObjects::Tables(names) => join_names(names),
+        GrantObjects::Schemas(names) => format!("schema {}", join_names(names)),
+        GrantObjects::Sequences(names) => format!("sequence {}", join_names(names)),
+        GrantObjects::AllTablesInSchema { schemas } => format!("all tables in {}", join_names(schemas)),
+        GrantObjects::AllSequencesInSchema { schemas } => {
+            format!("all sequences in {}", join_names(schemas))
+        }
+    }
+}
+
+/// Analyze a shell command for dangerous SQL. Returns one finding per flagged statement.
+pub fn analyze(command: &str) -> Vec<SafetyFinding> {
+    extract_sql(command)
+        .into_iter()
+        .flat_map(|(client, sql)| parse_statements(client, &sql))
+        .filter_map(|stmt| analyze_statement(&stmt))
+        .collect()
+}
+
+/// Analyze input typed into a database REPL with that client's dialect.
+/// Client commands (psql `\dt`, sqlite3 `.tables`) are skipped.
+pub(super) fn analyze_repl(input: &str, kind: ReplKind) -> Vec<SafetyFinding> {
+    let client = match kind {
+        ReplKind::Postgres => SqlClient::Postgres,
+        ReplKind::Mysql => SqlClient::MySql,
+        ReplKind::Sqlite => SqlClient::Sqlite,
+        _ => return Vec::new(),
+    };
+    let sql: Vec<&str> = input
+        .lines()
+        .filter(|line| !line.trim_start().starts_with(['\\', '.']))
+        .collect();
+    parse_statements(client, &sql.join("\n"))
+        .iter()
+        .filter_map(analyze_statement)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn severities(command: &str) -> Vec<Severity> {
+        analyze(command).into_iter().map(|f| f.severity).collect()
+    }
+
+    #[test]
+    fn test_update_without_where() {
+        let findings = analyze(r#"psql -d app -c "UPDATE users SET admin = true""#);
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].message.contains("users"));
+        assert_eq!(findings[0].severity, Severity::High);
+    }
+
+    #[test]
+    fn test_update_with_where_is_safe() {
+        assert!(analyze(r#"psql -c "UPDATE users SET admin = true WHERE id = 42""#).is_empty());
+    }
+
+    #[test]
+    fn test_delete_with_tautology() {
+        let findings = analyze(r#"mysql -u root -e "DELETE FROM orders WHERE 1=1""#);
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].message.contains("orders"));
+        assert!(findings[0].message.contains("matches every row"));
+    }
+
+    #[test]
+    fn test_delete_or_tautology() {
+        assert_eq!(
+            severities("sqlite3 app.db \"DELETE FROM sessions WHERE user_id = 5 OR 'a'='a'\""),
+            vec![Severity::High]
+        );
+    }
+
+    #[test]
+    fn test_sqlite_positional_sql() {
+        let findings = analyze("sqlite3 data.db 'DROP TABLE logs;'");
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].message.contains("logs"));
+    }
+
+    #[test]
+    fn test_heredoc() {
+        let cmd = "psql mydb <<'SQL'\nBEGIN;\nDELETE FROM audit;\nCOMMIT;\nSQL";
+        let findings = analyze(cmd);
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].message.contains("audit"));
+    }
+
+    #[test]
+    fn test_echo_pipe() {
+        assert_eq!(severities(r#"echo "TRUNCATE TABLE events" | psql prod"#), vec![Severity::High]);
+    }
+
+    #[test]
+    fn test_drop_database_is_critical() {
+        assert_eq!(severities(r#"psql -c "DROP DATABASE analytics""#), vec![Severity::Critical]);
+    }
+
+    #[test]
+    fn test_alter_drop_column() {
+        let findings = analyze(r#"mysql -e "ALTER TABLE users DROP COLUMN email""#);
+        assert_eq!(findings[0].severity, Severity::High);
+        assert!(findings[0].message.contains("email"));
+    }
+
+    #[test]
+    fn test_grant_all() {
+        let findings = analyze(r#"psql -c "GRANT ALL PRIVILEGES ON accounts TO intern""#);
+        assert_eq!(findings[0].severity, Severity::High);
+        assert!(findings[0].message.contains("intern"));
+    }
+
+    #[test]
+    fn test_bare_sql() {
+        assert_eq!(severities("UPDATE accounts SET balance = 0;"), vec![Severity::High]);
+    }
+
+    #[test]
+    fn test_select_is_safe() {
+        assert!(analyze(r#"psql -c "SELECT * FROM users""#).is_empty());
+        assert!(analyze("mysql -e 'SHOW TABLES'").is_empty());
+    }
+
+    #[test]
+    fn test_non_sql_commands_ignored() {
+        assert!(analyze("git commit -m 'delete from cache'").is_empty());
+        assert!(analyze("ls -la").is_empty());
+    }
+
+    #[test]
+    fn test_repl_input() {
+        let input = "\\x\nDELETE FROM jobs;\nSELECT count(*) FROM jobs;";
+        assert_eq!(
+            analyze_repl(input, ReplKind::Postgres).into_iter().map(|f| f.severity).collect::<Vec<_>>(),
+            vec![Severity::High]
+        );
+        assert!(analyze_repl(".tables\nSELECT * FROM logs;", ReplKind::Sqlite).is_empty());
+    }
+
+    #[test]
+    fn test_client_only_in_command_position() {
+        assert!(analyze(r#"echo psql -c "DELETE FROM jobs""#).is_empty());
+        assert!(analyze("grep mysql -e 'DELETE FROM jobs' app.log").is_empty());
+        assert!(analyze("cp sqlite3 backup 'DROP TABLE logs;'").is_empty());
+        assert_eq!(severities(r#"docker exec -it -u postgres db psql -c "DELETE FROM jobs""#).len(), 1);
+        assert_eq!(severities(r#"kubectl exec -n prod pg-0 -c db -- psql -c "DROP TABLE logs""#).len(), 1);
+        assert_eq!(severities(r#"docker compose exec db mysql -e "TRUNCATE events""#).len(), 1);
+    }
+
+    #[test]
+    fn test_env_prefix_and_sudo() {
+        assert_eq!(
+            severities(r#"PGPASSWORD=x sudo -u postgres psql -c "DELETE FROM jobs""#).len(),
+            1
+        );
+    }
+}
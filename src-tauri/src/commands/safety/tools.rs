@@ -0,0 +1,109 @@
+//! Flags generated commands that call programs missing from the shell's PATH.
+
+use super::shell::{shell_words, split_pipeline};
+use super::{SafetyFinding, Severity};
+
+/// Builtins and keywords of bash, zsh and fish that are never on PATH.
+const BUILTINS: &[&str] = &[
+    ".", ":", "[", "[[", "{", "}", "!", "alias", "bg", "bind", "break", "builtin", "case", "cd",
+    "command", "continue", "declare", "dirs", "disown", "do", "done", "echo", "elif", "else",
+    "esac", "eval", "exec", "exit", "export", "false", "fc", "fg", "fi", "for", "function",
+    "getopts", "hash", "history", "if", "jobs", "kill", "let", "local", "popd", "printf", "pushd",
+    "pwd", "read", "readonly", "return", "select", "set", "shift", "source", "test", "then",
+    "time", "trap", "true", "type", "typeset", "ulimit", "umask", "unalias", "unset", "until",
+    "wait", "while",
+    // zsh
+    "autoload", "noglob", "rehash", "setopt", "unsetopt", "whence", "where", "which", "zmodload",
+    // fish
+    "abbr", "and", "begin", "contains", "end", "functions", "math", "not", "or", "set_color",
+    "status", "string",
+];
+
+/// Prefixes that run the following word as the actual command.
+const WRAPPERS: &[&str] = &["sudo", "env", "nohup", "exec", "command", "time"];
+
+/// Wrapper options that take a value (`sudo -u root`, `env -u VAR`).
+const WRAPPER_VALUE_OPTIONS: &[&str] = &["-u", "-g", "-C", "-D", "-h", "-p", "-U"];
+
+/// Index of the word at or after `i` that runs as the command, skipping
+/// `NAME=value` assignments and wrappers with their options.
+pub(super) fn command_index(words: &[String], mut i: usize) -> usize {
+    while let Some(word) = words.get(i) {
+        if matches!(word.find('='), Some(p) if p > 0) {
+            i += 1;
+        } else if WRAPPERS.contains(&word.as_str()) {
+            i += 1;
+            while let Some(opt) = words.get(i).filter(|o| o.starts_with('-')) {
+                i += if WRAPPER_VALUE_OPTIONS.contains(&opt.as_str()) { 2 } else { 1 };
+            }
+        } else {
+            break;
+        }
+    }
+    i
+}
+
+/// The program each pipeline stage runs, skipping variable assignments and wrappers.
+fn invoked_programs(command: &str) -> Vec<String> {
+    let mut programs = Vec::new();
+    for line in command.lines().filter(|l| !l.trim_start().starts_with('#')) {
+        for stage in split_pipeline(line) {
+            let mut words = shell_words(stage.trim().trim_start_matches(['(', '{']));
+            let idx = command_index(&words, 0);
+            if idx < words.len() {
+                programs.push(words.swap_remove(idx));
+            }
+        }
+    }
+    programs
+}
+
+/// Findings for programs in `command` that are neither builtins, shell functions
+/// nor on `search_path`. Aliases are expected to be expanded already.
+pub(super) fn analyze(command: &str, search_path: &str, functions: &[String]) -> Vec<SafetyFinding> {
+    let mut missing: Vec<String> = Vec::new();
+    for program in invoked_programs(command) {
+        let skip = BUILTINS.contains(&program.as_str())
+            || functions.contains(&program)
+            || program.contains(['/', '\\', '$', '`', '(', ')'])
+            || missing.contains(&program);
+        if !skip && crate::terminal::tools::find_on_path(&program, search_path).is_none() {
+            missing.push(program);
+        }
+    }
+    missing
+        .into_iter()
+        .map(|program| SafetyFinding {
+            category: "tools",
+            severity: Severity::Low,
+            message: format!("`{}` was not found on this shell's PATH", program),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invoked_programs() {
+        assert_eq!(
+            invoked_programs("FOO=1 sudo -u root -E apt update && cd /tmp | (yq '.a' f.yml); echo done"),
+            vec!["apt", "cd", "yq", "echo"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_flags_missing_programs_only() {
+        let functions = vec!["mkcd".to_string()];
+        let findings = analyze(
+            "ls | definitely-not-installed-xyz && cd .. && ./run.sh && mkcd x",
+            "/bin:/usr/bin",
+            &functions,
+        );
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].message.contains("definitely-not-installed-xyz"));
+        assert_eq!(findings[0].severity, Severity::Low);
+    }
+}
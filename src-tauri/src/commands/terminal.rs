@@ -1,4 +1,5 @@
 use tauri::{AppHandle, Manager};
+use tauri_plugin_store::StoreExt;
 
 use crate::state::AppState;
 use crate::terminal;
@@ -77,7 +78,17 @@ pub fn get_app_context(app: AppHandle) -> Option<terminal::AppContext> {
         .ok()
         .and_then(|g| *g);
 
+    // A new overlay session starts a new secret vault under the current privacy settings
+    terminal::filter::set_privacy(load_privacy_settings(&app));
+    terminal::filter::reset_vault();
+
     let result = terminal::detect_full_with_hwnd(pid, pre_captured, previous_hwnd);
+    if let Ok(mut pane) = state.multiplexer_pane.lock() {
+        *pane = result
+            .as_ref()
+            .and_then(|ctx| ctx.terminal.as_ref())
+            .and_then(|t| t.multiplexer.clone());
+    }
     eprintln!(
         "[terminal] detect_full({}) returned: {:?}",
         pid,
@@ -85,3 +96,47 @@ pub fn get_app_context(app: AppHandle) -> Option<terminal::AppContext> {
     );
     result
 }
+
+/// Read the privacy level ("privacyLevel") and user redaction rules ("privacyRules")
+/// from the settings store. Missing or invalid values fall back to the defaults.
+fn load_privacy_settings(app: &AppHandle) -> terminal::filter::PrivacySettings {
+    let store = match app.store("settings.json") {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("[terminal] Failed to open settings store: {}", e);
+            return Default::default();
+        }
+    };
+    let level = store
+        .get("privacyLevel")
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    let rules: Vec<String> = store
+        .get("privacyRules")
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    terminal::filter::PrivacySettings::new(level, &rules)
+}
+
+/// Substitute secret placeholders (`<SECRET_1:github_token>`) in generated text
+/// with the real values captured in this overlay session.
+///
+/// Used when the frontend copies a command to the clipboard itself.
+#[tauri::command]
+pub fn restore_secrets(text: String) -> String {
+    terminal::filter::restore_secrets(&text)
+}
+
+/// Write the shell integration script for `shell` ("bash", "zsh" or "fish") to
+/// the app config directory and return the line to add to the shell's rc file.
+///
+/// The rc file is not modified; the settings UI shows the line for the user to add.
+#[tauri::command]
+pub fn install_shell_hook(app: AppHandle, shell: String) -> Result<terminal::hooks::HookInstall, String> {
+    let dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to resolve config directory: {}", e))?
+        .join("shell");
+    terminal::hooks::install(&shell, &dir)
+}
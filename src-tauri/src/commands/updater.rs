@@ -42,7 +42,7 @@ async fn check_and_download(app: &tauri::AppHandle) {
         .and_then(|v| v.as_bool())
         .unwrap_or(true); // default: enabled
 
-    if !auto_update {
+    if !auto_update || super::policy::policy().disable_updater {
         if let Ok(mut status) = app.state::<UpdateState>().status.lock() {
             *status = UpdateStatus::Disabled;
         }
@@ -0,0 +1,475 @@
+//! Keystroke injection on Wayland, where xdotool cannot reach native windows.
+//!
+//! Three backends send the same clear-line, paste and Return chords the X11
+//! path sends through xdotool:
+//! - `wtype`, over the virtual-keyboard protocol of wlroots compositors
+//!   (sway, Hyprland, river)
+//! - the xdg-desktop-portal RemoteDesktop keyboard (GNOME, KDE). The restore
+//!   token is kept in settings.json so the consent dialog shows once.
+//! - `ydotoold`, by writing evdev events straight to its socket
+
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tauri::AppHandle;
+
+use crate::state::LinuxToolAvailability;
+use crate::terminal::wayland::Compositor;
+
+/// A key combination the paste and confirm paths send.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chord {
+    /// Ctrl+U: clear the current shell line
+    ClearLine,
+    /// Ctrl+Shift+V: paste into the terminal
+    Paste,
+    Return,
+}
+
+// evdev key codes (linux/input-event-codes.h)
+const KEY_LEFTCTRL: u16 = 29;
+const KEY_LEFTSHIFT: u16 = 42;
+const KEY_U: u16 = 22;
+const KEY_V: u16 = 47;
+const KEY_ENTER: u16 = 28;
+
+// X keysyms
+const XK_CONTROL_L: i32 = 0xffe3;
+const XK_SHIFT_L: i32 = 0xffe1;
+const XK_U: i32 = 0x0075;
+const XK_V: i32 = 0x0076;
+const XK_RETURN: i32 = 0xff0d;
+
+impl Chord {
+    /// Modifiers and key as evdev codes (ydotool).
+    fn evdev(self) -> (&'static [u16], u16) {
+        match self {
+            Chord::ClearLine => (&[KEY_LEFTCTRL], KEY_U),
+            Chord::Paste => (&[KEY_LEFTCTRL, KEY_LEFTSHIFT], KEY_V),
+            Chord::Return => (&[], KEY_ENTER),
+        }
+    }
+
+    /// Modifiers and key as keysyms (RemoteDesktop portal).
+    fn keysyms(self) -> (&'static [i32], i32) {
+        match self {
+            Chord::ClearLine => (&[XK_CONTROL_L], XK_U),
+            Chord::Paste => (&[XK_CONTROL_L, XK_SHIFT_L], XK_V),
+            Chord::Return => (&[], XK_RETURN),
+        }
+    }
+
+    fn wtype_args(self) -> &'static [&'static str] {
+        match self {
+            Chord::ClearLine => &["-M", "ctrl", "u", "-m", "ctrl"],
+            Chord::Paste => &["-M", "ctrl", "-M", "shift", "v", "-m", "shift", "-m", "ctrl"],
+            Chord::Return => &["-k", "Return"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputBackend {
+    Wtype,
+    Portal,
+    Ydotool(PathBuf),
+}
+
+/// Pick the input backend for this compositor from what was detected at startup.
+/// wtype needs the wlroots virtual-keyboard protocol, which KWin and GNOME Shell
+/// do not offer to clients; the portal covers those two. ydotool works
+/// everywhere but needs ydotoold running with access to /dev/uinput.
+pub fn choose(tools: &LinuxToolAvailability, compositor: &Compositor) -> Option<InputBackend> {
+    let wlroots = !matches!(compositor, Compositor::KWin | Compositor::GnomeShell);
+    if wlroots && tools.has_wtype {
+        Some(InputBackend::Wtype)
+    } else if tools.has_portal_keyboard {
+        Some(InputBackend::Portal)
+    } else {
+        tools.ydotool_socket.clone().map(InputBackend::Ydotool)
+    }
+}
+
+impl InputBackend {
+    /// Make sure keys can be sent before focus leaves the overlay, so a paste
+    /// that cannot go through still shows its clipboard hint.
+    pub fn prepare(&self, app: &AppHandle) -> Result<(), String> {
+        match self {
+            InputBackend::Portal => portal_session(app).map(|_| ()),
+            InputBackend::Wtype | InputBackend::Ydotool(_) => Ok(()),
+        }
+    }
+
+    pub fn send(&self, app: &AppHandle, chord: Chord) -> Result<(), String> {
+        match self {
+            InputBackend::Wtype => {
+                let output = std::process::Command::new("wtype")
+                    .args(chord.wtype_args())
+                    .output()
+                    .map_err(|e| format!("wtype failed: {}", e))?;
+                if output.status.success() {
+                    Ok(())
+                } else {
+                    Err(format!("wtype failed: {}", String::from_utf8_lossy(&output.stderr).trim()))
+                }
+            }
+            InputBackend::Portal => portal_send(app, chord),
+            InputBackend::Ydotool(socket) => ydotool_send(socket, chord),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// ydotool
+// ---------------------------------------------------------------------------
+
+const EV_SYN: u16 = 0;
+const EV_KEY: u16 = 1;
+const SYN_REPORT: u16 = 0;
+
+/// Pause between key events, ydotool's own default key delay.
+const YDOTOOL_KEY_DELAY: Duration = Duration::from_millis(12);
+
+/// The ydotoold socket: `$YDOTOOL_SOCKET`, else the runtime-dir and /tmp defaults.
+pub fn ydotool_socket() -> Option<PathBuf> {
+    let candidates = [
+        std::env::var("YDOTOOL_SOCKET").ok().map(PathBuf::from),
+        std::env::var("XDG_RUNTIME_DIR").ok().map(|dir| Path::new(&dir).join(".ydotool_socket")),
+        Some(PathBuf::from("/tmp/.ydotool_socket")),
+    ];
+    candidates.into_iter().flatten().find(|path| path.exists())
+}
+
+/// A `struct input_event` as ydotoold reads it off the socket: a zeroed
+/// timeval (two C longs), then type, code and value in native byte order.
+fn input_event(kind: u16, code: u16, value: i32) -> Vec<u8> {
+    let mut event = vec![0u8; 2 * std::mem::size_of::<std::os::raw::c_long>()];
+    event.extend_from_slice(&kind.to_ne_bytes());
+    event.extend_from_slice(&code.to_ne_bytes());
+    event.extend_from_slice(&value.to_ne_bytes());
+    event
+}
+
+/// Key presses for a chord: modifiers down, key down and up, modifiers up in
+/// reverse. `(code, 1)` is a press, `(code, 0)` a release.
+fn chord_presses<T: Copy>(mods: &[T], key: T) -> Vec<(T, u8)> {
+    mods.iter()
+        .map(|m| (*m, 1))
+        .chain([(key, 1), (key, 0)])
+        .chain(mods.iter().rev().map(|m| (*m, 0)))
+        .collect()
+}
+
+/// Each key event is followed by a SYN_REPORT, as `ydotool key` sends them.
+fn ydotool_send(socket: &Path, chord: Chord) -> Result<(), String> {
+    let client = UnixDatagram::unbound().map_err(|e| e.to_string())?;
+    client
+        .connect(socket)
+        .map_err(|e| format!("connect {} failed: {}", socket.display(), e))?;
+    let (mods, key) = chord.evdev();
+    for (code, value) in chord_presses(mods, key) {
+        for event in [input_event(EV_KEY, code, value as i32), input_event(EV_SYN, SYN_REPORT, 0)] {
+            client.send(&event).map_err(|e| format!("ydotoold write failed: {}", e))?;
+        }
+        std::thread::sleep(YDOTOOL_KEY_DELAY);
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// xdg-desktop-portal RemoteDesktop
+// ---------------------------------------------------------------------------
+
+const RESTORE_TOKEN_KEY: &str = "portalRestoreToken";
+
+/// The live RemoteDesktop session. Created on first use and kept for the rest
+/// of the run; cleared when a keystroke fails so the next paste starts over.
+static PORTAL_SESSION: std::sync::Mutex<Option<portal::Session>> = std::sync::Mutex::new(None);
+static PORTAL_STARTING: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// How long a paste waits for a session to start. Restoring a saved session
+/// fits in this; a first start waits on the consent dialog, so that paste
+/// falls back to the clipboard hint and the next one uses the session.
+const PORTAL_START_WAIT: Duration = Duration::from_secs(1);
+
+fn portal_send(app: &AppHandle, chord: Chord) -> Result<(), String> {
+    let session = portal_session(app)?;
+    session.send(chord).inspect_err(|_| {
+        if let Ok(mut guard) = PORTAL_SESSION.lock() {
+            *guard = None;
+        }
+    })
+}
+
+fn portal_session(app: &AppHandle) -> Result<portal::Session, String> {
+    use std::sync::atomic::Ordering;
+    use tauri_plugin_store::StoreExt;
+
+    if let Some(session) = PORTAL_SESSION.lock().ok().and_then(|guard| guard.clone()) {
+        return Ok(session);
+    }
+    if PORTAL_STARTING.swap(true, Ordering::SeqCst) {
+        return Err("remote desktop session is still starting".to_string());
+    }
+
+    let restore_token = app
+        .store("settings.json")
+        .ok()
+        .and_then(|store| store.get(RESTORE_TOKEN_KEY))
+        .and_then(|v| v.as_str().map(String::from));
+    let app = app.clone();
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        match portal::start(restore_token.as_deref()) {
+            Ok((session, token)) => {
+                // Tokens are single-use: every start hands out the next one
+                if let (Some(token), Ok(store)) = (token, app.store("settings.json")) {
+                    store.set(RESTORE_TOKEN_KEY, serde_json::json!(token));
+                }
+                if let Ok(mut guard) = PORTAL_SESSION.lock() {
+                    *guard = Some(session);
+                }
+                eprintln!("[wayland_input] RemoteDesktop portal session started");
+            }
+            Err(e) => eprintln!("[wayland_input] RemoteDesktop portal session failed: {}", e),
+        }
+        PORTAL_STARTING.store(false, Ordering::SeqCst);
+        let _ = tx.send(());
+    });
+
+    rx.recv_timeout(PORTAL_START_WAIT)
+        .map_err(|_| "waiting for remote desktop permission".to_string())?;
+    PORTAL_SESSION
+        .lock()
+        .ok()
+        .and_then(|guard| guard.clone())
+        .ok_or_else(|| "remote desktop session unavailable".to_string())
+}
+
+mod portal {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use zbus::blocking::Connection;
+    use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+
+    use super::{chord_presses, Chord};
+
+    const DESTINATION: &str = "org.freedesktop.portal.Desktop";
+    const PATH: &str = "/org/freedesktop/portal/desktop";
+    const REMOTE_DESKTOP: &str = "org.freedesktop.portal.RemoteDesktop";
+
+    const DEVICE_KEYBOARD: u32 = 1;
+    const PERSIST_UNTIL_REVOKED: u32 = 2;
+
+    /// The consent dialog waits on the user.
+    const START_TIMEOUT: Duration = Duration::from_secs(120);
+    const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[derive(Clone)]
+    pub(super) struct Session {
+        conn: Connection,
+        handle: OwnedObjectPath,
+    }
+
+    pub(super) fn keyboard_available() -> bool {
+        let Ok(conn) = Connection::session() else {
+            return false;
+        };
+        conn.call_method(
+            Some(DESTINATION),
+            PATH,
+            Some("org.freedesktop.DBus.Properties"),
+            "Get",
+            &(REMOTE_DESKTOP, "AvailableDeviceTypes"),
+        )
+        .ok()
+        .and_then(|reply| reply.body().deserialize::<OwnedValue>().ok())
+        .and_then(|value| u32::try_from(value).ok())
+        .is_some_and(|types| types & DEVICE_KEYBOARD != 0)
+    }
+
+    /// Create, configure and start a keyboard session. Returns the session and
+    /// the restore token for the next start.
+    pub(super) fn start(restore_token: Option<&str>) -> Result<(Session, Option<String>), String> {
+        let conn = Connection::session().map_err(|e| format!("D-Bus session failed: {}", e))?;
+
+        let created = request(&conn, "CreateSession", REQUEST_TIMEOUT, |token| {
+            let mut options = HashMap::new();
+            options.insert("handle_token", Value::from(token.to_string()));
+            options.insert("session_handle_token", Value::from("cmdk"));
+            call(&conn, "CreateSession", &(options,))
+        })?;
+        let handle = created
+            .get("session_handle")
+            .and_then(|v| <&str>::try_from(v).ok())
+            .and_then(|s| OwnedObjectPath::try_from(s.to_string()).ok())
+            .ok_or("CreateSession returned no session handle")?;
+
+        request(&conn, "SelectDevices", REQUEST_TIMEOUT, |token| {
+            let mut options = HashMap::new();
+            options.insert("handle_token", Value::from(token.to_string()));
+            options.insert("types", Value::from(DEVICE_KEYBOARD));
+            options.insert("persist_mode", Value::from(PERSIST_UNTIL_REVOKED));
+            if let Some(restore) = restore_token {
+                options.insert("restore_token", Value::from(restore.to_string()));
+            }
+            call(&conn, "SelectDevices", &(&handle, options))
+        })?;
+
+        let started = request(&conn, "Start", START_TIMEOUT, |token| {
+            let mut options = HashMap::new();
+            options.insert("handle_token", Value::from(token.to_string()));
+            call(&conn, "Start", &(&handle, "", options))
+        })?;
+        let token = started
+            .get("restore_token")
+            .and_then(|v| <&str>::try_from(v).ok())
+            .map(String::from);
+
+        Ok((Session { conn, handle }, token))
+    }
+
+    impl Session {
+        pub(super) fn send(&self, chord: Chord) -> Result<(), String> {
+            let (mods, key) = chord.keysyms();
+            for (keysym, pressed) in chord_presses(mods, key) {
+                let options: HashMap<&str, Value> = HashMap::new();
+                let state = pressed as u32;
+                call(&self.conn, "NotifyKeyboardKeysym", &(&self.handle, options, keysym, state))?;
+            }
+            Ok(())
+        }
+    }
+
+    fn call<B>(conn: &Connection, method: &str, body: &B) -> Result<zbus::Message, String>
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        conn.call_method(Some(DESTINATION), PATH, Some(REMOTE_DESKTOP), method, body)
+            .map_err(|e| format!("{} failed: {}", method, e))
+    }
+
+    /// Run a portal method that answers through a Request object: subscribe to
+    /// the Response signal at the predictable request path first, then call,
+    /// then wait for the results.
+    fn request<F>(
+        conn: &Connection,
+        method: &str,
+        timeout: Duration,
+        send: F,
+    ) -> Result<HashMap<String, OwnedValue>, String>
+    where
+        F: FnOnce(&str) -> Result<zbus::Message, String>,
+    {
+        let token = format!("cmdk_{}", method.to_lowercase());
+        let sender = conn
+            .unique_name()
+            .ok_or("D-Bus connection has no unique name")?
+            .trim_start_matches(':')
+            .replace('.', "_");
+        let request_path = format!("/org/freedesktop/portal/desktop/request/{}/{}", sender, token);
+
+        let proxy = zbus::blocking::Proxy::new(
+            conn,
+            DESTINATION,
+            request_path,
+            "org.freedesktop.portal.Request",
+        )
+        .map_err(|e| e.to_string())?;
+        let mut responses = proxy.receive_signal("Response").map_err(|e| e.to_string())?;
+
+        send(&token)?;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(responses.next());
+        });
+        let message = rx
+            .recv_timeout(timeout)
+            .ok()
+            .flatten()
+            .ok_or_else(|| format!("{} got no response", method))?;
+        let (code, results): (u32, HashMap<String, OwnedValue>) =
+            message.body().deserialize().map_err(|e| e.to_string())?;
+        match code {
+            0 => Ok(results),
+            1 => Err(format!("{} was denied", method)),
+            _ => Err(format!("{} was cancelled", method)),
+        }
+    }
+}
+
+/// Whether the portal offers a RemoteDesktop keyboard on this session bus.
+pub fn portal_keyboard_available() -> bool {
+    portal::keyboard_available()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_choose_backend() {
+        let tools = LinuxToolAvailability {
+            has_wtype: true,
+            has_portal_keyboard: true,
+            ..Default::default()
+        };
+        let sway = Compositor::Sway(PathBuf::from("/run/user/1000/sway-ipc.sock"));
+        assert_eq!(choose(&tools, &sway), Some(InputBackend::Wtype));
+        // KWin and GNOME Shell ignore wtype's virtual keyboard
+        assert_eq!(choose(&tools, &Compositor::GnomeShell), Some(InputBackend::Portal));
+
+        let tools = LinuxToolAvailability {
+            ydotool_socket: Some(PathBuf::from("/tmp/.ydotool_socket")),
+            ..Default::default()
+        };
+        assert_eq!(
+            choose(&tools, &Compositor::KWin),
+            Some(InputBackend::Ydotool(PathBuf::from("/tmp/.ydotool_socket")))
+        );
+        assert_eq!(choose(&LinuxToolAvailability::default(), &sway), None);
+    }
+
+    #[test]
+    fn test_ydotool_socket_events() {
+        let path = std::env::temp_dir().join(format!("cmdk-ydotool-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let daemon = UnixDatagram::bind(&path).unwrap();
+
+        // ydotoold reads as events arrive; the socket queues only a few datagrams
+        let client = {
+            let path = path.clone();
+            std::thread::spawn(move || ydotool_send(&path, Chord::Paste))
+        };
+
+        let size = input_event(0, 0, 0).len();
+        let mut keys = Vec::new();
+        let mut buf = vec![0u8; 64];
+        // 3 modifiers/keys down and up, each followed by SYN_REPORT
+        for _ in 0..12 {
+            assert_eq!(daemon.recv(&mut buf).unwrap(), size);
+            let kind = u16::from_ne_bytes([buf[size - 8], buf[size - 7]]);
+            let code = u16::from_ne_bytes([buf[size - 6], buf[size - 5]]);
+            let value = i32::from_ne_bytes(buf[size - 4..size].try_into().unwrap());
+            if kind == EV_KEY {
+                keys.push((code, value));
+            }
+        }
+        assert_eq!(
+            keys,
+            vec![
+                (KEY_LEFTCTRL, 1),
+                (KEY_LEFTSHIFT, 1),
+                (KEY_V, 1),
+                (KEY_V, 0),
+                (KEY_LEFTSHIFT, 0),
+                (KEY_LEFTCTRL, 0),
+            ]
+        );
+        assert_eq!(client.join().unwrap(), Ok(()));
+        let _ = std::fs::remove_file(&path);
+    }
+}
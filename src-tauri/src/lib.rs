@@ -15,14 +15,16 @@ fn chrono_free_timestamp() -> String {
 }
 
 use commands::{
-    ai::stream_ai_response,
+    ai::{preview_request, stream_ai_response},
+    audit::export_audit_log,
     history::{get_window_key, get_window_history, add_history_entry, clear_all_history},
     hotkey::register_hotkey,
     keychain::{delete_api_key, get_api_key, save_api_key},
     paste::{paste_to_terminal, confirm_terminal_command},
     permissions::{check_accessibility_permission, open_accessibility_settings, open_url, request_accessibility_permission},
-    safety::{check_destructive, get_destructive_explanation},
-    terminal::{get_app_context, get_terminal_context},
+    policy::get_policy,
+    safety::{analyze_command, check_command_provenance, check_destructive, get_destructive_explanation},
+    terminal::{get_app_context, get_terminal_context, install_shell_hook, restore_secrets},
     tray::setup_tray,
     window::{hide_overlay, show_overlay, set_overlay_position},
     models::{validate_api_key, fetch_models},
@@ -254,6 +256,9 @@ pub fn run() {
             // Migrate v0.2.4 users: set default provider if xAI key exists
             migrate_v024_api_key(app);
 
+            // Apply the administrator policy (if any) before the frontend reads settings
+            commands::policy::enforce_settings(app);
+
             // Set up menu bar tray icon with K.png branding
             setup_tray(app)?;
 
@@ -300,8 +305,13 @@ pub fn run() {
             request_accessibility_permission,
             get_terminal_context,
             get_app_context,
+            restore_secrets,
+            install_shell_hook,
             stream_ai_response,
+            preview_request,
             check_destructive,
+            analyze_command,
+            check_command_provenance,
             get_destructive_explanation,
             paste_to_terminal,
             confirm_terminal_command,
@@ -313,6 +323,8 @@ pub fn run() {
             set_overlay_position,
             get_usage_stats,
             reset_usage,
+            export_audit_log,
+            get_policy,
         ])
         .run(tauri::generate_context!())
         .expect("error while running CMD+K application");
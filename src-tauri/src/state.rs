@@ -48,6 +48,9 @@ pub struct TerminalContextSnapshot {
     pub cwd: Option<String>,
     pub shell_type: Option<String>,
     pub visible_output: Option<String>,
+    /// Git state of the CWD; absent in entries recorded before it was captured
+    #[serde(default)]
+    pub git: Option<crate::terminal::git::GitContext>,
 }
 
 /// A single history entry representing one query-response pair.
@@ -161,7 +164,8 @@ impl UsageAccumulator {
     }
 }
 
-/// Cached availability of Linux CLI tools (xdotool, xclip, wl-copy).
+/// Cached availability of Linux CLI tools (xdotool, xclip, wl-copy) and of the
+/// Wayland input backends (wtype, RemoteDesktop portal, ydotoold).
 /// Checked once at startup to avoid repeated `which` calls on every paste.
 #[cfg(target_os = "linux")]
 #[derive(Debug, Clone, Default)]
@@ -169,19 +173,34 @@ pub struct LinuxToolAvailability {
     pub has_xdotool: bool,
     pub has_xclip: bool,
     pub has_wl_copy: bool,
+    pub has_wtype: bool,
+    pub has_portal_keyboard: bool,
+    pub ydotool_socket: Option<std::path::PathBuf>,
 }
 
 #[cfg(target_os = "linux")]
 impl LinuxToolAvailability {
     pub fn detect() -> Self {
+        use crate::commands::wayland_input;
+
+        // The Wayland backends are only probed in a Wayland session
+        let wayland = std::env::var("WAYLAND_DISPLAY").is_ok();
         let result = Self {
             has_xdotool: Self::command_exists("xdotool"),
             has_xclip: Self::command_exists("xclip"),
             has_wl_copy: Self::command_exists("wl-copy"),
+            has_wtype: wayland && Self::command_exists("wtype"),
+            has_portal_keyboard: wayland && wayland_input::portal_keyboard_available(),
+            ydotool_socket: wayland.then(wayland_input::ydotool_socket).flatten(),
         };
         eprintln!(
-            "[state] LinuxToolAvailability: xdotool={}, xclip={}, wl-copy={}",
-            result.has_xdotool, result.has_xclip, result.has_wl_copy
+            "[state] LinuxToolAvailability: xdotool={}, xclip={}, wl-copy={}, wtype={}, portal-keyboard={}, ydotool-socket={:?}",
+            result.has_xdotool,
+            result.has_xclip,
+            result.has_wl_copy,
+            result.has_wtype,
+            result.has_portal_keyboard,
+            result.ydotool_socket
         );
         result
     }
@@ -218,13 +237,17 @@ pub struct AppState {
     /// `get_app_context` so it is used at most once.
     pub pre_captured_text: Mutex<Option<String>>,
     /// The window key for the current overlay invocation.
-    /// Format: "bundle_id:shell_pid" for terminals/IDEs, "bundle_id:app_pid" for other apps.
+    /// Format: "bundle_id:shell_pid" for terminals/IDEs ("bundle_id:shell_pid:tmux:session:%pane"
+    /// inside a multiplexer), "bundle_id:app_pid" for other apps.
     /// Set synchronously in the hotkey handler before toggle_overlay().
     pub current_window_key: Mutex<Option<String>>,
     /// Pre-captured CWD from the focused terminal tab (AX-derived).
     /// Set in the hotkey handler for IDEs with integrated terminals before the overlay
     /// steals focus. Consumed by compute_window_key to disambiguate multi-tab shell PIDs.
     pub pre_captured_focused_cwd: Mutex<Option<String>>,
+    /// Active tmux/screen/zellij pane of the current overlay invocation, set by
+    /// `get_app_context`. Paste and confirm type into it through the multiplexer.
+    pub multiplexer_pane: Mutex<Option<crate::terminal::multiplexer::MultiplexerPane>>,
     /// Per-window query history. Key is the window key, value is a bounded deque of entries.
     /// Capped at MAX_HISTORY_PER_WINDOW entries per window, MAX_TRACKED_WINDOWS total windows.
     pub history: Mutex<HashMap<String, VecDeque<HistoryEntry>>>,
@@ -240,6 +263,9 @@ pub struct AppState {
     pub usage: Mutex<UsageAccumulator>,
     /// Cached OpenRouter model pricing: model_id -> (input_price_per_m, output_price_per_m).
     pub openrouter_pricing: Mutex<HashMap<String, (f64, f64)>>,
+    /// Audit chain state and the context of the most recent AI request,
+    /// used to write audit records on paste/confirm.
+    pub audit: Mutex<crate::commands::audit::AuditState>,
     /// Cached Linux tool availability (xdotool, xclip, wl-copy).
     #[cfg(target_os = "linux")]
     pub linux_tools: LinuxToolAvailability,
@@ -255,11 +281,13 @@ impl Default for AppState {
             pre_captured_text: Mutex::new(None),
             current_window_key: Mutex::new(None),
             pre_captured_focused_cwd: Mutex::new(None),
+            multiplexer_pane: Mutex::new(None),
             history: Mutex::new(HashMap::new()),
             previous_hwnd: Mutex::new(None),
             last_position: Mutex::new(None),
             usage: Mutex::new(UsageAccumulator::default()),
             openrouter_pricing: Mutex::new(HashMap::new()),
+            audit: Mutex::new(crate::commands::audit::AuditState::default()),
             #[cfg(target_os = "linux")]
             linux_tools: LinuxToolAvailability::detect(),
         }
@@ -0,0 +1,366 @@
+//! Aliases and function names defined in the user's shell.
+//!
+//! With the cmd-k hooks installed, the shell dumps its live definitions at the
+//! first prompt (see `hooks::read_alias_dump`). Otherwise the usual rc files are
+//! scanned for `alias`, `abbr` and function definitions, which misses anything
+//! defined by sourced frameworks or plugins. Both sources use shell syntax, so one
+//! parser reads them.
+//!
+//! The prompt lists them so suggestions may use `k` for `kubectl`, and the safety
+//! analysis expands aliases so `k delete ns prod` is judged as `kubectl delete`.
+
+use std::path::{Path, PathBuf};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::{filter, hooks, process};
+
+/// Aliases listed in the prompt.
+const MAX_ALIASES: usize = 40;
+
+/// Functions listed in the prompt.
+const MAX_FUNCTIONS: usize = 30;
+
+/// Longest alias value shown in the prompt.
+const MAX_VALUE_CHARS: usize = 80;
+
+/// `name() {` / `name () (` style function definitions.
+static FUNCTION_DEF_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^([A-Za-z_][\w.:-]*)\s*\(\)\s*(\{|\(|$)").unwrap());
+
+/// Alias names and values, and function names, of one shell.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ShellAliases {
+    /// (name, expansion) in definition order, later definitions replacing earlier ones
+    pub aliases: Vec<(String, String)>,
+    pub functions: Vec<String>,
+    /// "hooks" for a live dump from the shell, "config" for rc file scanning
+    pub source: String,
+}
+
+impl ShellAliases {
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty() && self.functions.is_empty()
+    }
+
+    /// Lines for the AI prompt.
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if !self.aliases.is_empty() {
+            let shown: Vec<String> = self
+                .aliases
+                .iter()
+                .take(MAX_ALIASES)
+                .map(|(name, value)| {
+                    let value = if value.chars().count() > MAX_VALUE_CHARS {
+                        value.chars().take(MAX_VALUE_CHARS).collect::<String>() + "..."
+                    } else {
+                        value.clone()
+                    };
+                    format!("{}='{}'", name, value)
+                })
+                .collect();
+            lines.push(format!("Shell aliases (may be used in commands): {}", shown.join(", ")));
+        }
+        if !self.functions.is_empty() {
+            let shown: Vec<&str> = self.functions.iter().take(MAX_FUNCTIONS).map(String::as_str).collect();
+            lines.push(format!("Shell functions: {}", shown.join(", ")));
+        }
+        lines
+    }
+
+    fn add_alias(&mut self, name: String, value: String) {
+        self.aliases.retain(|(n, _)| *n != name);
+        self.aliases.push((name, value));
+    }
+
+    fn add_function(&mut self, name: String) {
+        if !name.starts_with('_') && !self.functions.contains(&name) {
+            self.functions.push(name);
+        }
+    }
+}
+
+/// Split a line into shell words, honouring quotes and backslashes, up to an
+/// unquoted `;`, `&`, `|` or a `#` that starts a word.
+fn words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                for q in chars.by_ref() {
+                    if q == '\'' {
+                        break;
+                    }
+                    current.push(q);
+                }
+            }
+            '"' => {
+                in_word = true;
+                while let Some(q) = chars.next() {
+                    match q {
+                        '"' => break,
+                        '\\' if matches!(chars.peek(), Some('"' | '\\' | '$' | '`')) => {
+                            current.extend(chars.next());
+                        }
+                        _ => current.push(q),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                current.extend(chars.next());
+            }
+            ';' | '&' | '|' => break,
+            '#' if !in_word => break,
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            _ => {
+                in_word = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(current);
+    }
+    words
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(|c: char| c.is_whitespace() || matches!(c, '\'' | '"' | '$' | '/' | '='))
+}
+
+/// Parse alias, abbreviation and function definitions from rc file text or a
+/// hook dump. Handles `alias k=kubectl` (bash, zsh, fish), `alias k kubectl` and
+/// `abbr -a k kubectl` (fish), `name() {`, `function name` and the dump's
+/// `function <name>` lines.
+pub fn parse_definitions(text: &str, into: &mut ShellAliases) {
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(caps) = FUNCTION_DEF_RE.captures(line) {
+            into.add_function(caps[1].to_string());
+            continue;
+        }
+        let w = words(line);
+        let Some(keyword) = w.first() else { continue };
+        match keyword.as_str() {
+            "alias" => {
+                let mut args = w[1..].iter().skip_while(|a| a.starts_with('-'));
+                let Some(first) = args.next() else { continue };
+                let (name, value) = match first.split_once('=') {
+                    Some((name, value)) => (name.to_string(), value.to_string()),
+                    // fish: alias name value...
+                    None => (first.clone(), args.cloned().collect::<Vec<_>>().join(" ")),
+                };
+                if valid_name(&name) && !value.trim().is_empty() {
+                    into.add_alias(name, value);
+                }
+            }
+            "abbr" => {
+                let mut rest: Vec<&String> = Vec::new();
+                let mut iter = w[1..].iter();
+                while let Some(arg) = iter.next() {
+                    match arg.as_str() {
+                        "--position" | "-p" | "--regex" | "-r" | "--function" | "-f" | "--set-cursor" => {
+                            iter.next();
+                        }
+                        a if a.starts_with('-') => {}
+                        _ => rest.push(arg),
+                    }
+                }
+                if let [name, value @ ..] = rest.as_slice() {
+                    let value: Vec<&str> = value.iter().map(|v| v.as_str()).collect();
+                    if valid_name(name) && !value.is_empty() {
+                        into.add_alias(name.to_string(), value.join(" "));
+                    }
+                }
+            }
+            "function" => {
+                if let Some(name) = w.get(1) {
+                    let name = name.trim_end_matches("()").trim_end_matches('{');
+                    if valid_name(name) {
+                        into.add_function(name.to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// rc files that commonly define aliases for `shell`, most general first so
+/// later files override.
+fn config_files(shell: &str, var: &dyn Fn(&str) -> Option<String>, home: &Path) -> Vec<PathBuf> {
+    match shell {
+        "bash" => [".profile", ".bash_profile", ".bashrc", ".aliases", ".bash_aliases"]
+            .iter()
+            .map(|f| home.join(f))
+            .collect(),
+        "zsh" => {
+            let dir = var("ZDOTDIR").map_or_else(|| home.to_path_buf(), PathBuf::from);
+            [".zprofile", ".zshrc", ".aliases", ".zsh_aliases"]
+                .iter()
+                .map(|f| dir.join(f))
+                .collect()
+        }
+        "fish" => {
+            let dir = var("XDG_CONFIG_HOME")
+                .map_or_else(|| home.join(".config"), PathBuf::from)
+                .join("fish");
+            let mut files = vec![dir.join("config.fish")];
+            if let Ok(entries) = std::fs::read_dir(dir.join("conf.d")) {
+                let mut conf: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+                conf.sort();
+                files.extend(conf);
+            }
+            files
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Scan the rc files of `shell`. fish autoloads one function per file from
+/// `functions/`, so those file names are functions too.
+fn from_config(shell: &str, var: &dyn Fn(&str) -> Option<String>, home: &Path) -> ShellAliases {
+    let mut found = ShellAliases {
+        source: "config".to_string(),
+        ..Default::default()
+    };
+    for path in config_files(shell, var, home) {
+        if let Ok(text) = std::fs::read_to_string(&path) {
+            parse_definitions(&text, &mut found);
+        }
+    }
+    if shell == "fish" {
+        let dir = var("XDG_CONFIG_HOME")
+            .map_or_else(|| home.join(".config"), PathBuf::from)
+            .join("fish")
+            .join("functions");
+        if let Ok(entries) = std::fs::read_dir(dir) {
+            let mut names: Vec<String> = entries
+                .flatten()
+                .filter_map(|e| e.file_name().to_str()?.strip_suffix(".fish").map(String::from))
+                .collect();
+            names.sort();
+            for name in names {
+                found.add_function(name);
+            }
+        }
+    }
+    found
+}
+
+/// Aliases and functions of `shell` running as `shell_pid`: the hooks' live dump
+/// when present, else the rc files. Values pass through `filter_sensitive`, since
+/// aliases sometimes embed tokens.
+pub fn detect(shell: &str, shell_pid: Option<i32>) -> Option<ShellAliases> {
+    let mut found = match shell_pid.and_then(hooks::read_alias_dump) {
+        Some(dump) => {
+            let mut found = ShellAliases {
+                source: "hooks".to_string(),
+                ..Default::default()
+            };
+            parse_definitions(&dump, &mut found);
+            found
+        }
+        None => {
+            let env = shell_pid.map(process::get_process_env).unwrap_or_default();
+            let var = |key: &str| -> Option<String> {
+                env.get(key)
+                    .cloned()
+                    .or_else(|| std::env::var(key).ok())
+                    .filter(|v| !v.is_empty())
+            };
+            let home = var("HOME").map(PathBuf::from)?;
+            from_config(shell, &var, &home)
+        }
+    };
+    // fish implements aliases as functions; list each name once
+    let ShellAliases { aliases, functions, .. } = &mut found;
+    functions.retain(|f| !aliases.iter().any(|(name, _)| name == f));
+    for (_, value) in aliases.iter_mut() {
+        *value = filter::filter_sensitive(value);
+    }
+    eprintln!(
+        "[aliases] {} aliases, {} functions from {}",
+        found.aliases.len(),
+        found.functions.len(),
+        found.source
+    );
+    (!found.is_empty()).then_some(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> ShellAliases {
+        let mut found = ShellAliases::default();
+        parse_definitions(text, &mut found);
+        found
+    }
+
+    #[test]
+    fn test_parse_bash_and_zsh_aliases() {
+        let found = parse(
+            "alias k='kubectl'\n\
+             alias ll=\"ls -la\"  # long listing\n\
+             alias -g G='| grep'\n\
+             alias say='echo '\\''hi'\\'''\n\
+             alias k=kubecolor\n\
+             export PATH=$PATH:/opt/bin\n",
+        );
+        assert_eq!(
+            found.aliases,
+            vec![
+                ("ll".to_string(), "ls -la".to_string()),
+                ("G".to_string(), "| grep".to_string()),
+                ("say".to_string(), "echo 'hi'".to_string()),
+                ("k".to_string(), "kubecolor".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_fish_aliases_and_abbreviations() {
+        let found = parse("alias gs 'git status'\nabbr -a -- gco 'git checkout'\nabbr --add --position anywhere L '| less'\n");
+        assert_eq!(
+            found.aliases,
+            vec![
+                ("gs".to_string(), "git status".to_string()),
+                ("gco".to_string(), "git checkout".to_string()),
+                ("L".to_string(), "| less".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_functions() {
+        let found = parse("mkcd() {\n  mkdir -p \"$1\" && cd \"$1\"\n}\nfunction gclone {\n}\nfunction _private\nfunction kctx --description 'switch'\n");
+        assert_eq!(found.functions, vec!["mkcd", "gclone", "kctx"]);
+    }
+
+    #[test]
+    fn test_describe() {
+        let found = ShellAliases {
+            aliases: vec![("k".into(), "kubectl".into())],
+            functions: vec!["mkcd".into()],
+            source: "hooks".into(),
+        };
+        assert_eq!(
+            found.describe(),
+            vec!["Shell aliases (may be used in commands): k='kubectl'", "Shell functions: mkcd"]
+        );
+    }
+}
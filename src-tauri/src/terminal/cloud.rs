@@ -0,0 +1,280 @@
+//! Kubernetes and cloud CLI context for the foreground shell.
+//!
+//! Reads the active kubeconfig context and namespace, the AWS profile and region,
+//! and the gcloud project the way the CLIs themselves resolve them: environment
+//! variables of the shell first, then the tools' config files.
+//!
+//! Only the shell's startup environment is visible (see `process::get_process_env`),
+//! so `export AWS_PROFILE=...` typed into an already-running shell is missed. Config
+//! file changes (`kubectl config use-context`, `gcloud config set project`) are seen.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::process;
+
+/// Cloud targets the shell's CLIs would currently act on.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CloudContext {
+    /// kubeconfig `current-context`
+    pub kube_context: Option<String>,
+    /// Namespace of the current context ("default" when the context sets none)
+    pub kube_namespace: Option<String>,
+    pub aws_profile: Option<String>,
+    pub aws_region: Option<String>,
+    pub gcloud_project: Option<String>,
+}
+
+impl CloudContext {
+    pub fn is_empty(&self) -> bool {
+        *self == CloudContext::default()
+    }
+
+    /// One line per tool, for the AI prompt.
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(ref ctx) = self.kube_context {
+            let ns = self.kube_namespace.as_deref().unwrap_or("default");
+            lines.push(format!("Kubernetes context: {} (namespace: {})", ctx, ns));
+        }
+        match (&self.aws_profile, &self.aws_region) {
+            (Some(p), Some(r)) => lines.push(format!("AWS profile: {} (region: {})", p, r)),
+            (Some(p), None) => lines.push(format!("AWS profile: {}", p)),
+            (None, Some(r)) => lines.push(format!("AWS region: {}", r)),
+            (None, None) => {}
+        }
+        if let Some(ref project) = self.gcloud_project {
+            lines.push(format!("GCP project: {}", project));
+        }
+        lines
+    }
+}
+
+/// Detect the cloud context for the shell with the given PID.
+///
+/// Returns None when no tool has an active context.
+pub fn detect(shell_pid: Option<i32>) -> Option<CloudContext> {
+    let env = shell_pid.map(process::get_process_env).unwrap_or_default();
+    let var = |key: &str| -> Option<String> {
+        env.get(key)
+            .cloned()
+            .or_else(|| std::env::var(key).ok())
+            .filter(|v| !v.is_empty())
+    };
+    let home = var("HOME").or_else(|| var("USERPROFILE")).map(PathBuf::from)?;
+
+    let ctx = resolve(&var, &home);
+    eprintln!("[cloud] detected {:?}", ctx);
+    (!ctx.is_empty()).then_some(ctx)
+}
+
+fn resolve(var: &dyn Fn(&str) -> Option<String>, home: &Path) -> CloudContext {
+    let read = |path: &Path| std::fs::read_to_string(path).ok();
+    let mut ctx = CloudContext::default();
+
+    // Kubernetes: KUBECONFIG may list several files; the first current-context wins.
+    let kubeconfigs: Vec<PathBuf> = match var("KUBECONFIG") {
+        Some(list) => std::env::split_paths(&list).collect(),
+        None => vec![home.join(".kube").join("config")],
+    };
+    let contents: Vec<String> = kubeconfigs.iter().filter_map(|p| read(p)).collect();
+    if let Some(current) = contents.iter().find_map(|c| kube_current_context(c)) {
+        ctx.kube_namespace = Some(
+            contents
+                .iter()
+                .find_map(|c| kube_context_namespace(c, &current))
+                .unwrap_or_else(|| "default".to_string()),
+        );
+        ctx.kube_context = Some(current);
+    }
+
+    // AWS: explicit profile variables, region from env or the profile's config section.
+    ctx.aws_profile = var("AWS_PROFILE").or_else(|| var("AWS_DEFAULT_PROFILE"));
+    ctx.aws_region = var("AWS_REGION").or_else(|| var("AWS_DEFAULT_REGION"));
+    if ctx.aws_region.is_none() {
+        let config = var("AWS_CONFIG_FILE")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home.join(".aws").join("config"));
+        if let Some(content) = read(&config) {
+            let section = match ctx.aws_profile.as_deref() {
+                None | Some("default") => "default".to_string(),
+                Some(p) => format!("profile {}", p),
+            };
+            ctx.aws_region = ini_value(&content, &section, "region");
+        }
+    }
+
+    // gcloud: env override, else the active named configuration.
+    ctx.gcloud_project = var("CLOUDSDK_CORE_PROJECT").or_else(|| {
+        let dir = var("CLOUDSDK_CONFIG").map(PathBuf::from).unwrap_or_else(|| {
+            match var("APPDATA") {
+                Some(appdata) if cfg!(target_os = "windows") => PathBuf::from(appdata).join("gcloud"),
+                _ => home.join(".config").join("gcloud"),
+            }
+        });
+        let active = var("CLOUDSDK_ACTIVE_CONFIG_NAME")
+            .or_else(|| read(&dir.join("active_config")).map(|s| s.trim().to_string()))
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "default".to_string());
+        let content = read(&dir.join("configurations").join(format!("config_{}", active)))?;
+        ini_value(&content, "core", "project")
+    });
+
+    ctx
+}
+
+/// Strip YAML scalar quoting.
+fn unquote(value: &str) -> &str {
+    let v = value.trim();
+    v.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .or_else(|| v.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')))
+        .unwrap_or(v)
+}
+
+/// Top-level `current-context:` of a kubeconfig.
+fn kube_current_context(content: &str) -> Option<String> {
+    content
+        .lines()
+        .find_map(|l| l.strip_prefix("current-context:"))
+        .map(unquote)
+        .filter(|v| !v.is_empty())
+        .map(String::from)
+}
+
+/// Namespace of the named entry in a kubeconfig's top-level `contexts:` list.
+///
+/// A line-based reading of the layout kubectl writes; not a general YAML parser.
+fn kube_context_namespace(content: &str, context: &str) -> Option<String> {
+    let mut in_contexts = false;
+    let mut entry: HashMap<&str, &str> = HashMap::new();
+    let mut entries = Vec::new();
+
+    for line in content.lines() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        let top_level = !line.starts_with(' ') && !line.starts_with('-');
+        if top_level {
+            if in_contexts {
+                break;
+            }
+            in_contexts = line.trim_end() == "contexts:";
+            continue;
+        }
+        if !in_contexts {
+            continue;
+        }
+        let trimmed = line.trim_start();
+        let item = match trimmed.strip_prefix("- ") {
+            Some(rest) => {
+                if !entry.is_empty() {
+                    entries.push(std::mem::take(&mut entry));
+                }
+                rest
+            }
+            None => trimmed,
+        };
+        if let Some((key, value)) = item.split_once(':') {
+            entry.insert(key.trim(), unquote(value));
+        }
+    }
+    if !entry.is_empty() {
+        entries.push(entry);
+    }
+
+    entries
+        .iter()
+        .find(|e| e.get("name").copied() == Some(context))
+        .and_then(|e| e.get("namespace"))
+        .filter(|ns| !ns.is_empty())
+        .map(|ns| ns.to_string())
+}
+
+/// Value of `key` in `[section]` of an INI-style file (AWS and gcloud config).
+fn ini_value(content: &str, section: &str, key: &str) -> Option<String> {
+    let mut in_section = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            in_section = name.trim() == section;
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        if let Some((k, v)) = line.split_once('=') {
+            if k.trim() == key {
+                let v = v.trim();
+                return (!v.is_empty()).then(|| v.to_string());
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KUBECONFIG: &str = "\
+apiVersion: v1
+clusters:
+- cluster:
+    server: https://prod.example.com
+  name: prod-cluster
+contexts:
+- context:
+    cluster: prod-cluster
+    namespace: payments
+    user: admin
+  name: prod-eu
+- context:
+    cluster: dev-cluster
+    user: dev
+  name: \"dev\"
+current-context: prod-eu
+kind: Config
+";
+
+    #[test]
+    fn test_kube_current_context_and_namespace() {
+        assert_eq!(kube_current_context(KUBECONFIG).as_deref(), Some("prod-eu"));
+        assert_eq!(kube_context_namespace(KUBECONFIG, "prod-eu").as_deref(), Some("payments"));
+        assert_eq!(kube_context_namespace(KUBECONFIG, "dev"), None);
+        assert_eq!(kube_context_namespace(KUBECONFIG, "missing"), None);
+    }
+
+    #[test]
+    fn test_empty_current_context() {
+        assert_eq!(kube_current_context("current-context: \"\"\n"), None);
+    }
+
+    #[test]
+    fn test_ini_value() {
+        let aws = "[default]\nregion = us-east-1\n\n[profile prod]\nregion=eu-west-1\noutput = json\n";
+        assert_eq!(ini_value(aws, "default", "region").as_deref(), Some("us-east-1"));
+        assert_eq!(ini_value(aws, "profile prod", "region").as_deref(), Some("eu-west-1"));
+        assert_eq!(ini_value(aws, "profile dev", "region"), None);
+    }
+
+    #[test]
+    fn test_describe() {
+        let ctx = CloudContext {
+            kube_context: Some("prod-eu".into()),
+            aws_profile: Some("prod".into()),
+            aws_region: Some("eu-west-1".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            ctx.describe(),
+            vec![
+                "Kubernetes context: prod-eu (namespace: default)",
+                "AWS profile: prod (region: eu-west-1)",
+            ]
+        );
+        assert!(CloudContext::default().is_empty());
+    }
+}
@@ -0,0 +1,537 @@
+//! Shells inside containers: `docker exec`, `podman exec`, `kubectl exec`,
+//! `toolbox enter` and `distrobox enter`.
+//!
+//! The terminal's shell is on the host, but commands typed at the prompt run in
+//! the container, where the CWD, distribution and package manager differ. The
+//! container or pod comes from the CLI's command line; the image from the
+//! engine; the in-container shell, CWD and distribution from `/proc/<pid>/root`
+//! when the container's processes are visible to this user (Linux, rootless
+//! podman or root).
+
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use super::process;
+
+/// Bound on `docker inspect` / `kubectl get pod`, further capped by the time
+/// left before the caller's detection deadline.
+const QUERY_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// docker/podman global options that take a value (before the subcommand).
+const ENGINE_GLOBAL_VALUE_OPTIONS: &[&str] = &[
+    "-H", "--host", "-c", "--context", "--connection", "--config", "-l", "--log-level", "--url",
+    "--identity", "--root", "--runroot", "--tlscacert", "--tlscert", "--tlskey",
+];
+
+/// docker/podman global options that select the engine and are kept for `inspect`.
+const ENGINE_SELECT_OPTIONS: &[&str] = &["-H", "--host", "-c", "--context", "--connection", "--url", "--identity"];
+
+/// `docker exec` / `podman exec` options that take a value.
+const EXEC_VALUE_OPTIONS: &[&str] = &[
+    "-e", "--env", "--env-file", "-u", "--user", "-w", "--workdir", "--detach-keys", "--preserve-fds",
+];
+
+/// kubectl options that take a value, global or of `exec`.
+const KUBECTL_VALUE_OPTIONS: &[&str] = &[
+    "-c", "--container", "-n", "--namespace", "--context", "--cluster", "--kubeconfig", "-f",
+    "--filename", "--pod-running-timeout", "-s", "--server", "--user", "--as", "--as-group", "--token",
+    "--request-timeout", "-v",
+];
+
+/// kubectl options that select the cluster and are kept for `get pod`.
+const KUBECTL_SELECT_OPTIONS: &[&str] = &["--context", "--cluster", "--kubeconfig", "-s", "--server", "--user"];
+
+/// `toolbox` and `distrobox enter` options that take a value.
+const TOOLBOX_VALUE_OPTIONS: &[&str] = &[
+    "-c", "--container", "-d", "--distro", "-r", "--release", "--log-level", "-n", "--name", "-a",
+    "--additional-flags",
+];
+
+/// Image name tokens that give away the distribution: `python:3.12-slim-bookworm`,
+/// `node:20-alpine3.19`, `fedora-toolbox:40`.
+const DISTRO_HINTS: &[(&str, &str)] = &[
+    ("alpine", "alpine"),
+    ("ubuntu", "ubuntu"),
+    ("jammy", "ubuntu"),
+    ("noble", "ubuntu"),
+    ("focal", "ubuntu"),
+    ("debian", "debian"),
+    ("bookworm", "debian"),
+    ("bullseye", "debian"),
+    ("buster", "debian"),
+    ("slim", "debian"),
+    ("fedora", "fedora"),
+    ("centos", "centos"),
+    ("rockylinux", "rocky"),
+    ("almalinux", "almalinux"),
+    ("ubi", "rhel"),
+    ("amazonlinux", "amzn"),
+    ("archlinux", "arch"),
+    ("opensuse", "opensuse"),
+    ("busybox", "busybox"),
+];
+
+/// A shell running in a container, with what could be found out about it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContainerSession {
+    /// What the user ran: "docker", "podman", "kubectl", "toolbox" or "distrobox"
+    pub program: String,
+    /// Container name or ID; the pod for kubectl
+    pub name: String,
+    /// kubectl: the pod's namespace
+    pub namespace: Option<String>,
+    /// kubectl: the container within the pod
+    pub container: Option<String>,
+    pub image: Option<String>,
+    /// `/etc/os-release` ID ("alpine", "debian"), or guessed from the image name
+    pub distro: Option<String>,
+    /// `/etc/os-release` PRETTY_NAME: "Alpine Linux v3.19"
+    pub os: Option<String>,
+    pub user: Option<String>,
+    /// Shell running in the container: "ash", "bash"
+    pub shell: Option<String>,
+    /// CWD inside the container
+    pub cwd: Option<String>,
+    /// Engine binary and the global options that select its daemon, context
+    /// or cluster. Internal; not sent to the frontend.
+    #[serde(skip)]
+    engine_args: Vec<String>,
+}
+
+impl ContainerSession {
+    /// "container web" or "pod api-7d9f (container app, namespace prod)".
+    pub fn target(&self) -> String {
+        if self.program != "kubectl" {
+            return format!("container {}", self.name);
+        }
+        let details: Vec<String> = [
+            self.container.as_ref().map(|c| format!("container {}", c)),
+            self.namespace.as_ref().map(|n| format!("namespace {}", n)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if details.is_empty() {
+            format!("pod {}", self.name)
+        } else {
+            format!("pod {} ({})", self.name, details.join(", "))
+        }
+    }
+
+    /// Short distribution label for the prompt: the os-release ID, else the image.
+    pub fn distro_label(&self) -> Option<&str> {
+        self.distro.as_deref().or(self.image.as_deref())
+    }
+
+    /// Context lines for the AI prompt, first line "Container: inside container web (alpine) ...".
+    pub fn describe(&self) -> Vec<String> {
+        let label = self.distro_label().map(|d| format!(" ({})", d)).unwrap_or_default();
+        let mut lines = vec![format!(
+            "Container: inside {}{}, entered with {} (commands run in the container, not on the host)",
+            self.target(),
+            label,
+            self.program
+        )];
+        if let Some(image) = &self.image {
+            lines.push(format!("Container image: {}", image));
+        }
+        if let Some(os) = &self.os {
+            lines.push(format!("Container OS: {}", os));
+        }
+        if let Some(user) = &self.user {
+            lines.push(format!("Container user: {}", user));
+        }
+        if let Some(shell) = &self.shell {
+            lines.push(format!("Container shell: {}", shell));
+        }
+        if let Some(cwd) = &self.cwd {
+            lines.push(format!("Container CWD: {}", cwd));
+        }
+        lines
+    }
+}
+
+/// Options and positional arguments of a command line.
+#[derive(Default)]
+struct ParsedArgs {
+    /// Options in order; short clusters are split, so `-it` gives `-i` and `-t`
+    options: Vec<(String, Option<String>)>,
+    positionals: Vec<String>,
+}
+
+impl ParsedArgs {
+    fn value(&self, names: &[&str]) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(name, value)| names.contains(&name.as_str()) && value.is_some())
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    fn has(&self, names: &[&str]) -> bool {
+        self.options.iter().any(|(name, _)| names.contains(&name.as_str()))
+    }
+}
+
+/// Parse argv (without argv[0]) until `--` or until `done` says the positionals
+/// seen so far are enough; the rest is the command to run in the container.
+fn parse_args(args: &[String], value_options: &[&str], done: impl Fn(&[String]) -> bool) -> ParsedArgs {
+    let mut parsed = ParsedArgs::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--" || done(&parsed.positionals) {
+            break;
+        }
+        if let Some(long) = arg.strip_prefix("--") {
+            let (name, value) = match long.split_once('=') {
+                Some((name, value)) => (format!("--{}", name), Some(value.to_string())),
+                None if value_options.contains(&arg.as_str()) => (arg.clone(), iter.next().cloned()),
+                None => (arg.clone(), None),
+            };
+            parsed.options.push((name, value));
+        } else if let Some(cluster) = arg.strip_prefix('-').filter(|c| !c.is_empty()) {
+            for (i, c) in cluster.char_indices() {
+                let name = format!("-{}", c);
+                if value_options.contains(&name.as_str()) {
+                    let rest = &cluster[i + c.len_utf8()..];
+                    let value = if rest.is_empty() { iter.next().cloned() } else { Some(rest.to_string()) };
+                    parsed.options.push((name, value));
+                    break;
+                }
+                parsed.options.push((name, None));
+            }
+        } else {
+            parsed.positionals.push(arg.clone());
+        }
+    }
+    parsed
+}
+
+/// Global options among `parsed` that are in `keep`, as arguments to pass again.
+fn kept_options(parsed: &ParsedArgs, keep: &[&str]) -> Vec<String> {
+    parsed
+        .options
+        .iter()
+        .filter(|(name, value)| keep.contains(&name.as_str()) && value.is_some())
+        .flat_map(|(name, value)| [name.clone(), value.clone().unwrap_or_default()])
+        .collect()
+}
+
+/// `docker [global options] [container] exec -it [options] NAME COMMAND`.
+fn parse_engine_exec(program: &str, args: &[String]) -> Option<ContainerSession> {
+    let value_options: Vec<&str> = ENGINE_GLOBAL_VALUE_OPTIONS.iter().chain(EXEC_VALUE_OPTIONS).copied().collect();
+    let parsed = parse_args(args.get(1..)?, &value_options, |positionals| {
+        positionals.iter().position(|p| p == "exec").is_some_and(|i| positionals.len() > i + 1)
+    });
+    let exec = parsed.positionals.iter().position(|p| p == "exec")?;
+    // `docker exec` and `docker container exec`, nothing else
+    if exec > 1 || (exec == 1 && parsed.positionals[0] != "container") {
+        return None;
+    }
+    // Without a TTY it runs one command, not an interactive shell
+    if !parsed.has(&["-t", "--tty"]) {
+        return None;
+    }
+    let name = parsed.positionals.get(exec + 1)?.clone();
+    let mut engine_args = vec![program.to_string()];
+    engine_args.extend(kept_options(&parsed, ENGINE_SELECT_OPTIONS));
+    Some(ContainerSession {
+        program: program.to_string(),
+        name,
+        user: parsed.value(&["-u", "--user"]).map(String::from),
+        cwd: parsed.value(&["-w", "--workdir"]).map(String::from),
+        engine_args,
+        ..Default::default()
+    })
+}
+
+/// `kubectl [options] exec [-it] POD [-c CONTAINER] [-n NAMESPACE] -- COMMAND`.
+fn parse_kubectl_exec(args: &[String]) -> Option<ContainerSession> {
+    let parsed = parse_args(args.get(1..)?, KUBECTL_VALUE_OPTIONS, |_| false);
+    if parsed.positionals.first().map(String::as_str) != Some("exec") {
+        return None;
+    }
+    if !parsed.has(&["-t", "--tty"]) {
+        return None;
+    }
+    let name = parsed.positionals.get(1)?;
+    let name = name.strip_prefix("pod/").or_else(|| name.strip_prefix("pods/")).unwrap_or(name);
+    let mut engine_args = vec!["kubectl".to_string()];
+    engine_args.extend(kept_options(&parsed, KUBECTL_SELECT_OPTIONS));
+    Some(ContainerSession {
+        program: "kubectl".to_string(),
+        name: name.to_string(),
+        namespace: parsed.value(&["-n", "--namespace"]).map(String::from),
+        container: parsed.value(&["-c", "--container"]).map(String::from),
+        engine_args,
+        ..Default::default()
+    })
+}
+
+/// `toolbox enter [NAME]`, `distrobox enter [NAME]` and `distrobox-enter [NAME]`.
+/// Without a name, toolbox's default container depends on the host release, so
+/// it is left to the `podman exec` it runs.
+fn parse_toolbox_enter(program: &str, args: &[String]) -> Option<ContainerSession> {
+    let parsed = parse_args(args.get(1..)?, TOOLBOX_VALUE_OPTIONS, |_| false);
+    let mut positionals = parsed.positionals.iter();
+    if program != "distrobox-enter" && positionals.next().map(String::as_str) != Some("enter") {
+        return None;
+    }
+    let is_distrobox = program.starts_with("distrobox");
+    let name = parsed
+        .value(&["-c", "--container", "-n", "--name"])
+        .map(String::from)
+        .or_else(|| positionals.next().cloned())
+        .or_else(|| is_distrobox.then(|| "my-distrobox".to_string()))?;
+    Some(ContainerSession {
+        program: if is_distrobox { "distrobox" } else { "toolbox" }.to_string(),
+        name,
+        engine_args: vec!["podman".to_string()],
+        ..Default::default()
+    })
+}
+
+fn parse_wrapper(name: &str, args: &[String]) -> Option<ContainerSession> {
+    match name {
+        "docker" | "podman" => parse_engine_exec(name, args),
+        "kubectl" => parse_kubectl_exec(args),
+        "toolbox" | "distrobox" | "distrobox-enter" => parse_toolbox_enter(name, args),
+        _ => None,
+    }
+}
+
+/// Combine the wrapper chain, outermost first: the innermost engine call names
+/// the container, an outer toolbox or distrobox is what the user ran.
+fn from_wrappers(chain: &[(String, Vec<String>)]) -> Option<ContainerSession> {
+    let mut session = chain.iter().rev().find_map(|(name, args)| parse_wrapper(name, args))?;
+    let tool = chain.iter().find_map(|(name, _)| match name.as_str() {
+        "toolbox" => Some("toolbox"),
+        "distrobox" | "distrobox-enter" => Some("distrobox"),
+        _ => None,
+    });
+    if let Some(tool) = tool {
+        session.program = tool.to_string();
+    }
+    Some(session)
+}
+
+/// Guess the distribution from the image name: `python:3.12-slim-bookworm` is Debian.
+fn distro_from_image(image: &str) -> Option<String> {
+    let last = image.rsplit('/').next().unwrap_or(image).to_lowercase();
+    last.split(['-', ':', '.', '_', '@']).find_map(|token| {
+        DISTRO_HINTS
+            .iter()
+            .find(|(hint, _)| token.starts_with(hint))
+            .map(|(_, distro)| distro.to_string())
+    })
+}
+
+/// Parse `/etc/os-release`: (ID, PRETTY_NAME).
+fn parse_os_release(text: &str) -> (Option<String>, Option<String>) {
+    let field = |key: &str| {
+        text.lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+            .map(|value| value.trim().trim_matches('"').to_string())
+            .filter(|value| !value.is_empty())
+    };
+    (field("ID"), field("PRETTY_NAME").or_else(|| field("NAME")))
+}
+
+/// Ask the engine for the container's image and (docker/podman) its init PID.
+/// Skipped when `deadline` has already passed.
+fn query_engine(session: &mut ContainerSession, deadline: Instant) -> Option<i32> {
+    let timeout = QUERY_TIMEOUT.min(deadline.saturating_duration_since(Instant::now()));
+    if timeout.is_zero() {
+        return None;
+    }
+    let (program, globals) = session.engine_args.split_first()?;
+    let mut cmd = std::process::Command::new(program);
+    cmd.args(globals);
+    if program == "kubectl" {
+        if let Some(namespace) = &session.namespace {
+            cmd.args(["-n", namespace]);
+        }
+        cmd.args(["get", "pod", &session.name, "-o"]).arg(
+            "jsonpath={range .spec.containers[*]}{.name}{\"\\t\"}{.image}{\"\\n\"}{end}",
+        );
+        let out = process::run_bounded(cmd, timeout)?;
+        let rows: Vec<(&str, &str)> = out.lines().filter_map(|line| line.split_once('\t')).collect();
+        let wanted = session.container.as_deref();
+        session.image = rows
+            .iter()
+            .find(|(name, _)| Some(*name) == wanted)
+            .or(rows.first())
+            .map(|(_, image)| image.to_string());
+        return None;
+    }
+    let image_field = if program == "podman" { "{{.ImageName}}" } else { "{{.Config.Image}}" };
+    cmd.args(["inspect", "--type", "container", "--format"])
+        .arg(format!("{{{{.State.Pid}}}}\t{}", image_field))
+        .arg(&session.name);
+    let out = process::run_bounded(cmd, timeout)?;
+    let (pid, image) = out.trim().split_once('\t')?;
+    session.image = Some(image.to_string()).filter(|i| !i.is_empty());
+    pid.parse().ok().filter(|&pid| pid > 0)
+}
+
+/// Start time of a process in clock ticks since boot (`/proc/<pid>/stat` field 22).
+#[cfg(target_os = "linux")]
+fn start_time(pid: i32) -> u64 {
+    std::fs::read_to_string(format!("/proc/{}/stat", pid))
+        .ok()
+        .and_then(|stat| stat.rsplit_once(')')?.1.split_whitespace().nth(19)?.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Find the newest shell in the container's mount namespace and read its CWD,
+/// user and distribution through `/proc/<pid>/root`. Needs the same access as
+/// ptrace, so it works for rootless podman, toolbox and distrobox but not for
+/// a root docker daemon's containers.
+#[cfg(target_os = "linux")]
+fn inspect_namespace(init_pid: i32, session: &mut ContainerSession) {
+    let Ok(mnt) = std::fs::read_link(format!("/proc/{}/ns/mnt", init_pid)) else {
+        return;
+    };
+    // Container shells are often busybox, so go by comm rather than the executable
+    let shell = std::fs::read_dir("/proc")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|e| e.file_name().to_str()?.parse::<i32>().ok())
+        .filter(|&pid| std::fs::read_link(format!("/proc/{}/ns/mnt", pid)).is_ok_and(|ns| ns == mnt))
+        .filter_map(|pid| {
+            let comm = std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+            let comm = comm.trim().trim_start_matches('-').to_string();
+            process::is_known_shell(&comm).then_some((pid, comm))
+        })
+        .max_by_key(|(pid, _)| start_time(*pid));
+
+    let root_pid = shell.as_ref().map_or(init_pid, |(pid, _)| *pid);
+    let root = format!("/proc/{}/root", root_pid);
+    let os_release = std::fs::read_to_string(format!("{}/etc/os-release", root))
+        .or_else(|_| std::fs::read_to_string(format!("{}/usr/lib/os-release", root)));
+    if let Ok(text) = os_release {
+        let (id, pretty) = parse_os_release(&text);
+        session.distro = id.or(session.distro.take());
+        session.os = pretty;
+    }
+
+    let Some((pid, comm)) = shell else {
+        return;
+    };
+    session.shell = Some(comm);
+    if let Ok(cwd) = std::fs::read_link(format!("/proc/{}/cwd", pid)) {
+        session.cwd = Some(cwd.to_string_lossy().into_owned());
+    }
+    let uid = std::fs::read_to_string(format!("/proc/{}/status", pid))
+        .ok()
+        .and_then(|status| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix("Uid:"))
+                .and_then(|ids| ids.split_whitespace().next().map(String::from))
+        });
+    if let (Some(uid), Ok(passwd)) = (uid, std::fs::read_to_string(format!("{}/etc/passwd", root))) {
+        session.user = passwd.lines().find_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            (fields.get(2) == Some(&uid.as_str())).then(|| fields[0].to_string())
+        });
+    }
+}
+
+/// Detect a container shell started from the terminal's shell, or None.
+///
+/// The engine query must finish by `deadline`; past it the session is still
+/// returned, from the command line alone.
+pub fn detect(shell_pid: i32, deadline: Instant) -> Option<ContainerSession> {
+    let mut session = from_wrappers(&process::find_container_wrappers(shell_pid))?;
+    let init_pid = query_engine(&mut session, deadline);
+    session.distro = session.image.as_deref().and_then(distro_from_image);
+    #[cfg(target_os = "linux")]
+    if let Some(pid) = init_pid {
+        inspect_namespace(pid, &mut session);
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = init_pid;
+    eprintln!(
+        "[container] {} in {} ({})",
+        session.program,
+        session.target(),
+        session.distro_label().unwrap_or("unknown image")
+    );
+    Some(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn chain(lines: &[&str]) -> Vec<(String, Vec<String>)> {
+        lines
+            .iter()
+            .map(|line| {
+                let args = args(line);
+                (args[0].rsplit('/').next().unwrap().to_string(), args)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_engine_exec() {
+        let s = from_wrappers(&chain(&["docker --context prod exec -itu www-data -w /srv/app web sh -l"])).unwrap();
+        assert_eq!((s.program.as_str(), s.name.as_str()), ("docker", "web"));
+        assert_eq!(s.user.as_deref(), Some("www-data"));
+        assert_eq!(s.cwd.as_deref(), Some("/srv/app"));
+        assert_eq!(s.engine_args, args("docker --context prod"));
+        let s = from_wrappers(&chain(&["podman container exec --tty --interactive --env=A=1 db bash"])).unwrap();
+        assert_eq!(s.target(), "container db");
+        // No TTY, or not exec at all
+        assert!(from_wrappers(&chain(&["docker exec web ls /"])).is_none());
+        assert!(from_wrappers(&chain(&["docker run -it alpine"])).is_none());
+        assert!(from_wrappers(&chain(&["docker compose exec -it web sh"])).is_none());
+    }
+
+    #[test]
+    fn test_parse_kubectl_exec() {
+        let s = from_wrappers(&chain(&["kubectl exec -it pod/api-7d9f -c app -n prod --context eks -- /bin/sh"])).unwrap();
+        assert_eq!(s.target(), "pod api-7d9f (container app, namespace prod)");
+        assert_eq!(s.engine_args, args("kubectl --context eks"));
+        let s = from_wrappers(&chain(&["kubectl --namespace=dev exec worker-0 --stdin --tty -- bash"])).unwrap();
+        assert_eq!(s.target(), "pod worker-0 (namespace dev)");
+        assert!(from_wrappers(&chain(&["kubectl exec api -- env"])).is_none());
+        assert!(from_wrappers(&chain(&["kubectl logs -f api"])).is_none());
+    }
+
+    #[test]
+    fn test_toolbox_and_distrobox_chains() {
+        // toolbox names the tool, the podman exec it runs names the container
+        let s = from_wrappers(&chain(&[
+            "toolbox enter",
+            "podman exec --interactive --tty --user me --workdir /home/me fedora-toolbox-40 capsh",
+        ]))
+        .unwrap();
+        assert_eq!((s.program.as_str(), s.name.as_str()), ("toolbox", "fedora-toolbox-40"));
+        let s = from_wrappers(&chain(&["distrobox enter dev", "/usr/bin/distrobox-enter dev"])).unwrap();
+        assert_eq!((s.program.as_str(), s.name.as_str()), ("distrobox", "dev"));
+        let s = from_wrappers(&chain(&["distrobox-enter"])).unwrap();
+        assert_eq!(s.name, "my-distrobox");
+        assert!(from_wrappers(&chain(&["toolbox list"])).is_none());
+    }
+
+    #[test]
+    fn test_distro_hints() {
+        assert_eq!(distro_from_image("alpine:3.19").as_deref(), Some("alpine"));
+        assert_eq!(distro_from_image("python:3.12-slim-bookworm").as_deref(), Some("debian"));
+        assert_eq!(distro_from_image("node:20-alpine3.19").as_deref(), Some("alpine"));
+        assert_eq!(distro_from_image("registry.fedoraproject.org/fedora-toolbox:40").as_deref(), Some("fedora"));
+        assert_eq!(distro_from_image("registry.access.redhat.com/ubi9/ubi-minimal").as_deref(), Some("rhel"));
+        assert_eq!(distro_from_image("ghcr.io/acme/api:1.4"), None);
+        let (id, pretty) = parse_os_release("NAME=\"Alpine Linux\"\nID=alpine\nPRETTY_NAME=\"Alpine Linux v3.19\"\n");
+        assert_eq!((id.as_deref(), pretty.as_deref()), (Some("alpine"), Some("Alpine Linux v3.19")));
+    }
+}
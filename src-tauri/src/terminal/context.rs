@@ -2,9 +2,15 @@
 //!
 //! Replaces the hard-coded 25-line truncation with an intelligent pipeline:
 //! 1. Strip ANSI escape sequences and non-printable control characters
-//! 2. Compute a token budget from the selected model's context window
-//! 3. Segment terminal output by command boundaries (shell prompt patterns)
-//! 4. Truncate oldest complete command+output segments to fit within budget
+//! 2. Compress repetitive output: progress-bar frames, runs of similar lines,
+//!    the middle of long stack traces
+//! 3. Compute a token budget from the selected model's context window
+//! 4. Segment terminal output by command boundaries (shell prompt patterns)
+//! 5. Truncate oldest complete command+output segments to fit within budget
+//!
+//! When the terminal emits OSC 133 shell-integration marks, segmentation uses them
+//! instead of the prompt regex: they give exact prompt, command, output and exit-code
+//! boundaries regardless of how the prompt looks.
 //!
 //! This module is purely cross-platform -- no `cfg(target_os)` anywhere.
 
@@ -20,6 +26,16 @@ const DEFAULT_CONTEXT_WINDOW: u32 = 128_000;
 /// Approximate characters per token for budget estimation.
 const CHARS_PER_TOKEN: u32 = 4;
 
+/// Runs of at least this many similar lines are collapsed.
+const MIN_SIMILAR_RUN: usize = 5;
+
+/// Leading lines kept from a collapsed run; the last line is kept as well.
+const SIMILAR_RUN_HEAD: usize = 2;
+
+/// Lines kept at the start and end of a long stack trace.
+const STACK_HEAD: usize = 6;
+const STACK_TAIL: usize = 4;
+
 /// Matches all common ANSI escape sequences:
 /// 1. CSI sequences: \x1b[ ... (letter) -- covers colors, cursor movement, erase
 /// 2. OSC sequences: \x1b] ... (BEL or ST) -- covers title setting, hyperlinks
@@ -59,6 +75,24 @@ static PROMPT_RE: Lazy<Regex> = Lazy::new(|| {
     .unwrap()
 });
 
+/// OSC 133 semantic prompt marks (BEL or ST terminated):
+/// A = prompt start, B = command start, C = output start, D[;exit] = command finished.
+static OSC133_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\x1b\]133;([ABCD])((?:;[^\x07\x1b]*)?)(?:\x07|\x1b\\)").unwrap()
+});
+
+/// Stack frame lines: JavaScript/Java/Rust `at ...`, Python `File "..."`, numbered
+/// Rust/C++ frames, Go `\t/path/file.go:12`.
+static STACK_FRAME_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^(?:\s+at\s|\s+File\s"|\s*\d+:\s+\S|\s*#\d+\s|\t\S+\.go:\d+)"#).unwrap()
+});
+
+/// Lines that must survive compression even inside a run of similar lines.
+static IMPORTANT_LINE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:error|fail(?:s|ed|ure)?|panic(?:ked)?|fatal|exception|traceback|denied)\b|\berr!")
+        .unwrap()
+});
+
 /// Known context window sizes by model ID prefix (in tokens).
 static CONTEXT_WINDOWS: Lazy<Vec<(&'static str, u32)>> = Lazy::new(|| {
     vec![
@@ -85,8 +119,28 @@ static CONTEXT_WINDOWS: Lazy<Vec<(&'static str, u32)>> = Lazy::new(|| {
 struct CommandSegment {
     /// The prompt line + command text (may be empty for pre-prompt output).
     prompt_and_command: String,
+    /// The command alone, without the prompt.
+    command: String,
     /// Output lines following the command.
     output: String,
+    /// Exit status reported by an OSC 133 `D` mark.
+    exit_code: Option<i32>,
+}
+
+impl CommandSegment {
+    /// Text of the segment as sent to the model. A non-zero exit status is
+    /// appended so the model knows the command failed.
+    fn render(&self) -> String {
+        let mut out = if self.output.is_empty() {
+            self.prompt_and_command.clone()
+        } else {
+            format!("{}\n{}", self.prompt_and_command, self.output.trim_end())
+        };
+        if let Some(code) = self.exit_code.filter(|c| *c != 0) {
+            out.push_str(&format!("\n[exit status {}]", code));
+        }
+        out
+    }
 }
 
 /// Strip ANSI escape sequences and non-printable control characters.
@@ -98,6 +152,111 @@ fn strip_ansi_and_control(text: &str) -> String {
     no_control.into_owned()
 }
 
+/// The last non-empty frame of a line redrawn with carriage returns, as the
+/// terminal finally showed it.
+fn last_cr_frame(line: &str) -> &str {
+    if !line.contains('\r') {
+        return line;
+    }
+    line.rsplit('\r').find(|frame| !frame.trim().is_empty()).unwrap_or("")
+}
+
+/// Shape of a line for run detection: the whole line with digit runs and
+/// progress-bar characters masked and whitespace squeezed, so "Compiling crate1
+/// v0.1.1" and "Compiling crate2 v0.2.10" or "[===>  ] 12%" and "[====> ] 15%"
+/// match, while `ls -l` or `ps` rows that differ in a name do not. None for lines
+/// that must never be collapsed.
+fn line_shape(line: &str) -> Option<String> {
+    let body = line.trim_start();
+    if body.trim_end().is_empty()
+        || is_prompt_line(line)
+        || IMPORTANT_LINE_RE.is_match(line)
+        || STACK_FRAME_RE.is_match(line)
+    {
+        return None;
+    }
+    let indent = line.len() - body.len();
+    let mut shape = format!("{}:", indent);
+    for c in body.trim_end().chars() {
+        let masked = match c {
+            '0'..='9' => '0',
+            '=' | '#' | '>' | '-' | '*' | '.' | '█' | '░' | '▒' | '▓' | '■' | '□' => '=',
+            c if c.is_whitespace() => ' ',
+            c => c,
+        };
+        if !shape.ends_with(masked) || masked.is_alphabetic() {
+            shape.push(masked);
+        }
+    }
+    Some(shape)
+}
+
+/// Keep the head and tail of every long stack trace. A trace is a block of frame
+/// lines, including single lines between two frames (Python source lines, Go
+/// function names).
+fn elide_stack_traces(lines: Vec<&str>) -> Vec<String> {
+    let is_frame: Vec<bool> = lines.iter().map(|l| STACK_FRAME_RE.is_match(l)).collect();
+    let mut out = Vec::with_capacity(lines.len());
+    let mut i = 0;
+    while i < lines.len() {
+        if !is_frame[i] {
+            out.push(lines[i].to_string());
+            i += 1;
+            continue;
+        }
+        let mut end = i + 1;
+        while end < lines.len()
+            && (is_frame[end] || (end + 1 < lines.len() && is_frame[end + 1] && !is_prompt_line(lines[end])))
+        {
+            end += 1;
+        }
+        if end - i > STACK_HEAD + STACK_TAIL + 1 {
+            let elided = is_frame[i + STACK_HEAD..end - STACK_TAIL].iter().filter(|f| **f).count();
+            out.extend(lines[i..i + STACK_HEAD].iter().map(|l| l.to_string()));
+            out.push(format!("[… {} more frames …]", elided));
+            out.extend(lines[end - STACK_TAIL..end].iter().map(|l| l.to_string()));
+        } else {
+            out.extend(lines[i..end].iter().map(|l| l.to_string()));
+        }
+        i = end;
+    }
+    out
+}
+
+/// Collapse runs of similar lines to their first lines, a count and the last line.
+fn collapse_similar_runs(lines: Vec<String>) -> Vec<String> {
+    let shapes: Vec<Option<String>> = lines.iter().map(|l| line_shape(l)).collect();
+    let mut out = Vec::with_capacity(lines.len());
+    let mut i = 0;
+    while i < lines.len() {
+        let mut end = i + 1;
+        if shapes[i].is_some() {
+            while end < lines.len() && shapes[end] == shapes[i] {
+                end += 1;
+            }
+        }
+        let run = end - i;
+        if run >= MIN_SIMILAR_RUN {
+            out.extend(lines[i..i + SIMILAR_RUN_HEAD].iter().cloned());
+            out.push(format!("[… {} similar lines …]", run - SIMILAR_RUN_HEAD - 1));
+            out.push(lines[end - 1].clone());
+        } else {
+            out.extend(lines[i..end].iter().cloned());
+        }
+        i = end;
+    }
+    out
+}
+
+/// Compress repetitive output so that one noisy build or install cannot crowd
+/// everything else out of the budget: keep only the final frame of lines redrawn
+/// with `\r`, elide the middle of long stack traces, and collapse runs of similar
+/// lines. Error and failure lines are never collapsed.
+fn compress_output(text: &str) -> String {
+    let lines: Vec<&str> = text.split('\n').map(last_cr_frame).collect();
+    collapse_similar_runs(elide_stack_traces(lines)).join("\n")
+}
+
 /// Compute the terminal context budget in characters.
 /// Uses 12% of the context window, multiplied by 4 chars/token.
 fn compute_budget_chars(context_window: u32) -> usize {
@@ -110,6 +269,19 @@ fn compute_budget_chars(context_window: u32) -> usize {
     budget_tokens * CHARS_PER_TOKEN as usize
 }
 
+/// Returns true if the line starts with a recognized shell prompt pattern.
+pub(crate) fn is_prompt_line(line: &str) -> bool {
+    PROMPT_RE.is_match(line)
+}
+
+/// Text following the prompt on a prompt line.
+fn command_after_prompt(line: &str) -> String {
+    PROMPT_RE
+        .find(line)
+        .map(|m| line[m.end()..].trim().to_string())
+        .unwrap_or_default()
+}
+
 /// Segment terminal text into command+output pairs by detecting shell prompt patterns.
 fn segment_commands(text: &str) -> Vec<CommandSegment> {
     let lines: Vec<&str> = text.lines().collect();
@@ -122,8 +294,10 @@ fn segment_commands(text: &str) -> Vec<CommandSegment> {
             // Save previous segment if non-empty
             if !current_prompt.is_empty() || !current_output.is_empty() {
                 segments.push(CommandSegment {
+                    command: command_after_prompt(&current_prompt),
                     prompt_and_command: current_prompt,
                     output: current_output,
+                    exit_code: None,
                 });
             }
             current_prompt = line.to_string();
@@ -137,14 +311,110 @@ fn segment_commands(text: &str) -> Vec<CommandSegment> {
     // Push last segment
     if !current_prompt.is_empty() || !current_output.is_empty() {
         segments.push(CommandSegment {
+            command: command_after_prompt(&current_prompt),
             prompt_and_command: current_prompt,
             output: current_output,
+            exit_code: None,
         });
     }
 
     segments
 }
 
+/// Which part of a command cycle the text between two OSC 133 marks belongs to.
+#[derive(Clone, Copy, PartialEq)]
+enum MarkPart {
+    /// Before the first prompt mark, or after a `D` mark
+    Output,
+    Prompt,
+    Command,
+}
+
+/// Segment raw terminal text using OSC 133 marks. Returns None when the text
+/// carries no marks, so the caller falls back to prompt-regex segmentation.
+fn segment_osc133(raw: &str) -> Option<Vec<CommandSegment>> {
+    if !OSC133_RE.is_match(raw) {
+        return None;
+    }
+
+    fn push(
+        segments: &mut Vec<CommandSegment>,
+        prompt: &str,
+        command: &str,
+        output: &str,
+        exit_code: Option<i32>,
+    ) {
+        let prompt = prompt.trim_end_matches('\n');
+        let output = output.trim_start_matches('\n');
+        if prompt.trim().is_empty() && output.trim().is_empty() && exit_code.is_none() {
+            return;
+        }
+        let mut output = output.to_string();
+        if !output.is_empty() && !output.ends_with('\n') {
+            output.push('\n');
+        }
+        segments.push(CommandSegment {
+            prompt_and_command: prompt.to_string(),
+            command: command.trim().to_string(),
+            output,
+            exit_code,
+        });
+    }
+
+    let mut segments = Vec::new();
+    let (mut prompt, mut command, mut output) = (String::new(), String::new(), String::new());
+    let mut exit_code = None;
+    let mut part = MarkPart::Output;
+    let mut last = 0;
+
+    for caps in OSC133_RE.captures_iter(raw) {
+        let mark = caps.get(0).unwrap();
+        let chunk = strip_ansi_and_control(&raw[last..mark.start()]);
+        last = mark.end();
+        match part {
+            MarkPart::Prompt => prompt.push_str(&chunk),
+            MarkPart::Command => {
+                prompt.push_str(&chunk);
+                command.push_str(&chunk);
+            }
+            MarkPart::Output => output.push_str(&chunk),
+        }
+        match &caps[1] {
+            "A" => {
+                push(&mut segments, &prompt, &command, &output, exit_code);
+                prompt.clear();
+                command.clear();
+                output.clear();
+                exit_code = None;
+                part = MarkPart::Prompt;
+            }
+            "B" => part = MarkPart::Command,
+            "C" => part = MarkPart::Output,
+            _ => {
+                // D;<exit> -- a bare D means the command was aborted before running
+                exit_code = caps[2]
+                    .trim_start_matches(';')
+                    .split(';')
+                    .next()
+                    .and_then(|code| code.parse().ok());
+                part = MarkPart::Output;
+            }
+        }
+    }
+
+    let tail = strip_ansi_and_control(&raw[last..]);
+    match part {
+        MarkPart::Prompt => prompt.push_str(&tail),
+        MarkPart::Command => {
+            prompt.push_str(&tail);
+            command.push_str(&tail);
+        }
+        MarkPart::Output => output.push_str(&tail),
+    }
+    push(&mut segments, &prompt, &command, &output, exit_code);
+    Some(segments)
+}
+
 /// Truncate text to fit within a character budget, preserving the most recent
 /// complete command+output segments and dropping the oldest.
 fn smart_truncate(text: &str, budget_chars: usize) -> String {
@@ -153,8 +423,12 @@ fn smart_truncate(text: &str, budget_chars: usize) -> String {
         return text.to_string();
     }
 
-    let segments = segment_commands(text);
+    truncate_segments(text, segment_commands(text), budget_chars)
+}
 
+/// Keep the newest segments that fit within the budget. `text` is the rendered
+/// form of `segments`, used for tail truncation when there are no boundaries.
+fn truncate_segments(text: &str, segments: Vec<CommandSegment>, budget_chars: usize) -> String {
     // Fallback: no prompt patterns found -- simple tail truncation
     if segments.is_empty()
         || (segments.len() == 1 && segments[0].prompt_and_command.is_empty())
@@ -173,7 +447,7 @@ fn smart_truncate(text: &str, budget_chars: usize) -> String {
     let mut total_chars: usize = 0;
 
     for segment in segments.iter().rev() {
-        let seg_chars = segment.prompt_and_command.len() + segment.output.len();
+        let seg_chars = segment.render().len();
         if total_chars + seg_chars > budget_chars && !selected.is_empty() {
             break; // Drop this and all older segments
         }
@@ -195,26 +469,31 @@ fn smart_truncate(text: &str, budget_chars: usize) -> String {
             .find('\n')
             .map(|i| start + i + 1)
             .unwrap_or(start);
-        return format!("{}\n{}", seg.prompt_and_command, &output[start..]);
+        return CommandSegment {
+            prompt_and_command: seg.prompt_and_command.clone(),
+            command: seg.command.clone(),
+            output: output[start..].to_string(),
+            exit_code: seg.exit_code,
+        }
+        .render();
     }
 
-    // Reassemble selected segments
-    selected
+    render_segments(&selected)
+}
+
+/// Reassemble segments into terminal text.
+fn render_segments(segments: &[&CommandSegment]) -> String {
+    segments
         .iter()
-        .map(|s| {
-            if s.output.is_empty() {
-                s.prompt_and_command.clone()
-            } else {
-                format!("{}\n{}", s.prompt_and_command, s.output.trim_end())
-            }
-        })
+        .map(|s| s.render())
         .collect::<Vec<_>>()
         .join("\n")
 }
 
 /// Prepare terminal context for AI consumption.
 ///
-/// Pipeline: normalize CRLF -> strip ANSI + control chars -> compute budget -> smart truncate.
+/// Pipeline: normalize CRLF -> strip ANSI + control chars -> compress -> compute budget -> smart truncate.
+/// Text carrying OSC 133 marks is segmented by the marks before they are stripped.
 /// Returns empty string for empty input.
 ///
 /// Note: `filter_sensitive()` should be called AFTER this function in the prompt building path.
@@ -222,12 +501,73 @@ pub fn prepare_terminal_context(raw_text: &str, context_window: u32) -> String {
     if raw_text.is_empty() {
         return String::new();
     }
+    let budget_chars = compute_budget_chars(context_window);
+    if let Some(mut segments) = segment_osc133(raw_text) {
+        for segment in &mut segments {
+            segment.output = compress_output(&segment.output);
+        }
+        let text = render_segments(&segments.iter().collect::<Vec<_>>());
+        if text.len() <= budget_chars {
+            return text;
+        }
+        return truncate_segments(&text, segments, budget_chars);
+    }
     let stripped = strip_ansi_and_control(raw_text);
     if stripped.is_empty() {
         return String::new();
     }
-    let budget_chars = compute_budget_chars(context_window);
-    smart_truncate(&stripped, budget_chars)
+    smart_truncate(&compress_output(&stripped), budget_chars)
+}
+
+/// Maximum error output kept for a failed command.
+const FAILED_OUTPUT_LINES: usize = 60;
+
+/// The command a "fix the last error" request is about.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FailedCommand {
+    /// The command as typed, without the prompt
+    pub command: String,
+    /// Tail of its output
+    pub output: String,
+    /// Exit status, when shell integration reported one
+    pub exit_code: Option<i32>,
+}
+
+/// Find the most recent failed command in raw terminal text.
+///
+/// With OSC 133 marks (or cmd-k shell hooks) this is the latest command with a
+/// non-zero exit status. Without exit codes, it is the latest command whose
+/// output contains an error line; None when no output does, rather than
+/// guessing at a command that may have succeeded.
+pub(crate) fn last_failed_command(raw_text: &str) -> Option<FailedCommand> {
+    let segments = segment_osc133(raw_text)
+        .unwrap_or_else(|| segment_commands(&strip_ansi_and_control(raw_text)));
+    let has_exit_codes = segments.iter().any(|s| s.exit_code.is_some());
+
+    let segment = segments.iter().rev().find(|s| {
+        if s.command.is_empty() {
+            return false;
+        }
+        if has_exit_codes {
+            s.exit_code.is_some_and(|code| code != 0)
+        } else {
+            s.output.lines().any(|line| IMPORTANT_LINE_RE.is_match(line))
+        }
+    })?;
+
+    let output = compress_output(segment.output.trim_end());
+    let lines: Vec<&str> = output.lines().collect();
+    let start = lines.len().saturating_sub(FAILED_OUTPUT_LINES);
+    Some(FailedCommand {
+        command: segment.command.clone(),
+        output: lines[start..].join("\n"),
+        exit_code: segment.exit_code,
+    })
+}
+
+/// Rough token count for `text`, using the same chars-per-token ratio as the budget.
+pub(crate) fn estimate_tokens(text: &str) -> u32 {
+    (text.chars().count() as u32).div_ceil(CHARS_PER_TOKEN)
 }
 
 /// Look up context window size (in tokens) for a model by its ID.
@@ -307,6 +647,13 @@ mod tests {
         assert_eq!(context_window_for_model("unknown-model"), 128_000);
     }
 
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+    }
+
     // === Segmentation tests ===
 
     #[test]
@@ -407,4 +754,202 @@ mod tests {
     fn test_empty_input() {
         assert_eq!(prepare_terminal_context("", 128_000), "");
     }
+
+    // === Compression tests ===
+
+    #[test]
+    fn test_compress_cargo_build() {
+        let mut text = String::from("   Updating crates.io index\n");
+        for i in 0..40 {
+            text.push_str(&format!("   Compiling crate{} v0.{}.{}\n", i, i % 7, i));
+        }
+        text.push_str("error[E0425]: cannot find value `x` in this scope\n");
+        let result = compress_output(&text);
+        assert!(result.contains("Compiling crate0 v0.0.0\n   Compiling crate1 v0.1.1\n[… 37 similar lines …]\n   Compiling crate39 v0.4.39\n"));
+        assert!(result.contains("error[E0425]"));
+        assert!(result.lines().count() < 10);
+    }
+
+    #[test]
+    fn test_compress_progress_frames() {
+        let text = "added 1 package\n\r[#####     ] 50% fetch\r[########  ] 80% fetch\r[##########] 100% done\r\nfinished\n";
+        assert_eq!(compress_output(text), "added 1 package\n[##########] 100% done\nfinished\n");
+    }
+
+    #[test]
+    fn test_compress_keeps_failures_in_test_runs() {
+        let mut text = String::new();
+        for i in 0..30 {
+            text.push_str(&format!("test tests::case_{} ... ok\n", i));
+        }
+        text.push_str("test tests::case_30 ... FAILED\n");
+        for i in 31..60 {
+            text.push_str(&format!("test tests::case_{} ... ok\n", i));
+        }
+        let result = compress_output(&text);
+        assert!(result.contains("test tests::case_30 ... FAILED"));
+        assert!(result.contains("[… 27 similar lines …]\ntest tests::case_29 ... ok\ntest tests::case_30 ... FAILED\n"));
+        assert!(result.contains("[… 26 similar lines …]\ntest tests::case_59 ... ok"));
+    }
+
+    #[test]
+    fn test_compress_stack_trace() {
+        let mut text = String::from("TypeError: Cannot read properties of undefined (reading 'map')\n");
+        for i in 0..30 {
+            text.push_str(&format!("    at fn{} (/app/node_modules/lib/index.js:{}:7)\n", i, i + 10));
+        }
+        text.push_str("\nNode.js v20.11.0\n");
+        let result = compress_output(&text);
+        assert!(result.contains("TypeError: Cannot read properties"));
+        assert!(result.contains("at fn0 "));
+        assert!(result.contains("at fn5 "));
+        assert!(!result.contains("at fn6 "));
+        assert!(result.contains("[… 20 more frames …]"));
+        assert!(result.contains("at fn26 "));
+        assert!(result.contains("at fn29 "));
+        assert!(result.contains("Node.js v20.11.0"));
+    }
+
+    #[test]
+    fn test_compress_python_traceback_pairs() {
+        let mut text = String::from("Traceback (most recent call last):\n");
+        for i in 0..10 {
+            text.push_str(&format!("  File \"/app/mod{}.py\", line {}, in f{}\n    f{}()\n", i, i + 1, i, i + 1));
+        }
+        text.push_str("RecursionError: maximum recursion depth exceeded\n");
+        let result = compress_output(&text);
+        assert!(result.contains("more frames …]"));
+        assert!(result.starts_with("Traceback"));
+        assert!(result.contains("RecursionError"));
+        assert!(result.contains("mod9.py"));
+    }
+
+    #[test]
+    fn test_compress_keeps_listings() {
+        let mut ls = String::from("total 48\n");
+        for name in ["Cargo.toml", "README.md", "build.rs", "notes.txt", "src", "target"] {
+            ls.push_str(&format!("-rw-r--r--  1 alice  staff  1204 Mar  3 10:12 {}\n", name));
+        }
+        assert_eq!(compress_output(&ls), ls);
+
+        let mut ps = String::from("USER  PID %CPU %MEM  VSZ  RSS TTY STAT START TIME COMMAND\n");
+        for cmd in ["-zsh", "vim notes.txt", "ssh prod-db", "node server.js", "tmux", "top"] {
+            ps.push_str(&format!("alice 913 0.0 0.1 41234 5120 s001 S+ 9:14AM 0:00.05 {}\n", cmd));
+        }
+        assert_eq!(compress_output(&ps), ps);
+
+        let imports = "use std::fs;\nuse std::io;\nuse std::path::Path;\nuse serde::Serialize;\nuse regex::Regex;\n";
+        assert_eq!(compress_output(imports), imports);
+    }
+
+    #[test]
+    fn test_compress_leaves_short_output_alone() {
+        let text = "user@host:~$ ls\nfile1\nfile2\nfile3\nuser@host:~$ ls\nfile1\n";
+        assert_eq!(compress_output(text), text);
+    }
+
+    #[test]
+    fn test_prepare_compresses_before_budgeting() {
+        let mut raw = String::from("user@host:~$ npm install\n");
+        for i in 0..2000 {
+            raw.push_str(&format!("npm http fetch GET 200 https://registry.npmjs.org/pkg{} 12ms\n", i));
+        }
+        raw.push_str("npm ERR! code ERESOLVE\nuser@host:~$ ");
+        let result = prepare_terminal_context(&raw, 128_000);
+        assert!(result.contains("user@host:~$ npm install"));
+        assert!(result.contains("[… 1997 similar lines …]"));
+        assert!(result.contains("npm ERR! code ERESOLVE"));
+    }
+
+    // === OSC 133 tests ===
+
+    /// Build a command cycle with shell-integration marks, as bash/zsh integrations emit them.
+    fn osc_cycle(prompt: &str, command: &str, output: &str, exit: i32) -> String {
+        format!(
+            "\x1b]133;A\x07{}\x1b]133;B\x07{}\n\x1b]133;C\x07{}\x1b]133;D;{}\x07",
+            prompt, command, output, exit
+        )
+    }
+
+    #[test]
+    fn test_osc133_segments_custom_prompt() {
+        // Starship-style two-line prompt that PROMPT_RE does not recognize
+        let raw = format!(
+            "{}{}\x1b]133;A\x1b\\~/src on main\n❯ \x1b]133;B\x1b\\",
+            osc_cycle("~/src on main\n❯ ", "cat notes.txt", "$ not a prompt\n> quoted\n", 0),
+            osc_cycle("~/src on main\n❯ ", "cargo build", "error[E0425]: cannot find value\n", 101),
+        );
+        let segments = segment_osc133(&raw).unwrap();
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0].prompt_and_command, "~/src on main\n❯ cat notes.txt");
+        // Output lines that look like prompts stay inside the segment
+        assert_eq!(segments[0].output, "$ not a prompt\n> quoted\n");
+        assert_eq!(segments[0].exit_code, Some(0));
+        assert_eq!(segments[1].exit_code, Some(101));
+        assert_eq!(segments[2].prompt_and_command, "~/src on main\n❯ ");
+        assert_eq!(segments[2].exit_code, None);
+    }
+
+    #[test]
+    fn test_osc133_pipeline_reports_failure() {
+        let raw = format!(
+            "{}{}",
+            osc_cycle("❯ ", "ls", "a\nb\n", 0),
+            osc_cycle("❯ ", "make test", "FAIL\n", 2)
+        );
+        let result = prepare_terminal_context(&raw, 128_000);
+        assert!(!result.contains('\x1b'));
+        assert_eq!(result, "❯ ls\na\nb\n❯ make test\nFAIL\n[exit status 2]");
+    }
+
+    #[test]
+    fn test_osc133_truncation_keeps_newest() {
+        let mut raw = String::new();
+        for i in 0..50 {
+            raw.push_str(&osc_cycle("❯ ", &format!("echo {}", i), &"x".repeat(100), 0));
+        }
+        let segments = segment_osc133(&raw).unwrap();
+        let text = render_segments(&segments.iter().collect::<Vec<_>>());
+        let result = truncate_segments(&text, segments, 500);
+        assert!(result.contains("echo 49"));
+        assert!(!result.contains("echo 0\n"));
+        assert!(result.starts_with("❯ echo"));
+    }
+
+    #[test]
+    fn test_last_failed_command_uses_exit_codes() {
+        let raw = format!(
+            "{}{}{}",
+            osc_cycle("❯ ", "make test", "FAIL: test_parse\n", 2),
+            osc_cycle("❯ ", "echo done", "done\n", 0),
+            "\x1b]133;A\x07❯ \x1b]133;B\x07"
+        );
+        let failed = last_failed_command(&raw).unwrap();
+        assert_eq!(failed.command, "make test");
+        assert_eq!(failed.output, "FAIL: test_parse");
+        assert_eq!(failed.exit_code, Some(2));
+
+        let ok = osc_cycle("❯ ", "ls", "a\n", 0);
+        assert!(last_failed_command(&ok).is_none());
+    }
+
+    #[test]
+    fn test_last_failed_command_without_exit_codes() {
+        let raw = "user@host:~/app$ npm run build\nnpm ERR! missing script: build\nuser@host:~/app$ ";
+        let failed = last_failed_command(raw).unwrap();
+        assert_eq!(failed.command, "npm run build");
+        assert_eq!(failed.output, "npm ERR! missing script: build");
+        assert_eq!(failed.exit_code, None);
+
+        // A later successful command with output is not the error
+        let raw = "user@host:~/app$ cargo build\nerror[E0425]: cannot find value `x`\n\
+                   user@host:~/app$ git status\nOn branch main\nnothing to commit\nuser@host:~/app$ ";
+        assert_eq!(last_failed_command(raw).unwrap().command, "cargo build");
+        assert!(last_failed_command("user@host:~$ ls\nsrc\ntarget\nuser@host:~$ ").is_none());
+    }
+
+    #[test]
+    fn test_no_osc133_marks() {
+        assert!(segment_osc133("user@host:~$ ls\nfile").is_none());
+    }
 }
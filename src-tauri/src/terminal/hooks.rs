@@ -0,0 +1,331 @@
+//! Optional shell integration for terminals whose text cannot be read.
+//!
+//! GPU-rendered terminals (Alacritty, kitty, foot, st, xterm) expose no text to
+//! accessibility APIs, so cmd-k only knows the shell and CWD there. The bash, zsh
+//! and fish scripts in `hooks/` record each command line, its exit status,
+//! duration and CWD -- and optionally a tail of its error output -- in a per-user
+//! spool directory, one file per shell keyed by PID. Detection resolves the shell
+//! PID as usual and reads that file.
+//!
+//! Once the rc files have run, each hook also writes `<pid>.aliases`: the shell's
+//! own alias listing followed by `function <name>` lines (see `aliases`).
+//!
+//! Spool format: records separated by `\x1e`, each a block of `key=value` lines
+//! (`exit`, `duration_ms`, `finished`, `cwd`, `command`), optionally followed by an
+//! `output` line and the raw output tail. Newlines inside the command are written
+//! as `\x1f` so every field stays on one line.
+
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+const BASH_HOOK: &str = include_str!("hooks/cmd-k.bash");
+const ZSH_HOOK: &str = include_str!("hooks/cmd-k.zsh");
+const FISH_HOOK: &str = include_str!("hooks/cmd-k.fish");
+
+/// Only the end of a spool file is read; older records are irrelevant.
+const READ_LIMIT: u64 = 32 * 1024;
+
+/// Most recent commands turned into context.
+const MAX_RECORDS: usize = 20;
+
+/// Alias dumps larger than this are ignored rather than cut mid-definition.
+const ALIAS_DUMP_LIMIT: u64 = 256 * 1024;
+
+/// One command reported by a shell hook.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HookRecord {
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub duration_ms: Option<u64>,
+    pub cwd: Option<String>,
+    /// Tail of the command's error output, when the hook captures it
+    pub output: Option<String>,
+}
+
+/// Where a hook script was written and how to enable it.
+#[derive(Debug, Clone, Serialize)]
+pub struct HookInstall {
+    pub shell: String,
+    pub path: String,
+    pub rc_file: &'static str,
+    /// Line to add to `rc_file`
+    pub source_line: String,
+}
+
+/// Per-user directory the hooks write to. None on Windows, which has no
+/// supported hook shells.
+pub fn spool_dir() -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    {
+        None
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        if let Some(runtime) = std::env::var_os("XDG_RUNTIME_DIR").filter(|d| !d.is_empty()) {
+            return Some(PathBuf::from(runtime).join("cmd-k"));
+        }
+        // macOS $TMPDIR is already per-user; a shared /tmp needs the uid in the name
+        #[cfg(target_os = "macos")]
+        {
+            Some(std::env::temp_dir().join("cmd-k"))
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let uid = std::fs::metadata("/proc/self").ok()?.uid();
+            Some(std::env::temp_dir().join(format!("cmd-k-{}", uid)))
+        }
+    }
+}
+
+/// Whether the spool directory is private to its owner. A directory another
+/// user could write to might carry planted commands, so it is ignored.
+#[cfg(unix)]
+fn is_private_dir(dir: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(dir)
+        .map(|m| m.is_dir() && m.permissions().mode() & 0o077 == 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_private_dir(_dir: &Path) -> bool {
+    false
+}
+
+/// Whether `dir` belongs to this user. Only the shared-/tmp fallback on Linux
+/// needs the check; elsewhere the spool directory's parent is already per-user.
+fn is_own_dir(dir: &Path) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let uid = |path: &Path| std::fs::metadata(path).map(|m| m.uid()).ok();
+        uid(dir).is_some() && uid(dir) == uid(Path::new("/proc/self"))
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = dir;
+        true
+    }
+}
+
+/// Private directory for short-lived files other programs write or read for us
+/// (multiplexer captures, KWin scripts): `tmp` inside the spool directory,
+/// created 0700. None when it cannot be created, or when it or the spool
+/// directory is not private to this user, since its files could then be planted
+/// or swapped.
+pub fn scratch_dir() -> Option<PathBuf> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        let spool = spool_dir()?;
+        let dir = spool.join("tmp");
+        if !dir.exists() {
+            std::fs::DirBuilder::new().recursive(true).mode(0o700).create(&dir).ok()?;
+        }
+        let private = |d: &Path| is_private_dir(d) && is_own_dir(d);
+        (private(&spool) && private(&dir)).then_some(dir)
+    }
+    #[cfg(not(unix))]
+    {
+        None
+    }
+}
+
+/// Read at most `limit` bytes from the end of a file.
+pub(crate) fn read_tail_bytes(path: &Path, limit: u64) -> Option<Vec<u8>> {
+    let mut file = std::fs::File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(limit))).ok()?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf).ok()?;
+    Some(buf)
+}
+
+fn read_tail(path: &Path, limit: u64) -> Option<String> {
+    read_tail_bytes(path, limit).map(|buf| String::from_utf8_lossy(&buf).into_owned())
+}
+
+fn parse_record(chunk: &str) -> Option<HookRecord> {
+    let chunk = chunk.strip_prefix('\n').unwrap_or(chunk);
+    let (fields, output) = match chunk.split_once("\noutput\n") {
+        Some((fields, output)) => (fields, Some(output)),
+        None => (chunk, None),
+    };
+
+    let mut record = HookRecord::default();
+    for line in fields.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        match key {
+            "exit" => record.exit_code = value.parse().ok(),
+            "duration_ms" => record.duration_ms = value.parse().ok(),
+            "cwd" => record.cwd = Some(value.to_string()).filter(|v| !v.is_empty()),
+            "command" => record.command = value.replace('\x1f', "\n"),
+            _ => {}
+        }
+    }
+    record.output = output
+        .map(|o| o.trim_matches('\n').to_string())
+        .filter(|o| !o.is_empty());
+    (!record.command.trim().is_empty()).then_some(record)
+}
+
+/// Parse spool text into the most recent records, oldest first. Text before
+/// the first separator is a partial record cut off by tail reading and is dropped.
+fn parse_records(text: &str) -> Vec<HookRecord> {
+    let mut records: Vec<HookRecord> = text.split('\x1e').skip(1).filter_map(parse_record).collect();
+    let excess = records.len().saturating_sub(MAX_RECORDS);
+    records.drain(..excess);
+    records
+}
+
+/// Recent commands reported by the hooks running in `shell_pid`.
+pub fn read_records(shell_pid: i32) -> Vec<HookRecord> {
+    let Some(dir) = spool_dir().filter(|d| is_private_dir(d)) else {
+        return Vec::new();
+    };
+    read_tail(&dir.join(format!("{}.log", shell_pid)), READ_LIMIT)
+        .map(|text| parse_records(&text))
+        .unwrap_or_default()
+}
+
+/// Alias and function dump written by the hooks running in `shell_pid`.
+pub fn read_alias_dump(shell_pid: i32) -> Option<String> {
+    let dir = spool_dir().filter(|d| is_private_dir(d))?;
+    let path = dir.join(format!("{}.aliases", shell_pid));
+    if std::fs::metadata(&path).ok()?.len() > ALIAS_DUMP_LIMIT {
+        return None;
+    }
+    std::fs::read_to_string(path).ok()
+}
+
+/// Render records as terminal text carrying OSC 133 marks, so
+/// `context::prepare_terminal_context` segments them exactly and reports exit codes.
+pub fn transcript(records: &[HookRecord]) -> String {
+    let mut out = String::new();
+    for record in records {
+        let prompt = match &record.cwd {
+            Some(cwd) => format!("{} $ ", cwd),
+            None => "$ ".to_string(),
+        };
+        out.push_str(&format!(
+            "\x1b]133;A\x07{}\x1b]133;B\x07{}\n\x1b]133;C\x07",
+            prompt, record.command
+        ));
+        if let Some(output) = &record.output {
+            out.push_str(output);
+            out.push('\n');
+        }
+        if let Some(ms) = record.duration_ms.filter(|ms| *ms >= 1000) {
+            out.push_str(&format!("[took {:.1}s]\n", ms as f64 / 1000.0));
+        }
+        match record.exit_code {
+            Some(code) => out.push_str(&format!("\x1b]133;D;{}\x07", code)),
+            None => out.push_str("\x1b]133;D\x07"),
+        }
+    }
+    out
+}
+
+/// Single-quote a path for a POSIX or fish `source` line.
+fn shell_quote(path: &str) -> String {
+    format!("'{}'", path.replace('\'', r"'\''"))
+}
+
+/// Write the hook script for `shell` into `dir` with the spool path filled in.
+pub fn install(shell: &str, dir: &Path) -> Result<HookInstall, String> {
+    let (template, rc_file) = match shell {
+        "bash" => (BASH_HOOK, "~/.bashrc"),
+        "zsh" => (ZSH_HOOK, "~/.zshrc"),
+        "fish" => (FISH_HOOK, "~/.config/fish/config.fish"),
+        other => return Err(format!("Shell integration is not available for {}", other)),
+    };
+    let spool = spool_dir().ok_or("Shell integration is not supported on this platform")?;
+
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+        if !spool.exists() {
+            std::fs::DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(&spool)
+                .map_err(|e| format!("Failed to create {}: {}", spool.display(), e))?;
+        }
+        let _ = std::fs::set_permissions(&spool, std::fs::Permissions::from_mode(0o700));
+    }
+
+    let path = dir.join(format!("cmd-k.{}", shell));
+    // The spool path is substituted inside single quotes in every script
+    let script = template.replace("@SPOOL_DIR@", &spool.to_string_lossy().replace('\'', r"'\''"));
+    std::fs::write(&path, script).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    let quoted = shell_quote(&path.to_string_lossy());
+    let source_line = if shell == "fish" {
+        format!("test -f {0}; and source {0}", quoted)
+    } else {
+        format!("[ -f {0} ] && source {0}", quoted)
+    };
+    eprintln!("[hooks] installed {} integration at {}", shell, path.display());
+    Ok(HookInstall {
+        shell: shell.to_string(),
+        path: path.to_string_lossy().into_owned(),
+        rc_file,
+        source_line,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPOOL: &str = "partial=1\ncommand=cut off\n\
+        \x1e\nexit=0\nduration_ms=4\nfinished=1700000000\ncwd=/home/u/src\ncommand=ls\n\
+        \x1e\nexit=101\nduration_ms=2300\ncwd=/home/u/src\ncommand=cargo build\x1f  --release\n\
+        output\nerror[E0425]: cannot find value `x`\n\n\
+        \x1e\nexit=0\nduration_ms=\ncwd=/home/u\ncommand=\n";
+
+    #[test]
+    fn test_parse_records() {
+        let records = parse_records(SPOOL);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].command, "ls");
+        assert_eq!(records[0].cwd.as_deref(), Some("/home/u/src"));
+        assert_eq!(records[1].command, "cargo build\n  --release");
+        assert_eq!(records[1].exit_code, Some(101));
+        assert_eq!(records[1].duration_ms, Some(2300));
+        assert_eq!(records[1].output.as_deref(), Some("error[E0425]: cannot find value `x`"));
+    }
+
+    #[test]
+    fn test_parse_keeps_most_recent() {
+        let spool: String = (0..30)
+            .map(|i| format!("\x1e\nexit=0\ncommand=echo {}\n", i))
+            .collect();
+        let records = parse_records(&spool);
+        assert_eq!(records.len(), MAX_RECORDS);
+        assert_eq!(records.last().unwrap().command, "echo 29");
+    }
+
+    #[test]
+    fn test_transcript_segments_with_exit_codes() {
+        let text = transcript(&parse_records(SPOOL));
+        let prepared = crate::terminal::context::prepare_terminal_context(&text, 128_000);
+        assert_eq!(
+            prepared,
+            "/home/u/src $ ls\n/home/u/src $ cargo build\n  --release\n\
+             error[E0425]: cannot find value `x`\n[took 2.3s]\n[exit status 101]"
+        );
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("/a b/it's"), r"'/a b/it'\''s'");
+    }
+}
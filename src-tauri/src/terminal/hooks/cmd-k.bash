@@ -0,0 +1,117 @@
+# cmd-k shell integration for bash.
+#
+# Records each command line, its exit status, duration and working directory in
+# a per-user spool file keyed by this shell's PID, so cmd-k has context in
+# terminals whose text it cannot read. Commands kept out of history (for example
+# with HISTCONTROL=ignorespace) are not recorded.
+#
+# Aliases and function names are written to a second file once the rc files have
+# run, so suggestions may use them and safety checks can see through them.
+#
+# Set CMDK_CAPTURE_STDERR=1 before sourcing to also record the tail of each
+# command's error output. This routes stderr through tee for the whole session.
+
+[[ $- == *i* ]] || return 0
+[[ -n $__cmdk_hooked ]] && return 0
+__cmdk_hooked=1
+
+__cmdk_dir='@SPOOL_DIR@'
+mkdir -p -m 700 "$__cmdk_dir" 2>/dev/null || return 0
+__cmdk_log="$__cmdk_dir/$$.log"
+__cmdk_err="$__cmdk_dir/$$.err"
+__cmdk_aliases="$__cmdk_dir/$$.aliases"
+__cmdk_count=0
+__cmdk_armed=
+__cmdk_start=
+__cmdk_histno=
+: > "$__cmdk_log"
+
+if [[ $CMDK_CAPTURE_STDERR == 1 ]]; then
+  : > "$__cmdk_err"
+  exec 2> >(tee -a "$__cmdk_err" >&2)
+fi
+
+# Milliseconds since the epoch in __cmdk_ms (EPOCHREALTIME needs bash 5)
+__cmdk_clock() {
+  if [[ -n $EPOCHREALTIME ]]; then
+    local t=${EPOCHREALTIME/[.,]/}
+    __cmdk_ms=${t:0:${#t}-3}
+  else
+    __cmdk_ms=$((SECONDS * 1000))
+  fi
+}
+
+# DEBUG trap: the first command after the prompt marks the start of a command line
+__cmdk_preexec() {
+  [[ -n $__cmdk_armed && -z $COMP_LINE ]] || return 0
+  __cmdk_armed=
+  __cmdk_clock
+  __cmdk_start=$__cmdk_ms
+  [[ -f $__cmdk_err ]] && : > "$__cmdk_err"
+  return 0
+}
+
+# Latest history entry in __cmdk_entry and its number in __cmdk_entry_no
+__cmdk_last_history() {
+  local entry
+  entry=$(HISTTIMEFORMAT= builtin history 1)
+  entry=${entry#"${entry%%[![:space:]]*}"}
+  __cmdk_entry_no=${entry%%[[:space:]]*}
+  entry=${entry#"$__cmdk_entry_no"}
+  __cmdk_entry=${entry#"${entry%%[![:space:]]*}"}
+}
+
+# Aliases in `alias -p` syntax, then one `function <name>` line per function
+__cmdk_dump_aliases() {
+  local name
+  {
+    builtin alias -p
+    for name in $(compgen -A function); do
+      [[ $name == _* ]] || printf 'function %s\n' "$name"
+    done
+  } > "$__cmdk_aliases"
+}
+
+__cmdk_precmd() {
+  local exit_status=$? entry duration= now
+  __cmdk_armed=
+  [[ -z $__cmdk_histno ]] && __cmdk_dump_aliases
+  __cmdk_last_history
+  entry=$__cmdk_entry
+  # Same history number: the user pressed enter on an empty line. The first
+  # prompt only records the baseline, since bash loads the history file after
+  # the rc files and its last entry was not run in this shell.
+  if [[ -z $entry || $__cmdk_entry_no == "$__cmdk_histno" || -z $__cmdk_histno ]]; then
+    __cmdk_histno=${__cmdk_entry_no:-0}
+    __cmdk_start=
+    return "$exit_status"
+  fi
+  __cmdk_histno=$__cmdk_entry_no
+  if [[ -n $__cmdk_start ]]; then
+    __cmdk_clock
+    duration=$((__cmdk_ms - __cmdk_start))
+  fi
+  __cmdk_start=
+  printf -v now '%(%s)T' -1
+  {
+    printf '\036\nexit=%s\nduration_ms=%s\nfinished=%s\ncwd=%s\ncommand=%s\n' \
+      "$exit_status" "$duration" "$now" "$PWD" "${entry//$'\n'/$'\037'}"
+    if [[ -s $__cmdk_err ]]; then
+      printf 'output\n'
+      tail -c 2048 "$__cmdk_err"
+    fi
+  } >> "$__cmdk_log"
+  if (( ++__cmdk_count % 200 == 0 )); then
+    tail -c 16384 "$__cmdk_log" > "$__cmdk_log.tmp" && mv -f "$__cmdk_log.tmp" "$__cmdk_log"
+  fi
+  return "$exit_status"
+}
+
+__cmdk_arm() {
+  __cmdk_armed=1
+}
+
+# Keep an existing DEBUG trap; durations are then not recorded
+[[ -z $(trap -p DEBUG) ]] && trap '__cmdk_preexec' DEBUG
+[[ -z $(trap -p EXIT) ]] && trap 'rm -f "$__cmdk_log" "$__cmdk_err" "$__cmdk_aliases"' EXIT
+PROMPT_COMMAND="__cmdk_precmd;${PROMPT_COMMAND:+$PROMPT_COMMAND;}__cmdk_arm"
@@ -0,0 +1,51 @@
+# cmd-k shell integration for fish.
+#
+# Records each command line, its exit status, duration and working directory in
+# a per-user spool file keyed by this shell's PID, so cmd-k has context in
+# terminals whose text it cannot read. Error output capture is not available
+# for fish. Aliases, abbreviations and the user's own functions are written to a
+# second file at the first prompt, once config.fish has run.
+
+status is-interactive; or exit
+set -q __cmdk_hooked; and exit
+set -g __cmdk_hooked 1
+
+set -g __cmdk_dir '@SPOOL_DIR@'
+mkdir -p -m 700 $__cmdk_dir 2>/dev/null; or exit
+set -g __cmdk_log $__cmdk_dir/$fish_pid.log
+set -g __cmdk_aliases $__cmdk_dir/$fish_pid.aliases
+set -g __cmdk_count 0
+true >$__cmdk_log
+
+function __cmdk_postexec --on-event fish_postexec
+    set -l exit_status $status
+    set -l cmd (string join \x1f -- (string split \n -- $argv[1]))
+    test -n "$cmd"; or return
+    printf '\x1e\nexit=%s\nduration_ms=%s\ncwd=%s\ncommand=%s\n' \
+        $exit_status $CMD_DURATION $PWD "$cmd" >>$__cmdk_log
+    set __cmdk_count (math $__cmdk_count + 1)
+    if test (math $__cmdk_count % 200) -eq 0
+        tail -c 16384 $__cmdk_log >$__cmdk_log.tmp; and mv -f $__cmdk_log.tmp $__cmdk_log
+    end
+end
+
+# `alias` and `abbr --show` syntax, then one `function <name>` line for each
+# function not shipped with fish
+function __cmdk_dump_aliases --on-event fish_prompt
+    set -q __cmdk_dumped; and return
+    set -g __cmdk_dumped 1
+    begin
+        alias
+        abbr --show
+        for name in (functions --names)
+            string match -q -- '_*' $name; and continue
+            set -l file (functions --details -- $name)
+            string match -q -- "$__fish_data_dir/*" $file; and continue
+            echo "function $name"
+        end
+    end >$__cmdk_aliases
+end
+
+function __cmdk_exit --on-event fish_exit
+    rm -f $__cmdk_log $__cmdk_aliases
+end
@@ -1,5 +1,8 @@
+pub mod aliases;
 pub mod ax_reader;
 pub mod browser;
+pub mod cloud;
+pub mod container;
 pub mod detect;
 #[allow(dead_code)]
 pub mod detect_linux;
@@ -7,16 +10,28 @@ pub mod detect_linux;
 pub mod detect_windows;
 pub mod context;
 pub mod filter;
+pub mod git;
+pub mod hooks;
+pub mod injection;
+pub mod multiplexer;
 pub mod process;
+pub mod project;
+pub mod remote;
+pub mod repl;
+pub mod shell_history;
+pub mod tools;
 
 #[cfg(target_os = "windows")]
 pub mod uia_reader;
 
+#[cfg(target_os = "linux")]
+pub mod wayland;
+
 pub mod linux_reader;
 
 use serde::Serialize;
 use std::sync::mpsc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 /// Context information about the terminal that was frontmost before the overlay appeared.
 ///
@@ -24,7 +39,8 @@ use std::time::Duration;
 /// - CWD: the process may not have a valid CWD accessible via libproc
 /// - shell_type: derived from binary name, may be unknown
 /// - visible_output: populated by AX text reading for Terminal.app and iTerm2;
-///   always None for GPU-rendered terminals (Alacritty, kitty, WezTerm)
+///   for GPU-rendered terminals (Alacritty, kitty, WezTerm) only when the shell
+///   has the cmd-k hooks installed (see `hooks`)
 /// - running_process: only present when something is running inside the shell
 #[derive(Debug, Clone, Serialize)]
 pub struct TerminalContext {
@@ -43,6 +59,30 @@ pub struct TerminalContext {
     /// True when the session is running inside Windows Subsystem for Linux.
     /// Always false on non-Windows platforms.
     pub is_wsl: bool,
+    /// Kubernetes/AWS/gcloud targets active in the shell, if any.
+    pub cloud: Option<cloud::CloudContext>,
+    /// Branch, upstream, working-tree summary and in-progress operation of the CWD's repository.
+    pub git: Option<git::GitContext>,
+    /// Last commands from the shell's history file, read only when no output was captured.
+    pub recent_commands: Vec<shell_history::ShellCommand>,
+    /// Curated CLIs found on the shell's PATH, with versions once probed.
+    pub tools: Option<tools::ToolInventory>,
+    /// Aliases and function names defined in the shell.
+    pub aliases: Option<aliases::ShellAliases>,
+    /// Active pane of the tmux, screen or zellij client running in the terminal.
+    pub multiplexer: Option<multiplexer::MultiplexerPane>,
+    /// ssh, mosh or et session the shell is running; the local context then
+    /// does not describe where commands run.
+    pub remote: Option<remote::RemoteSession>,
+    /// Container, pod or toolbox the shell has entered (`docker exec`,
+    /// `kubectl exec`, `toolbox enter`); commands run there, not on the host.
+    pub container: Option<container::ContainerSession>,
+    /// Language REPL with the foreground (python, psql, irb); input goes to it,
+    /// not to the shell.
+    pub repl: Option<repl::ReplSession>,
+    /// PID of the foreground shell. Internal; not sent to the frontend.
+    #[serde(skip)]
+    pub shell_pid: Option<i32>,
 }
 
 /// Full context about the frontmost application, returned to the frontend.
@@ -136,12 +176,20 @@ pub fn detect_full_with_hwnd(
     rx.recv_timeout(Duration::from_millis(750)).ok().flatten()
 }
 
+/// Time after which detect_full_with_hwnd_inner starts no further optional
+/// context enrichers. Kept well under the 750ms receive timeout so the enricher
+/// still running at the deadline (each bounds its own subprocesses) can finish.
+const ENRICHMENT_BUDGET: Duration = Duration::from_millis(400);
+
 /// Inner detection logic for detect_full_with_hwnd, separated for catch_unwind.
 fn detect_full_with_hwnd_inner(
     previous_app_pid: i32,
     pre_captured_text: Option<String>,
     previous_hwnd: Option<isize>,
 ) -> Option<AppContext> {
+        let deadline = Instant::now() + ENRICHMENT_BUDGET;
+        let in_budget = || Instant::now() < deadline;
+
         // Windows: read UIA text FIRST to extract shell type hint for process tree disambiguation
         #[cfg(target_os = "windows")]
         let (uia_text, shell_type_hint) = {
@@ -156,12 +204,10 @@ fn detect_full_with_hwnd_inner(
 
         // Windows: call detect_app_context_windows directly with shell_type_hint
         #[cfg(target_os = "windows")]
-        #[allow(unused_mut)]
         let mut result = detect_app_context_windows(previous_app_pid, pre_captured_text, shell_type_hint);
 
         // Non-Windows: use generic detect_app_context (no shell_type_hint needed)
         #[cfg(not(target_os = "windows"))]
-        #[allow(unused_mut)]
         let mut result = detect_app_context(previous_app_pid, pre_captured_text);
 
         // Windows: window title WSL detection + UIA text processing (reusing already-captured UIA text)
@@ -240,6 +286,66 @@ fn detect_full_with_hwnd_inner(
         #[cfg(not(target_os = "windows"))]
         let _ = previous_hwnd;
 
+        // Inside tmux, screen or zellij, describe the multiplexer's active pane
+        // rather than the shell running the client
+        #[cfg(not(target_os = "windows"))]
+        if let Some(terminal) = result.as_mut().and_then(|ctx| ctx.terminal.as_mut()) {
+            if let Some(pane) = terminal.shell_pid.and_then(multiplexer::detect) {
+                use_multiplexer_pane(terminal, pane);
+            }
+        }
+
+        // Cloud CLI context (kubeconfig, AWS, gcloud), git state, installed tools and
+        // aliases describe the local shell: skip them when it is logged in to a
+        // remote host or has entered a container, and for WSL, whose config files,
+        // git and PATH the Windows side cannot see.
+        //
+        // The REPL, remote and container checks decide how the command is judged,
+        // so they always run. The enrichers after them are optional and run in
+        // series; once ENRICHMENT_BUDGET is spent the rest are skipped so the
+        // context already gathered still makes the timeout.
+        if let Some(terminal) = result.as_mut().and_then(|ctx| ctx.terminal.as_mut()) {
+            if !terminal.is_wsl {
+                // A language REPL in the foreground reads whatever is pasted
+                if terminal.running_process.is_some() {
+                    terminal.repl = terminal.shell_pid.and_then(repl::detect);
+                }
+                terminal.remote = terminal.shell_pid.and_then(remote::detect);
+                if let (Some(remote), Some(text)) =
+                    (terminal.remote.as_mut(), terminal.visible_output.as_deref())
+                {
+                    remote.infer_from_output(text);
+                }
+                if terminal.remote.is_none() {
+                    terminal.container = terminal.shell_pid.and_then(|pid| container::detect(pid, deadline));
+                }
+            }
+            let is_local =
+                !terminal.is_wsl && terminal.remote.is_none() && terminal.container.is_none();
+            if is_local && in_budget() {
+                terminal.cloud = cloud::detect(terminal.shell_pid);
+            }
+            if is_local && in_budget() {
+                terminal.git = terminal.cwd.as_deref().and_then(git::detect_context);
+            }
+            if is_local && in_budget() {
+                terminal.tools = tools::inventory(terminal.shell_pid);
+            }
+            if is_local && in_budget() {
+                terminal.aliases = terminal
+                    .shell_type
+                    .as_deref()
+                    .and_then(|shell| aliases::detect(shell, terminal.shell_pid));
+            }
+            // Without screen text or hook records, the history file shows what
+            // the user has been running
+            if terminal.visible_output.is_none() && is_local && in_budget() {
+                if let Some(shell) = terminal.shell_type.clone() {
+                    terminal.recent_commands = shell_history::recent_commands(&shell, terminal.shell_pid);
+                }
+            }
+        }
+
         result
 }
 
@@ -292,7 +398,8 @@ fn detect_inner(previous_app_pid: i32) -> Option<TerminalContext> {
             }
         } else {
             None
-        };
+        }
+        .or_else(|| shell_hook_output(proc_info.shell_pid));
 
         Some(TerminalContext {
             shell_type: proc_info.shell_type,
@@ -300,6 +407,16 @@ fn detect_inner(previous_app_pid: i32) -> Option<TerminalContext> {
             visible_output,
             running_process: proc_info.running_process,
             is_wsl: false,
+            cloud: None,
+            git: None,
+            recent_commands: Vec::new(),
+            tools: None,
+            aliases: None,
+            multiplexer: None,
+            remote: None,
+            container: None,
+            repl: None,
+            shell_pid: proc_info.shell_pid,
         })
     }
 
@@ -320,6 +437,36 @@ fn detect_inner(previous_app_pid: i32) -> Option<TerminalContext> {
     }
 }
 
+/// Recent commands reported by the cmd-k shell hooks, used when the terminal's
+/// text cannot be read. None when the shell has no hooks installed.
+#[cfg(not(target_os = "windows"))]
+fn shell_hook_output(shell_pid: Option<i32>) -> Option<String> {
+    let records = hooks::read_records(shell_pid?);
+    if records.is_empty() {
+        return None;
+    }
+    eprintln!("[detect] using {} commands from shell hooks", records.len());
+    Some(filter::filter_sensitive(&hooks::transcript(&records)))
+}
+
+/// Point the context at a multiplexer pane: its shell, CWD and foreground
+/// command, and its own scrollback instead of the terminal text, which shows
+/// every pane.
+#[cfg(not(target_os = "windows"))]
+fn use_multiplexer_pane(terminal: &mut TerminalContext, pane: multiplexer::MultiplexerPane) {
+    if let Some(pid) = pane.shell_pid {
+        terminal.shell_pid = Some(pid);
+        terminal.shell_type = process::get_process_name(pid).or(terminal.shell_type.take());
+    }
+    terminal.cwd = pane.cwd.clone().or(terminal.cwd.take());
+    terminal.running_process = pane.command.clone();
+    terminal.visible_output = pane
+        .capture()
+        .map(|text| filter::filter_sensitive(&text))
+        .or_else(|| shell_hook_output(pane.shell_pid));
+    terminal.multiplexer = Some(pane);
+}
+
 /// Linux-specific inner detection.
 #[cfg(target_os = "linux")]
 fn detect_inner_linux(previous_app_pid: i32) -> Option<TerminalContext> {
@@ -347,7 +494,8 @@ fn detect_inner_linux(previous_app_pid: i32) -> Option<TerminalContext> {
             .map(|text| filter::filter_sensitive(&text))
     } else {
         None
-    };
+    }
+    .or_else(|| shell_hook_output(proc_info.shell_pid));
 
     Some(TerminalContext {
         shell_type: proc_info.shell_type,
@@ -355,6 +503,16 @@ fn detect_inner_linux(previous_app_pid: i32) -> Option<TerminalContext> {
         visible_output,
         running_process: proc_info.running_process,
         is_wsl: false,
+        cloud: None,
+        git: None,
+        recent_commands: Vec::new(),
+        tools: None,
+        aliases: None,
+        multiplexer: None,
+        remote: None,
+        container: None,
+        repl: None,
+        shell_pid: proc_info.shell_pid,
     })
 }
 
@@ -412,6 +570,16 @@ fn detect_inner_windows(previous_app_pid: i32) -> Option<TerminalContext> {
         visible_output,
         running_process: proc_info.running_process,
         is_wsl,
+        cloud: None,
+        git: None,
+        recent_commands: Vec::new(),
+        tools: None,
+        aliases: None,
+        multiplexer: None,
+        remote: None,
+        container: None,
+        repl: None,
+        shell_pid: proc_info.shell_pid,
     })
 }
 
@@ -475,7 +643,8 @@ fn detect_app_context_linux(previous_app_pid: i32, _pre_captured_text: Option<St
                 .map(|text| filter::filter_sensitive(&text))
         } else {
             None
-        };
+        }
+        .or_else(|| shell_hook_output(proc_info.shell_pid));
 
         Some(TerminalContext {
             shell_type: proc_info.shell_type,
@@ -483,6 +652,16 @@ fn detect_app_context_linux(previous_app_pid: i32, _pre_captured_text: Option<St
             visible_output,
             running_process: proc_info.running_process,
             is_wsl: false,
+            cloud: None,
+            git: None,
+            recent_commands: Vec::new(),
+            tools: None,
+            aliases: None,
+            multiplexer: None,
+            remote: None,
+            container: None,
+            repl: None,
+            shell_pid: proc_info.shell_pid,
         })
     } else {
         None
@@ -528,7 +707,8 @@ fn detect_app_context_macos(previous_app_pid: i32, pre_captured_text: Option<Str
             }
         } else {
             None
-        };
+        }
+        .or_else(|| shell_hook_output(proc_info.shell_pid));
 
         Some(TerminalContext {
             shell_type: proc_info.shell_type,
@@ -536,6 +716,16 @@ fn detect_app_context_macos(previous_app_pid: i32, pre_captured_text: Option<Str
             visible_output,
             running_process: proc_info.running_process,
             is_wsl: false,
+            cloud: None,
+            git: None,
+            recent_commands: Vec::new(),
+            tools: None,
+            aliases: None,
+            multiplexer: None,
+            remote: None,
+            container: None,
+            repl: None,
+            shell_pid: proc_info.shell_pid,
         })
     } else {
         None
@@ -637,6 +827,16 @@ fn detect_app_context_windows(previous_app_pid: i32, _pre_captured_text: Option<
             visible_output: None, // UIA reading done separately via get_terminal_output command
             running_process: proc_info.running_process,
             is_wsl,
+            cloud: None,
+            git: None,
+            recent_commands: Vec::new(),
+            tools: None,
+            aliases: None,
+            multiplexer: None,
+            remote: None,
+            container: None,
+            repl: None,
+            shell_pid: proc_info.shell_pid,
         })
     } else if _is_terminal {
         // Known terminal (e.g. WindowsTerminal.exe) but no shell found in process tree.
@@ -649,6 +849,16 @@ fn detect_app_context_windows(previous_app_pid: i32, _pre_captured_text: Option<
             visible_output: None,
             running_process: None,
             is_wsl: false,
+            cloud: None,
+            git: None,
+            recent_commands: Vec::new(),
+            tools: None,
+            aliases: None,
+            multiplexer: None,
+            remote: None,
+            container: None,
+            repl: None,
+            shell_pid: None,
         })
     } else if detect_windows::is_ide_with_terminal_exe(exe_str) {
         // IDE (VS Code, Cursor) but no shell found in process tree.
@@ -663,6 +873,16 @@ fn detect_app_context_windows(previous_app_pid: i32, _pre_captured_text: Option<
             visible_output: None,
             running_process: None,
             is_wsl: false,
+            cloud: None,
+            git: None,
+            recent_commands: Vec::new(),
+            tools: None,
+            aliases: None,
+            multiplexer: None,
+            remote: None,
+            container: None,
+            repl: None,
+            shell_pid: None,
         })
     } else {
         None
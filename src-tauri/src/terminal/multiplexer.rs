@@ -0,0 +1,533 @@
+//! Terminal multiplexers (tmux, screen, zellij) running inside the terminal.
+//!
+//! With a multiplexer, the terminal's process tree ends at the multiplexer client
+//! and its screen text mixes every visible pane. When the terminal's shell runs a
+//! client, the multiplexer is asked for its active pane instead: the pane's shell
+//! (so CWD, hooks, history and aliases are the pane's), its foreground command and
+//! its scrollback. Commands are typed into that pane by the multiplexer itself
+//! rather than with clipboard keystrokes into whichever pane has focus.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use super::{hooks, process};
+
+/// Bound on each multiplexer query; detection as a whole has a 750ms budget.
+const QUERY_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Scrollback lines read from the pane.
+const SCROLLBACK_LINES: usize = 500;
+
+/// How long to wait for screen to write a hardcopy file.
+const HARDCOPY_WAIT: Duration = Duration::from_millis(200);
+
+/// Pane list format for `tmux list-clients`, which evaluates pane variables for
+/// each client's active pane.
+const TMUX_CLIENT_FORMAT: &str =
+    "#{client_pid}\t#{session_name}\t#{pane_id}\t#{pane_pid}\t#{pane_current_path}\t#{pane_current_command}";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MultiplexerKind {
+    Tmux,
+    Screen,
+    Zellij,
+}
+
+impl MultiplexerKind {
+    fn from_process_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "tmux" => Some(Self::Tmux),
+            n if n.starts_with("screen") => Some(Self::Screen),
+            "zellij" => Some(Self::Zellij),
+            _ => None,
+        }
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            Self::Tmux => "tmux",
+            Self::Screen => "screen",
+            Self::Zellij => "zellij",
+        }
+    }
+}
+
+/// The active pane of the multiplexer client running in the terminal.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MultiplexerPane {
+    pub kind: MultiplexerKind,
+    /// tmux or zellij session name; screen session id ("12345.pts-0.host")
+    pub session: String,
+    /// tmux pane id ("%3"), screen window number ("2") or zellij pane id
+    /// ("terminal_2"); empty when screen cannot report its window
+    pub pane: String,
+    /// Arguments that select the server: tmux `-L`/`-S`, `-S <session>` for
+    /// screen, `--session <name>` for zellij. Internal; not sent to the frontend.
+    #[serde(skip)]
+    server_args: Vec<String>,
+    /// Shell running in the pane. Internal; not sent to the frontend.
+    #[serde(skip)]
+    pub shell_pid: Option<i32>,
+    /// Working directory of the pane. Internal; not sent to the frontend.
+    #[serde(skip)]
+    pub cwd: Option<String>,
+    /// Program running in the pane's foreground, None when the shell is idle.
+    /// Internal; not sent to the frontend.
+    #[serde(skip)]
+    pub command: Option<String>,
+}
+
+impl MultiplexerPane {
+    /// Line for the AI prompt.
+    pub fn describe(&self) -> String {
+        if self.pane.is_empty() {
+            format!("Terminal multiplexer: {} (session {})", self.kind.program(), self.session)
+        } else {
+            format!(
+                "Terminal multiplexer: {} (session {}, pane {})",
+                self.kind.program(),
+                self.session,
+                self.pane
+            )
+        }
+    }
+
+    /// Suffix for the window key, so each pane keeps its own history.
+    pub fn window_key(&self) -> String {
+        format!("{}:{}:{}", self.kind.program(), self.session, self.pane)
+    }
+
+    fn command(&self, args: &[&str]) -> Command {
+        let mut cmd = Command::new(self.kind.program());
+        cmd.args(&self.server_args).args(args);
+        cmd
+    }
+
+    fn run(&self, args: &[&str]) -> Result<String, String> {
+        process::run_bounded(self.command(args), QUERY_TIMEOUT)
+            .ok_or_else(|| format!("{} {} failed", self.kind.program(), args.first().unwrap_or(&"")))
+    }
+
+    /// `-p <window>` for screen commands, when the window is known.
+    fn screen_window<'a>(&'a self, args: &[&'a str]) -> Vec<&'a str> {
+        let mut full = Vec::new();
+        if !self.pane.is_empty() {
+            full.extend(["-p", self.pane.as_str()]);
+        }
+        full.extend(args);
+        full
+    }
+
+    /// Scrollback of the pane, plain text without escape sequences.
+    pub fn capture(&self) -> Option<String> {
+        let text = match self.kind {
+            MultiplexerKind::Tmux => {
+                let start = format!("-{}", SCROLLBACK_LINES);
+                self.run(&["capture-pane", "-p", "-J", "-t", &self.pane, "-S", &start]).ok()?
+            }
+            MultiplexerKind::Screen => {
+                let path = scratch_path("hardcopy")?;
+                // A leftover file would end the wait below before screen writes
+                let _ = std::fs::remove_file(&path);
+                let path_str = path.to_string_lossy().into_owned();
+                self.run(&self.screen_window(&["-X", "hardcopy", "-h", &path_str])).ok()?;
+                // -X returns before screen has written the file
+                let deadline = Instant::now() + HARDCOPY_WAIT;
+                while !path.exists() && Instant::now() < deadline {
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                std::thread::sleep(Duration::from_millis(10));
+                let text = std::fs::read_to_string(&path).ok();
+                let _ = std::fs::remove_file(&path);
+                tail_lines(&text?, SCROLLBACK_LINES)
+            }
+            MultiplexerKind::Zellij => {
+                let path = scratch_path("dump")?;
+                let _ = std::fs::remove_file(&path);
+                let path_str = path.to_string_lossy().into_owned();
+                self.run(&["action", "dump-screen", &path_str, "--full"]).ok()?;
+                let text = std::fs::read_to_string(&path).ok();
+                let _ = std::fs::remove_file(&path);
+                tail_lines(&text?, SCROLLBACK_LINES)
+            }
+        };
+        let text = text.trim_end().to_string();
+        (!text.is_empty()).then_some(text)
+    }
+
+    /// Replace the pane's input line with `text` without running it. tmux and
+    /// screen paste through a buffer, so multi-line commands arrive as a paste
+    /// rather than as separate lines; zellij can only type, so multi-line text is
+    /// refused and the caller falls back to the clipboard.
+    pub fn send_text(&self, text: &str) -> Result<(), String> {
+        match self.kind {
+            MultiplexerKind::Tmux => {
+                self.run(&["send-keys", "-t", &self.pane, "C-u"])?;
+                with_buffer_file(text, |path| {
+                    self.run(&["load-buffer", "-b", "cmd-k", path])?;
+                    self.run(&["paste-buffer", "-d", "-p", "-b", "cmd-k", "-t", &self.pane])
+                })
+            }
+            MultiplexerKind::Screen => {
+                self.run(&self.screen_window(&["-X", "stuff", "\u{15}"]))?;
+                with_buffer_file(text, |path| {
+                    self.run(&self.screen_window(&["-X", "readreg", "k", path]))?;
+                    self.run(&self.screen_window(&["-X", "paste", "k"]))
+                })
+            }
+            MultiplexerKind::Zellij => {
+                if text.contains('\n') {
+                    return Err("zellij cannot paste multi-line commands".to_string());
+                }
+                self.run(&["action", "write", "21"])?;
+                self.run(&["action", "write-chars", text])
+            }
+        }
+        .map(|_| ())
+    }
+
+    /// Press Return in the pane.
+    pub fn send_enter(&self) -> Result<(), String> {
+        match self.kind {
+            MultiplexerKind::Tmux => self.run(&["send-keys", "-t", &self.pane, "Enter"]),
+            MultiplexerKind::Screen => self.run(&self.screen_window(&["-X", "stuff", "\r"])),
+            MultiplexerKind::Zellij => self.run(&["action", "write", "13"]),
+        }
+        .map(|_| ())
+    }
+}
+
+/// A per-process file path for multiplexer output, in the private scratch
+/// directory so no other user can plant or read it.
+fn scratch_path(name: &str) -> Option<PathBuf> {
+    Some(hooks::scratch_dir()?.join(format!("{}-{}.txt", name, std::process::id())))
+}
+
+/// Write `text` to a private temp file for the duration of `f`. Commands may hold
+/// restored secrets, so the file is owner-only and removed right after.
+fn with_buffer_file<T>(text: &str, f: impl FnOnce(&str) -> Result<T, String>) -> Result<T, String> {
+    use std::io::Write;
+    let path = scratch_path("paste").ok_or("No private directory for the paste buffer")?;
+    let _ = std::fs::remove_file(&path);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let written = options
+        .open(&path)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map_err(|e| format!("Failed to write paste buffer: {}", e));
+    let result = written.and_then(|_| f(&path.to_string_lossy()));
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+fn tail_lines(text: &str, max: usize) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    lines[lines.len().saturating_sub(max)..].join("\n")
+}
+
+/// Value of the first of `flags` in a client's argv (`-L name` or `-Lname`).
+fn flag_value(args: &[String], flags: &[&str]) -> Option<(String, String)> {
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        for flag in flags {
+            if arg == flag {
+                return iter.next().map(|v| (flag.to_string(), v.clone()));
+            }
+            if let Some(value) = arg.strip_prefix(flag).filter(|v| !v.is_empty() && flag.len() == 2) {
+                return Some((flag.to_string(), value.to_string()));
+            }
+        }
+    }
+    None
+}
+
+/// The tmux client's `-L`/`-S` socket selection, so queries reach its server.
+fn tmux_server_args(client_args: &[String]) -> Vec<String> {
+    flag_value(client_args, &["-S", "-L"])
+        .map(|(flag, value)| vec![flag, value])
+        .unwrap_or_default()
+}
+
+/// The `list-clients` line of the client with `client_pid`, or the only client.
+fn parse_tmux_clients(output: &str, client_pid: i32) -> Option<MultiplexerPane> {
+    let rows: Vec<Vec<&str>> = output
+        .lines()
+        .map(|line| line.split('\t').collect::<Vec<_>>())
+        .filter(|fields| fields.len() == 6)
+        .collect();
+    let row = rows
+        .iter()
+        .find(|fields| fields[0].parse() == Ok(client_pid))
+        .or(if rows.len() == 1 { rows.first() } else { None })?;
+    Some(MultiplexerPane {
+        kind: MultiplexerKind::Tmux,
+        session: row[1].to_string(),
+        pane: row[2].to_string(),
+        server_args: Vec::new(),
+        shell_pid: row[3].parse().ok(),
+        cwd: Some(row[4].to_string()).filter(|c| !c.is_empty()),
+        command: Some(row[5].to_string()).filter(|c| !c.is_empty()),
+    })
+}
+
+fn detect_tmux(client_pid: i32, client_args: &[String]) -> Option<MultiplexerPane> {
+    let server_args = tmux_server_args(client_args);
+    let mut cmd = Command::new("tmux");
+    cmd.args(&server_args).args(["list-clients", "-F", TMUX_CLIENT_FORMAT]);
+    let output = process::run_bounded(cmd, QUERY_TIMEOUT)?;
+    let mut pane = parse_tmux_clients(&output, client_pid)?;
+    pane.server_args = server_args;
+    Some(pane)
+}
+
+/// Session named on a screen client's command line (`screen -r name`, `-x`,
+/// `-dr`, `-S name`), or None for a plain `screen`.
+fn screen_session_arg(client_args: &[String]) -> Option<String> {
+    client_args.windows(2).find_map(|pair| {
+        let flag = pair[0].strip_prefix('-')?;
+        let selects = !flag.is_empty() && flag.chars().all(|c| "rRxSdD".contains(c));
+        (selects && !pair[1].starts_with('-')).then(|| pair[1].clone())
+    })
+}
+
+/// Directories screen keeps its session sockets in.
+fn screen_socket_dirs(client_env: &std::collections::HashMap<String, String>) -> Vec<PathBuf> {
+    if let Some(dir) = client_env.get("SCREENDIR").filter(|d| !d.is_empty()) {
+        return vec![PathBuf::from(dir)];
+    }
+    let user = client_env
+        .get("USER")
+        .cloned()
+        .or_else(|| std::env::var("USER").ok())
+        .unwrap_or_default();
+    ["/run/screen", "/var/run/screen", "/tmp/uscreens", "/tmp/screens"]
+        .iter()
+        .map(|dir| Path::new(dir).join(format!("S-{}", user)))
+        .collect()
+}
+
+/// Sessions in a socket directory as (id, attached). screen marks attached
+/// sessions by setting the owner execute bit on the socket.
+fn screen_sessions(dir: &Path) -> Vec<(String, bool)> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let id = entry.file_name().to_str()?.to_string();
+            #[cfg(unix)]
+            let attached = {
+                use std::os::unix::fs::PermissionsExt;
+                entry.metadata().ok()?.permissions().mode() & 0o100 != 0
+            };
+            #[cfg(not(unix))]
+            let attached = false;
+            id.contains('.').then_some((id, attached))
+        })
+        .collect()
+}
+
+/// The session named by the client, or else the only attached session.
+fn pick_screen_session(sessions: &[(String, bool)], requested: Option<&str>) -> Option<String> {
+    if let Some(name) = requested {
+        return sessions
+            .iter()
+            .find(|(id, _)| id == name || id.split_once('.').is_some_and(|(pid, rest)| pid == name || rest == name))
+            .map(|(id, _)| id.clone());
+    }
+    let attached: Vec<&String> = sessions.iter().filter(|(_, a)| *a).map(|(id, _)| id).collect();
+    match attached.as_slice() {
+        [only] => Some(only.to_string()),
+        _ => None,
+    }
+}
+
+fn detect_screen(client_pid: i32, client_args: &[String]) -> Option<MultiplexerPane> {
+    let env = process::get_process_env(client_pid);
+    let sessions: Vec<(String, bool)> = screen_socket_dirs(&env).iter().flat_map(|d| screen_sessions(d)).collect();
+    let session = pick_screen_session(&sessions, screen_session_arg(client_args).as_deref())?;
+    let mut pane = MultiplexerPane {
+        kind: MultiplexerKind::Screen,
+        session: session.clone(),
+        pane: String::new(),
+        server_args: vec!["-S".to_string(), session.clone()],
+        shell_pid: None,
+        cwd: None,
+        command: None,
+    };
+    // "2 zsh": the current window's number and title (screen 4.06+)
+    if let Ok(number) = pane.run(&["-Q", "number"]) {
+        pane.pane = number.split_whitespace().next().unwrap_or_default().to_string();
+    }
+    if !pane.pane.is_empty() {
+        pane.shell_pid = process::find_shell_with_env(&[("STY", &session), ("WINDOW", &pane.pane)]);
+    }
+    Some(pane)
+}
+
+/// Session named on a zellij client's command line (`zellij attach name`,
+/// `zellij -s name`).
+fn zellij_session_arg(client_args: &[String]) -> Option<String> {
+    client_args.windows(2).find_map(|pair| {
+        matches!(pair[0].as_str(), "attach" | "a" | "-s" | "--session")
+            .then(|| pair[1].clone())
+            .filter(|name| !name.starts_with('-'))
+    })
+}
+
+/// The single running session in `zellij list-sessions --no-formatting` output.
+fn parse_zellij_sessions(output: &str) -> Option<String> {
+    let live: Vec<&str> = output
+        .lines()
+        .filter(|line| !line.contains("EXITED"))
+        .filter_map(|line| line.split_whitespace().next())
+        .collect();
+    match live.as_slice() {
+        [only] => Some(only.to_string()),
+        _ => None,
+    }
+}
+
+/// Focused pane id and command of the first client in `zellij action list-clients`.
+fn parse_zellij_clients(output: &str) -> Option<(String, Option<String>)> {
+    let line = output.lines().skip(1).find(|line| !line.trim().is_empty())?;
+    let mut fields = line.split_whitespace();
+    let _client_id = fields.next()?;
+    let pane = fields.next()?.to_string();
+    let command = fields.next().map(|program| {
+        Path::new(program).file_name().map_or(program, |n| n.to_str().unwrap_or(program)).to_string()
+    });
+    Some((pane, command))
+}
+
+fn detect_zellij(client_args: &[String]) -> Option<MultiplexerPane> {
+    let session = match zellij_session_arg(client_args) {
+        Some(name) => name,
+        None => {
+            let mut cmd = Command::new("zellij");
+            cmd.args(["list-sessions", "--no-formatting"]);
+            parse_zellij_sessions(&process::run_bounded(cmd, QUERY_TIMEOUT)?)?
+        }
+    };
+    let mut pane = MultiplexerPane {
+        kind: MultiplexerKind::Zellij,
+        session: session.clone(),
+        pane: String::new(),
+        server_args: vec!["--session".to_string(), session.clone()],
+        shell_pid: None,
+        cwd: None,
+        command: None,
+    };
+    let (pane_id, command) = parse_zellij_clients(&pane.run(&["action", "list-clients"]).ok()?)?;
+    // Panes export their numeric id: "terminal_2" runs with ZELLIJ_PANE_ID=2
+    if let Some(number) = pane_id.strip_prefix("terminal_") {
+        pane.shell_pid = process::find_shell_with_env(&[("ZELLIJ_SESSION_NAME", &session), ("ZELLIJ_PANE_ID", number)]);
+    }
+    pane.pane = pane_id;
+    pane.command = command;
+    Some(pane)
+}
+
+/// The active pane of a multiplexer client running in the shell `shell_pid`, or
+/// None when the shell is not running tmux, screen or zellij.
+pub fn detect(shell_pid: i32) -> Option<MultiplexerPane> {
+    let (client_pid, kind) = process::get_child_pids(shell_pid).into_iter().find_map(|pid| {
+        let kind = MultiplexerKind::from_process_name(&process::get_process_name(pid)?)?;
+        Some((pid, kind))
+    })?;
+    let args = process::get_process_args(client_pid);
+    let mut pane = match kind {
+        MultiplexerKind::Tmux => detect_tmux(client_pid, &args),
+        MultiplexerKind::Screen => detect_screen(client_pid, &args),
+        MultiplexerKind::Zellij => detect_zellij(&args),
+    };
+    if let Some(pane) = pane.as_mut() {
+        // A pane started with a program instead of a shell has no shell to read
+        let shell_name = pane.shell_pid.and_then(process::get_process_name);
+        if !shell_name.as_deref().is_some_and(process::is_known_shell) {
+            pane.shell_pid = None;
+        }
+        if pane.cwd.is_none() {
+            pane.cwd = pane.shell_pid.and_then(process::get_process_cwd);
+        }
+        // tmux reports the idle shell itself as the current command
+        if pane.command.is_some() && pane.command == shell_name {
+            pane.command = None;
+        }
+    }
+    eprintln!(
+        "[multiplexer] {} client {}: {:?}",
+        kind.program(),
+        client_pid,
+        pane.as_ref().map(|p| (&p.session, &p.pane, p.shell_pid))
+    );
+    pane
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_tmux_clients_matches_client_pid() {
+        let output = "4100\twork\t%1\t4200\t/home/u/api\tzsh\n\
+                      4300\tmain\t%7\t4400\t/home/u/web\tnpm\n";
+        let pane = parse_tmux_clients(output, 4300).unwrap();
+        assert_eq!((pane.session.as_str(), pane.pane.as_str()), ("main", "%7"));
+        assert_eq!(pane.shell_pid, Some(4400));
+        assert_eq!(pane.cwd.as_deref(), Some("/home/u/web"));
+        assert_eq!(pane.command.as_deref(), Some("npm"));
+        assert_eq!(pane.window_key(), "tmux:main:%7");
+        // Two clients and neither is ours: no guessing
+        assert!(parse_tmux_clients(output, 9999).is_none());
+        assert!(parse_tmux_clients("4100\twork\t%1\t4200\t/\tzsh\n", 9999).is_some());
+    }
+
+    #[test]
+    fn test_client_arguments() {
+        assert_eq!(tmux_server_args(&args("tmux -L dev attach -t main")), vec!["-L", "dev"]);
+        assert_eq!(tmux_server_args(&args("tmux -S/tmp/s new")), vec!["-S", "/tmp/s"]);
+        assert!(tmux_server_args(&args("tmux new -s work")).is_empty());
+        assert_eq!(screen_session_arg(&args("screen -dr work")).as_deref(), Some("work"));
+        assert_eq!(screen_session_arg(&args("screen -S build -L")).as_deref(), Some("build"));
+        assert_eq!(screen_session_arg(&args("screen vim")), None);
+        assert_eq!(zellij_session_arg(&args("zellij attach dev")).as_deref(), Some("dev"));
+        assert_eq!(zellij_session_arg(&args("zellij --session ops")).as_deref(), Some("ops"));
+    }
+
+    #[test]
+    fn test_pick_screen_session() {
+        let sessions = vec![
+            ("12345.pts-0.host".to_string(), true),
+            ("12400.work".to_string(), false),
+        ];
+        assert_eq!(pick_screen_session(&sessions, None).as_deref(), Some("12345.pts-0.host"));
+        assert_eq!(pick_screen_session(&sessions, Some("work")).as_deref(), Some("12400.work"));
+        assert_eq!(pick_screen_session(&sessions, Some("12345")).as_deref(), Some("12345.pts-0.host"));
+        let both = vec![("1.a".to_string(), true), ("2.b".to_string(), true)];
+        assert_eq!(pick_screen_session(&both, None), None);
+    }
+
+    #[test]
+    fn test_parse_zellij_output() {
+        let sessions = "dev [Created 2h ago]\nold [Created 3d ago] (EXITED - attach to resurrect)\n";
+        assert_eq!(parse_zellij_sessions(sessions).as_deref(), Some("dev"));
+        let clients = "CLIENT_ID ZELLIJ_PANE_ID RUNNING_COMMAND\n1         terminal_2     /usr/bin/vim notes.md\n";
+        assert_eq!(
+            parse_zellij_clients(clients),
+            Some(("terminal_2".to_string(), Some("vim".to_string())))
+        );
+    }
+}
@@ -12,7 +12,7 @@
 /// Known shell binary names. Used to distinguish shells from other processes.
 /// Includes both Unix names (for macOS) and Windows names (stripped of .exe by get_process_name).
 const KNOWN_SHELLS: &[&str] = &[
-    "bash", "zsh", "fish", "sh", "dash", "tcsh", "csh", "ksh", "nu", "elvish", "ion", "xonsh",
+    "bash", "zsh", "fish", "sh", "dash", "ash", "tcsh", "csh", "ksh", "nu", "elvish", "ion", "xonsh",
     // Windows shell names (after stripping .exe)
     "powershell", "pwsh", "cmd",
 ];
@@ -80,6 +80,8 @@ pub struct ProcessInfo {
     pub running_process: Option<String>,
     /// True when wsl.exe is found in the process ancestry (Windows only).
     pub is_wsl: bool,
+    /// PID of the foreground shell, used to read its environment.
+    pub shell_pid: Option<i32>,
 }
 
 /// Get CWD, shell type, and running process for the terminal identified by `terminal_pid`.
@@ -119,6 +121,7 @@ pub fn get_foreground_info(terminal_pid: i32, snapshot: Option<&ProcessSnapshot>
                 shell_type: None,
                 running_process: None,
                 is_wsl: false,
+                shell_pid: None,
             }
         }
     };
@@ -184,6 +187,7 @@ pub fn get_foreground_info(terminal_pid: i32, snapshot: Option<&ProcessSnapshot>
         shell_type,
         running_process,
         is_wsl,
+        shell_pid: Some(shell_pid),
     }
 }
 
@@ -211,6 +215,7 @@ pub fn get_foreground_info(terminal_pid: i32) -> ProcessInfo {
                 shell_type: None,
                 running_process: None,
                 is_wsl: false,
+                shell_pid: None,
             }
         }
     };
@@ -234,6 +239,7 @@ pub fn get_foreground_info(terminal_pid: i32) -> ProcessInfo {
         shell_type,
         running_process,
         is_wsl,
+        shell_pid: Some(shell_pid),
     }
 }
 
@@ -243,7 +249,7 @@ pub fn get_foreground_info(terminal_pid: i32) -> ProcessInfo {
 /// Falls back to `lsof -a -p <pid> -d cwd -Fn` if proc_pidinfo fails
 /// (which happens for processes spawned by root-owned parents like `login`).
 #[cfg(target_os = "macos")]
-fn get_process_cwd(pid: i32) -> Option<String> {
+pub(crate) fn get_process_cwd(pid: i32) -> Option<String> {
     // Fast path: proc_pidinfo
     if let Some(cwd) = get_process_cwd_libproc(pid) {
         return Some(cwd);
@@ -318,7 +324,7 @@ fn get_process_cwd_lsof(pid: i32) -> Option<String> {
 /// CurrentDirectory path from RTL_USER_PROCESS_PARAMETERS. Requires PROCESS_QUERY_INFORMATION
 /// and PROCESS_VM_READ access. Returns None for elevated processes (Access Denied) or on error.
 #[cfg(target_os = "windows")]
-fn get_process_cwd(pid: i32) -> Option<String> {
+pub(crate) fn get_process_cwd(pid: i32) -> Option<String> {
     get_process_cwd_windows(pid as u32)
 }
 
@@ -437,7 +443,7 @@ unsafe fn read_process_memory(handle: windows_sys::Win32::Foundation::HANDLE, ad
 
 /// Linux: read /proc/PID/cwd symlink to get the process working directory.
 #[cfg(target_os = "linux")]
-fn get_process_cwd(pid: i32) -> Option<String> {
+pub(crate) fn get_process_cwd(pid: i32) -> Option<String> {
     let path = format!("/proc/{}/cwd", pid);
     match std::fs::read_link(&path) {
         Ok(p) => {
@@ -456,7 +462,7 @@ fn get_process_cwd(pid: i32) -> Option<String> {
 /// Uses proc_pidpath to get the full executable path (e.g., "/bin/zsh"),
 /// then extracts just the filename (e.g., "zsh").
 #[cfg(target_os = "macos")]
-fn get_process_name(pid: i32) -> Option<String> {
+pub(crate) fn get_process_name(pid: i32) -> Option<String> {
     let mut buf = [0u8; PATH_MAX];
     // SAFETY: buf is stack-allocated at PATH_MAX. proc_pidpath is stable macOS public API.
     let ret = unsafe { ffi::proc_pidpath(pid, buf.as_mut_ptr(), PATH_MAX as u32) };
@@ -474,7 +480,7 @@ fn get_process_name(pid: i32) -> Option<String> {
 
 /// Windows: get process name via QueryFullProcessImageNameW.
 #[cfg(target_os = "windows")]
-fn get_process_name(pid: i32) -> Option<String> {
+pub(crate) fn get_process_name(pid: i32) -> Option<String> {
     super::detect_windows::get_exe_name_for_pid(pid as u32)
         .map(|exe| exe.trim_end_matches(".exe").trim_end_matches(".EXE").to_string())
 }
@@ -494,13 +500,177 @@ pub(crate) fn get_process_name(pid: i32) -> Option<String> {
         .map(|s| s.to_string())
 }
 
+/// Read the environment a process was started with.
+///
+/// Only the startup environment is visible: variables the user exported later in
+/// an interactive shell are not reflected. Returns an empty map on failure.
+#[cfg(target_os = "linux")]
+pub(crate) fn get_process_env(pid: i32) -> std::collections::HashMap<String, String> {
+    match std::fs::read(format!("/proc/{}/environ", pid)) {
+        Ok(bytes) => bytes
+            .split(|&b| b == 0)
+            .filter_map(|entry| {
+                let entry = String::from_utf8_lossy(entry);
+                let (key, value) = entry.split_once('=')?;
+                Some((key.to_string(), value.to_string()))
+            })
+            .collect(),
+        Err(e) => {
+            eprintln!("[process] /proc/{}/environ read failed: {}", pid, e);
+            std::collections::HashMap::new()
+        }
+    }
+}
+
+/// macOS: `ps eww` appends the process environment to its command line. Values
+/// containing spaces are truncated, which is acceptable for the profile/context
+/// names callers look for.
+#[cfg(target_os = "macos")]
+pub(crate) fn get_process_env(pid: i32) -> std::collections::HashMap<String, String> {
+    let mut cmd = std::process::Command::new("ps");
+    cmd.args(["eww", "-o", "command=", "-p", &pid.to_string()]);
+    run_bounded(cmd, std::time::Duration::from_millis(200))
+        .map(|out| {
+            out.split_whitespace()
+                .filter_map(|token| {
+                    let (key, value) = token.split_once('=')?;
+                    let valid = !key.is_empty()
+                        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                        && !key.starts_with(|c: char| c.is_ascii_digit());
+                    valid.then(|| (key.to_string(), value.to_string()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Windows: reading another process's environment requires walking its PEB;
+/// callers fall back to config files.
+#[cfg(target_os = "windows")]
+pub(crate) fn get_process_env(_pid: i32) -> std::collections::HashMap<String, String> {
+    std::collections::HashMap::new()
+}
+
+/// Linux: a process's argv from /proc/PID/cmdline.
+#[cfg(target_os = "linux")]
+pub(crate) fn get_process_args(pid: i32) -> Vec<String> {
+    std::fs::read(format!("/proc/{}/cmdline", pid))
+        .map(|bytes| {
+            bytes
+                .split(|&b| b == 0)
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// macOS: a process's argv via `ps`. Arguments containing spaces are split.
+#[cfg(target_os = "macos")]
+pub(crate) fn get_process_args(pid: i32) -> Vec<String> {
+    let mut cmd = std::process::Command::new("ps");
+    cmd.args(["-ww", "-o", "args=", "-p", &pid.to_string()]);
+    run_bounded(cmd, std::time::Duration::from_millis(200))
+        .map(|out| out.split_whitespace().map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// Windows: only needed for Unix terminal multiplexers.
+#[cfg(target_os = "windows")]
+pub(crate) fn get_process_args(_pid: i32) -> Vec<String> {
+    Vec::new()
+}
+
+/// Whether `name` is a known shell binary name.
+pub(crate) fn is_known_shell(name: &str) -> bool {
+    KNOWN_SHELLS.contains(&name)
+}
+
+/// Container CLIs that run an interactive shell in a container, pod or toolbox.
+const CONTAINER_WRAPPERS: &[&str] = &["docker", "podman", "kubectl", "toolbox", "distrobox", "distrobox-enter"];
+
+/// Walk down from the shell through container CLIs (`toolbox enter` runs
+/// `podman exec`, `distrobox enter` runs `distrobox-enter`), returning each
+/// one's name and argv, outermost first. Wrappers that are shell scripts are
+/// named after the script, with the interpreter dropped from argv.
+pub(crate) fn find_container_wrappers(shell_pid: i32) -> Vec<(String, Vec<String>)> {
+    let mut chain = Vec::new();
+    let mut parent = shell_pid;
+    // toolbox -> podman, distrobox -> distrobox-enter -> podman
+    for _ in 0..4 {
+        let next = get_child_pids(parent).into_iter().find_map(|pid| {
+            let name = get_process_name(pid)?;
+            let mut args = get_process_args(pid);
+            if CONTAINER_WRAPPERS.contains(&name.as_str()) {
+                return Some((pid, name, args));
+            }
+            // `#!/bin/sh` scripts run as `sh /usr/bin/distrobox-enter ...`
+            let script = args.get(1)?.rsplit('/').next()?.to_string();
+            if !CONTAINER_WRAPPERS.contains(&script.as_str()) {
+                return None;
+            }
+            args.remove(0);
+            Some((pid, script, args))
+        });
+        let Some((pid, name, args)) = next else { break };
+        chain.push((name, args));
+        parent = pid;
+    }
+    chain
+}
+
+/// Find the outermost shell whose environment contains all of `vars`, e.g. the
+/// shell of a screen window (`STY`, `WINDOW`) or zellij pane (`ZELLIJ_PANE_ID`).
+/// Shells started from that shell inherit the variables, so the one whose parent
+/// does not match is picked.
+#[cfg(not(target_os = "windows"))]
+pub(crate) fn find_shell_with_env(vars: &[(&str, &str)]) -> Option<i32> {
+    #[cfg(target_os = "linux")]
+    let shell_pids: Vec<i32> = std::fs::read_dir("/proc")
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|e| e.file_name().to_str()?.parse::<i32>().ok())
+                .filter(|&pid| get_process_name(pid).is_some_and(|n| is_known_shell(&n)))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    #[cfg(target_os = "macos")]
+    let shell_pids: Vec<i32> = {
+        let mut cmd = std::process::Command::new("pgrep");
+        cmd.arg("-x").arg(KNOWN_SHELLS.join("|"));
+        run_bounded(cmd, std::time::Duration::from_millis(200))
+            .map(|out| out.lines().filter_map(|l| l.trim().parse().ok()).collect())
+            .unwrap_or_default()
+    };
+
+    let matching: Vec<i32> = shell_pids
+        .into_iter()
+        .filter(|&pid| {
+            let env = get_process_env(pid);
+            vars.iter().all(|(key, value)| env.get(*key).map(String::as_str) == Some(*value))
+        })
+        .collect();
+    matching
+        .iter()
+        .copied()
+        .find(|&pid| get_parent_pid(pid).is_none_or(|ppid| !matching.contains(&ppid)))
+        .or_else(|| matching.iter().copied().min())
+}
+
+#[cfg(target_os = "windows")]
+pub(crate) fn find_shell_with_env(_vars: &[(&str, &str)]) -> Option<i32> {
+    None
+}
+
 /// Get the child PIDs of a process.
 ///
 /// Tries proc_listchildpids first (fast), falls back to sysctl KERN_PROC scan
 /// if that returns nothing (proc_listchildpids can fail for root-owned processes
 /// like `login` when called from a user-owned process).
 #[cfg(target_os = "macos")]
-fn get_child_pids(pid: i32) -> Vec<i32> {
+pub(crate) fn get_child_pids(pid: i32) -> Vec<i32> {
     // Try proc_listchildpids first (fast path)
     let pids = get_child_pids_libproc(pid);
     if !pids.is_empty() {
@@ -569,7 +739,7 @@ fn get_child_pids_sysctl(ppid: i32) -> Vec<i32> {
 
 /// Windows: get child PIDs via CreateToolhelp32Snapshot.
 #[cfg(target_os = "windows")]
-fn get_child_pids(pid: i32) -> Vec<i32> {
+pub(crate) fn get_child_pids(pid: i32) -> Vec<i32> {
     get_child_pids_windows(pid as u32)
         .into_iter()
         .map(|p| p as i32)
@@ -612,7 +782,7 @@ fn get_child_pids_windows(ppid: u32) -> Vec<u32> {
 /// Linux: get child PIDs by reading /proc/PID/task/PID/children.
 /// Falls back to scanning /proc/*/stat for parent PID matches.
 #[cfg(target_os = "linux")]
-fn get_child_pids(pid: i32) -> Vec<i32> {
+pub(crate) fn get_child_pids(pid: i32) -> Vec<i32> {
     // Fast path: /proc/PID/task/PID/children
     let path = format!("/proc/{}/task/{}/children", pid, pid);
     if let Ok(content) = std::fs::read_to_string(&path) {
@@ -1775,6 +1945,50 @@ pub(crate) fn is_interactive_cmd(pid: u32, snapshot: &ProcessSnapshot) -> bool {
     }
 }
 
+/// Run a command with no stdin and return its stdout if it exits successfully
+/// within `timeout`. The child is killed once the timeout passes.
+pub(crate) fn run_bounded(mut cmd: std::process::Command, timeout: std::time::Duration) -> Option<String> {
+    use std::io::Read;
+    use std::process::Stdio;
+    use std::time::{Duration, Instant};
+
+    cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::null());
+
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    let mut child = cmd.spawn().ok()?;
+    // Drain stdout on a separate thread so a full pipe cannot block the child
+    let mut stdout = child.stdout.take()?;
+    let reader = std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = stdout.read_to_end(&mut buf);
+        String::from_utf8_lossy(&buf).into_owned()
+    });
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                let out = reader.join().ok()?;
+                return status.success().then_some(out);
+            }
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(5)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                let args: Vec<_> = cmd.get_args().map(|a| a.to_string_lossy()).collect();
+                eprintln!("[process] timed out: {} {}", cmd.get_program().to_string_lossy(), args.join(" "));
+                return None;
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,453 @@
+//! Project detection from the terminal's working directory.
+//!
+//! Walks up from the CWD looking for build manifests, stopping at the
+//! repository root (a directory containing `.git`) or after a few levels, and
+//! extracts the named tasks each one defines -- npm scripts, Make and just
+//! targets, Cargo binaries and features, Python entry points, Compose services --
+//! so a request like "run the tests" maps to the project's actual command.
+//!
+//! Manifests are parsed line by line; only the names are needed, so no full
+//! TOML/YAML parser is involved.
+
+use std::path::Path;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Build and task manifests recognized near the CWD.
+pub const MANIFESTS: &[&str] = &[
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "setup.py",
+    "requirements.txt",
+    "go.mod",
+    "Makefile",
+    "justfile",
+    "docker-compose.yml",
+    "compose.yaml",
+];
+
+/// Directories searched above the CWD when no repository root is found.
+const MAX_PARENT_LEVELS: usize = 4;
+
+/// Task names listed per manifest before the rest are summarized as "+N more".
+const MAX_TASKS: usize = 15;
+
+/// Upper bound for the whole "Project:" section (~200 tokens).
+pub const PROJECT_BUDGET_CHARS: usize = 800;
+
+/// Make target: a name at the start of a line followed by `:` (not `:=`).
+static MAKE_TARGET_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^([A-Za-z0-9][\w.\-/]*)\s*:([^=]|$)").unwrap());
+
+/// just recipe: `name params...:` at the start of a line, optionally `@`-prefixed.
+static JUST_RECIPE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^@?([A-Za-z_][\w\-]*)(?:\s+[^:]*)?:([^=]|$)").unwrap());
+
+/// `key = ...` or `"key" = ...` inside a TOML table.
+static TOML_KEY_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^\s*"?([A-Za-z0-9_.\-]+)"?\s*="#).unwrap());
+
+/// A manifest found near the CWD and the tasks it defines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manifest {
+    /// Path relative to the CWD, e.g. `../package.json`
+    pub path: String,
+    /// Ecosystem label shown to the model ("Rust", "Node", ...)
+    pub kind: &'static str,
+    /// How tasks are run, e.g. `pnpm run`, `make`
+    pub runner: Option<&'static str>,
+    /// Short facts about the project (package name, workspace)
+    pub facts: Vec<String>,
+    /// Task names grouped by what they are (`scripts`, `targets`, `bins`, ...)
+    pub tasks: Vec<(&'static str, Vec<String>)>,
+}
+
+/// Manifest files in the CWD and its parents, nearest first, as paths relative
+/// to the CWD (`Cargo.toml`, `../package.json`).
+pub fn find_manifests(cwd: &str) -> Vec<String> {
+    let mut found = Vec::new();
+    let mut dir = Path::new(cwd);
+    let mut prefix = String::new();
+
+    for _ in 0..=MAX_PARENT_LEVELS {
+        for name in MANIFESTS {
+            if dir.join(name).is_file() {
+                found.push(format!("{}{}", prefix, name));
+            }
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+        match dir.parent() {
+            Some(parent) => dir = parent,
+            None => break,
+        }
+        prefix.push_str("../");
+    }
+    found
+}
+
+/// Lines of a TOML file grouped under their `[table]` header.
+fn toml_tables(text: &str) -> Vec<(String, Vec<&str>)> {
+    let mut tables: Vec<(String, Vec<&str>)> = vec![(String::new(), Vec::new())];
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            let name = trimmed.trim_matches(|c| c == '[' || c == ']').trim().to_string();
+            tables.push((name, Vec::new()));
+        } else if !trimmed.is_empty() && !trimmed.starts_with('#') {
+            tables.last_mut().unwrap().1.push(line);
+        }
+    }
+    tables
+}
+
+/// Keys defined directly in a TOML table.
+fn toml_keys(lines: &[&str]) -> Vec<String> {
+    lines
+        .iter()
+        .filter_map(|line| TOML_KEY_RE.captures(line).map(|c| c[1].to_string()))
+        .collect()
+}
+
+/// String value of `key = "value"` in a TOML table.
+fn toml_string(lines: &[&str], key: &str) -> Option<String> {
+    lines.iter().find_map(|line| {
+        let (k, v) = line.split_once('=')?;
+        (k.trim().trim_matches('"') == key)
+            .then(|| v.trim().trim_matches(|c| c == '"' || c == '\'').to_string())
+            .filter(|v| !v.is_empty())
+    })
+}
+
+fn parse_cargo(text: &str) -> (Vec<String>, Vec<(&'static str, Vec<String>)>) {
+    let mut facts = Vec::new();
+    let mut bins = Vec::new();
+    let mut features = Vec::new();
+    let mut aliases = Vec::new();
+    for (table, lines) in toml_tables(text) {
+        match table.as_str() {
+            "package" => {
+                if let Some(name) = toml_string(&lines, "name") {
+                    facts.push(format!("package {}", name));
+                }
+            }
+            "workspace" => facts.push("workspace".to_string()),
+            "bin" => bins.extend(toml_string(&lines, "name")),
+            "features" => features.extend(toml_keys(&lines).into_iter().filter(|f| f != "default")),
+            "alias" => aliases.extend(toml_keys(&lines)),
+            _ => {}
+        }
+    }
+    let tasks = vec![("bins", bins), ("features", features), ("aliases", aliases)];
+    (facts, tasks)
+}
+
+fn parse_package_json(text: &str) -> (Vec<String>, Vec<(&'static str, Vec<String>)>) {
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(text) else {
+        return (Vec::new(), Vec::new());
+    };
+    let mut facts = Vec::new();
+    if let Some(name) = json["name"].as_str() {
+        facts.push(format!("package {}", name));
+    }
+    if json.get("workspaces").is_some() {
+        facts.push("workspaces".to_string());
+    }
+    let scripts = json["scripts"]
+        .as_object()
+        .map(|s| s.keys().cloned().collect())
+        .unwrap_or_default();
+    (facts, vec![("scripts", scripts)])
+}
+
+fn parse_pyproject(text: &str) -> (Vec<String>, Vec<(&'static str, Vec<String>)>) {
+    let mut facts = Vec::new();
+    let mut scripts = Vec::new();
+    let mut tasks = Vec::new();
+    for (table, lines) in toml_tables(text) {
+        match table.as_str() {
+            "project" | "tool.poetry" => {
+                if let Some(name) = toml_string(&lines, "name") {
+                    facts.push(format!("package {}", name));
+                }
+            }
+            "project.scripts" | "tool.poetry.scripts" => scripts.extend(toml_keys(&lines)),
+            "tool.pdm.scripts" | "tool.poe.tasks" | "tool.rye.scripts" => tasks.extend(toml_keys(&lines)),
+            t if t.starts_with("tool.pytest") => facts.push("pytest".to_string()),
+            t if t.starts_with("tool.hatch.envs.") && t.ends_with(".scripts") => tasks.extend(toml_keys(&lines)),
+            _ => {}
+        }
+    }
+    facts.dedup();
+    (facts, vec![("entry points", scripts), ("tasks", tasks)])
+}
+
+fn parse_go_mod(text: &str) -> Vec<String> {
+    text.lines()
+        .find_map(|l| l.trim().strip_prefix("module "))
+        .map(|m| vec![format!("module {}", m.trim())])
+        .unwrap_or_default()
+}
+
+fn parse_makefile(text: &str) -> Vec<String> {
+    let mut targets: Vec<String> = Vec::new();
+    for line in text.lines() {
+        if let Some(caps) = MAKE_TARGET_RE.captures(line) {
+            let name = &caps[1];
+            // Special targets (.PHONY) and file targets are not tasks
+            if name.starts_with('.') || name.contains('/') || name.contains('.') {
+                continue;
+            }
+            if !targets.iter().any(|t| t == name) {
+                targets.push(name.to_string());
+            }
+        }
+    }
+    targets
+}
+
+fn parse_justfile(text: &str) -> Vec<String> {
+    text.lines()
+        .filter(|line| !line.starts_with(char::is_whitespace))
+        .filter(|line| !line.starts_with("set ") && !line.starts_with("alias ") && !line.starts_with("import "))
+        .filter_map(|line| JUST_RECIPE_RE.captures(line).map(|c| c[1].to_string()))
+        .collect()
+}
+
+/// Service names under the top-level `services:` key of a Compose file.
+fn parse_compose(text: &str) -> Vec<String> {
+    let mut services = Vec::new();
+    let mut in_services = false;
+    let mut indent: Option<usize> = None;
+    for line in text.lines() {
+        let trimmed = line.trim_end();
+        if trimmed.is_empty() || trimmed.trim_start().starts_with('#') {
+            continue;
+        }
+        let depth = trimmed.len() - trimmed.trim_start().len();
+        if depth == 0 {
+            in_services = trimmed == "services:";
+            continue;
+        }
+        if !in_services {
+            continue;
+        }
+        let level = *indent.get_or_insert(depth);
+        if depth == level {
+            if let Some(name) = trimmed.trim_start().strip_suffix(':') {
+                services.push(name.trim_matches(|c| c == '"' || c == '\'').to_string());
+            }
+        }
+    }
+    services
+}
+
+/// Node package manager, from the lockfile next to package.json.
+fn node_runner(dir: &Path) -> &'static str {
+    if dir.join("pnpm-lock.yaml").is_file() {
+        "pnpm run"
+    } else if dir.join("yarn.lock").is_file() {
+        "yarn"
+    } else if dir.join("bun.lockb").is_file() || dir.join("bun.lock").is_file() {
+        "bun run"
+    } else {
+        "npm run"
+    }
+}
+
+/// Python environment tool, from the lockfile next to pyproject.toml.
+fn python_runner(dir: &Path) -> Option<&'static str> {
+    if dir.join("uv.lock").is_file() {
+        Some("uv run")
+    } else if dir.join("poetry.lock").is_file() {
+        Some("poetry run")
+    } else if dir.join("pdm.lock").is_file() {
+        Some("pdm run")
+    } else {
+        None
+    }
+}
+
+/// Parse one manifest. `path` is absolute, `display` relative to the CWD.
+fn parse_manifest(path: &Path, display: String) -> Option<Manifest> {
+    let name = path.file_name()?.to_str()?;
+    let dir = path.parent()?;
+    let text = std::fs::read_to_string(path).ok()?;
+
+    let (kind, runner, facts, tasks) = match name {
+        "Cargo.toml" => {
+            let (facts, tasks) = parse_cargo(&text);
+            ("Rust", Some("cargo"), facts, tasks)
+        }
+        "package.json" => {
+            let (facts, tasks) = parse_package_json(&text);
+            ("Node", Some(node_runner(dir)), facts, tasks)
+        }
+        "pyproject.toml" => {
+            let (facts, tasks) = parse_pyproject(&text);
+            ("Python", python_runner(dir), facts, tasks)
+        }
+        "setup.py" | "requirements.txt" => ("Python", None, Vec::new(), Vec::new()),
+        "go.mod" => ("Go", Some("go"), parse_go_mod(&text), Vec::new()),
+        "Makefile" => ("Make", Some("make"), Vec::new(), vec![("targets", parse_makefile(&text))]),
+        "justfile" => ("just", Some("just"), Vec::new(), vec![("recipes", parse_justfile(&text))]),
+        "docker-compose.yml" | "compose.yaml" => (
+            "Docker Compose",
+            Some("docker compose"),
+            Vec::new(),
+            vec![("services", parse_compose(&text))],
+        ),
+        _ => return None,
+    };
+
+    Some(Manifest {
+        path: display,
+        kind,
+        runner,
+        facts,
+        tasks: tasks.into_iter().filter(|(_, names)| !names.is_empty()).collect(),
+    })
+}
+
+/// Detect the manifests around `cwd` and the tasks they define, nearest first.
+pub fn detect(cwd: &str) -> Vec<Manifest> {
+    find_manifests(cwd)
+        .into_iter()
+        .filter_map(|rel| parse_manifest(&Path::new(cwd).join(&rel), rel))
+        .collect()
+}
+
+/// One-line summary of a manifest, e.g.
+/// `package.json (pnpm run): package web; scripts: build, dev, test`.
+fn describe_manifest(m: &Manifest) -> String {
+    let mut line = m.path.clone();
+    if let Some(runner) = m.runner {
+        line.push_str(&format!(" ({})", runner));
+    }
+    let mut details: Vec<String> = m.facts.clone();
+    for (label, names) in &m.tasks {
+        let shown = names.iter().take(MAX_TASKS).cloned().collect::<Vec<_>>().join(", ");
+        let more = names.len().saturating_sub(MAX_TASKS);
+        details.push(if more > 0 {
+            format!("{}: {} +{} more", label, shown, more)
+        } else {
+            format!("{}: {}", label, shown)
+        });
+    }
+    if !details.is_empty() {
+        line.push_str(": ");
+        line.push_str(&details.join("; "));
+    }
+    line
+}
+
+/// Compact "Project:" section for the prompt, at most `budget_chars` long.
+/// Manifests that do not fit are dropped, farthest from the CWD first.
+pub fn describe(manifests: &[Manifest], budget_chars: usize) -> Option<String> {
+    if manifests.is_empty() {
+        return None;
+    }
+    let mut kinds: Vec<&str> = Vec::new();
+    for m in manifests {
+        if !kinds.contains(&m.kind) {
+            kinds.push(m.kind);
+        }
+    }
+    let mut out = format!("Project: {}", kinds.join(", "));
+    for m in manifests {
+        let line = describe_manifest(m);
+        if out.len() + line.len() + 3 > budget_chars {
+            break;
+        }
+        out.push_str("\n- ");
+        out.push_str(&line);
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_manifests_stops_at_repo_root() {
+        let root = std::env::temp_dir().join(format!("cmdk-project-{}", std::process::id()));
+        let nested = root.join("repo/crates/core");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::create_dir_all(root.join("repo/.git")).unwrap();
+        std::fs::write(root.join("package.json"), "{}").unwrap();
+        std::fs::write(root.join("repo/Cargo.toml"), "").unwrap();
+        std::fs::write(root.join("repo/Makefile"), "").unwrap();
+        std::fs::write(nested.join("Cargo.toml"), "").unwrap();
+
+        let found = find_manifests(nested.to_str().unwrap());
+        assert_eq!(found, vec!["Cargo.toml", "../../Cargo.toml", "../../Makefile"]);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_parse_cargo() {
+        let text = "[package]\nname = \"cmd-k\"\nversion = \"0.1\"\n\n[[bin]]\nname = \"cmdk\"\n\n\
+                    [features]\ndefault = [\"x\"]\nwayland = []\n\n[dependencies]\nserde = \"1\"\n";
+        let (facts, tasks) = parse_cargo(text);
+        assert_eq!(facts, vec!["package cmd-k"]);
+        assert_eq!(tasks[0], ("bins", vec!["cmdk".to_string()]));
+        assert_eq!(tasks[1], ("features", vec!["wayland".to_string()]));
+    }
+
+    #[test]
+    fn test_parse_package_json() {
+        let (facts, tasks) =
+            parse_package_json(r#"{"name":"web","scripts":{"dev":"vite","test":"vitest run"}}"#);
+        assert_eq!(facts, vec!["package web"]);
+        assert_eq!(tasks[0].1, vec!["dev", "test"]);
+    }
+
+    #[test]
+    fn test_parse_pyproject() {
+        let text = "[project]\nname = \"tool\"\n\n[project.scripts]\ntool = \"tool.cli:main\"\n\n\
+                    [tool.poe.tasks]\nlint = \"ruff check\"\n\"test-all\" = \"pytest\"\n\n[tool.pytest.ini_options]\n";
+        let (facts, tasks) = parse_pyproject(text);
+        assert_eq!(facts, vec!["package tool", "pytest"]);
+        assert_eq!(tasks[0].1, vec!["tool"]);
+        assert_eq!(tasks[1].1, vec!["lint", "test-all"]);
+    }
+
+    #[test]
+    fn test_parse_makefile_and_justfile() {
+        let make = ".PHONY: build test\nCC := gcc\nbuild: deps\n\tcc main.c\ntest:\n\t./run\nmain.o: main.c\nbuild:\n";
+        assert_eq!(parse_makefile(make), vec!["build", "test"]);
+        let just = "set shell := [\"bash\", \"-c\"]\nalias t := test\n\ndefault:\n  just --list\n\n\
+                    test filter='':\n  cargo test {{filter}}\n@release version: build\n  echo\n";
+        assert_eq!(parse_justfile(just), vec!["default", "test", "release"]);
+    }
+
+    #[test]
+    fn test_parse_compose() {
+        let text = "version: \"3\"\nservices:\n  web:\n    image: nginx\n    ports:\n      - \"80:80\"\n  db:\n    image: postgres\nvolumes:\n  data:\n";
+        assert_eq!(parse_compose(text), vec!["web", "db"]);
+    }
+
+    #[test]
+    fn test_describe_respects_budget() {
+        let manifest = |path: &str, kind| Manifest {
+            path: path.to_string(),
+            kind,
+            runner: Some("make"),
+            facts: Vec::new(),
+            tasks: vec![("targets", (0..20).map(|i| format!("t{}", i)).collect())],
+        };
+        let manifests = vec![manifest("Makefile", "Make"), manifest("../Makefile", "Make")];
+        let full = describe(&manifests, PROJECT_BUDGET_CHARS).unwrap();
+        assert!(full.starts_with("Project: Make\n- Makefile (make): targets: t0, t1"));
+        assert!(full.contains("+5 more"));
+        assert!(full.contains("../Makefile"));
+        let tight = describe(&manifests, 120).unwrap();
+        assert!(!tight.contains("../Makefile"));
+        assert!(describe(&[], 100).is_none());
+    }
+}
@@ -0,0 +1,361 @@
+//! Remote sessions (ssh, mosh, Eternal Terminal) running in the terminal.
+//!
+//! When the shell's foreground process is a remote client, the local shell, CWD
+//! and installed tools say nothing about where the next command will run. The
+//! target user, host and port come from the client's command line; the remote OS,
+//! shell and CWD are inferred from the login banner and prompt visible after the
+//! session started.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::process;
+
+/// ssh options that take a value (`-p 22`, `-p22`, `-l user`).
+const SSH_VALUE_OPTIONS: &str = "BbcDEeFIiJLlmOoPpQRSWw";
+
+/// ssh flags for sessions without a remote shell: tunnels only, stdio
+/// forwarding, control commands.
+const SSH_NON_INTERACTIVE_FLAGS: &str = "NOW";
+
+/// mosh long options that take a value when not written as `--opt=value`.
+const MOSH_VALUE_OPTIONS: &[&str] = &[
+    "--ssh", "--port", "-p", "--predict", "--family", "--server", "--client", "--bind-server",
+    "--experimental-remote-ip",
+];
+
+/// Eternal Terminal options that take a value.
+const ET_VALUE_OPTIONS: &[&str] = &[
+    "-p", "--port", "-c", "--command", "-t", "--tunnel", "-r", "--reversetunnel", "--jumphost",
+    "--jport", "--terminal-path", "--serverfifo", "-l", "--logdir", "-v", "--verbose",
+];
+
+/// Operating system names in login banners, `/etc/os-release` and `uname` output.
+static OS_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(Ubuntu \d+\.\d+(?:\.\d+)?(?: LTS)?|Debian GNU/Linux \d+|Amazon Linux(?: 2023| 2)?|Red Hat Enterprise Linux(?: \d+(?:\.\d+)?)?|CentOS(?: Linux| Stream)?(?: \d+)?|Rocky Linux(?: \d+(?:\.\d+)?)?|AlmaLinux(?: \d+(?:\.\d+)?)?|Fedora(?: Linux)? \d+|Alpine Linux|Arch Linux|openSUSE(?: Leap| Tumbleweed)?|Raspbian|FreeBSD \d+\.\d+|OpenBSD \d+\.\d+|Darwin|GNU/Linux)",
+    )
+    .unwrap()
+});
+
+/// `user@host:~/dir$`, `[user@host dir]$`, `user@host ~/dir %`, `user@host ~/dir>`.
+static REMOTE_PROMPT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(?P<open>\[)?(?P<user>[\w.-]+)@(?P<host>[\w.-]+)(?::(?P<path>[~/][^\s$#%>]*)|\s+(?P<dir>[^\s\]$#%>]+))?\]?\s*(?P<sigil>[$#%>])(?:\s|$)",
+    )
+    .unwrap()
+});
+
+/// A remote login running in the terminal, with what could be inferred about
+/// the far side.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RemoteSession {
+    /// "ssh", "mosh" or "et"
+    pub program: String,
+    pub host: String,
+    pub user: Option<String>,
+    pub port: Option<u16>,
+    /// Inferred from the visible text: "Ubuntu 22.04.3 LTS", "Linux", "macOS"
+    pub os: Option<String>,
+    /// Inferred from the prompt style
+    pub shell: Option<String>,
+    /// Inferred from the prompt, e.g. "~/app"
+    pub cwd: Option<String>,
+}
+
+impl RemoteSession {
+    /// "user@host", or the host alone when no user was given.
+    pub fn target(&self) -> String {
+        match &self.user {
+            Some(user) => format!("{}@{}", user, self.host),
+            None => self.host.clone(),
+        }
+    }
+
+    /// Lines for the AI prompt.
+    pub fn describe(&self) -> Vec<String> {
+        let port = self.port.map(|p| format!(", port {}", p)).unwrap_or_default();
+        let mut lines = vec![format!(
+            "Remote session: {} to {}{} (commands run on the remote host, not this machine)",
+            self.program,
+            self.target(),
+            port
+        )];
+        if let Some(os) = &self.os {
+            lines.push(format!("Remote OS: {}", os));
+        }
+        if let Some(shell) = &self.shell {
+            lines.push(format!("Remote shell: {}", shell));
+        }
+        if let Some(cwd) = &self.cwd {
+            lines.push(format!("Remote CWD: {}", cwd));
+        }
+        lines
+    }
+
+    /// Fill in OS, shell, CWD and user from the terminal text shown since the
+    /// session started.
+    pub fn infer_from_output(&mut self, text: &str) {
+        let lines: Vec<&str> = text.lines().collect();
+        // Only what follows the line that started the session describes the far side
+        let start = lines
+            .iter()
+            .rposition(|line| line.contains(&self.program) && line.contains(&self.host))
+            .map_or(0, |i| i + 1);
+        let session = &lines[start..];
+
+        // A distribution name beats the bare "GNU/Linux" of `uname -a` or the banner
+        let found: Vec<&str> = session.iter().flat_map(|line| OS_RE.find_iter(line)).map(|m| m.as_str()).collect();
+        let os = found.iter().rev().find(|name| **name != "GNU/Linux").or(found.last());
+        if let Some(os) = os {
+            self.os = Some(match *os {
+                "Darwin" => "macOS".to_string(),
+                "GNU/Linux" => "Linux".to_string(),
+                name => name.to_string(),
+            });
+        }
+
+        let Some(caps) = session
+            .iter()
+            .rev()
+            .find_map(|line| REMOTE_PROMPT_RE.captures(line.trim_start()))
+        else {
+            return;
+        };
+        if self.user.is_none() {
+            self.user = Some(caps["user"].to_string());
+        }
+        // bash prints the full path after a colon, RHEL's `[user@host dir]` only the basename
+        self.cwd = caps
+            .name("path")
+            .or_else(|| caps.name("dir").filter(|d| d.as_str().starts_with(['~', '/'])))
+            .map(|m| m.as_str().to_string());
+        self.shell = match &caps["sigil"] {
+            "%" => Some("zsh".to_string()),
+            ">" => Some("fish".to_string()),
+            // The stock bash prompts of Debian/Ubuntu and Red Hat
+            _ if caps.name("path").is_some() || caps.name("open").is_some() => Some("bash".to_string()),
+            _ => None,
+        };
+    }
+}
+
+/// Split `[user@]host[:port]` or `ssh://[user@]host[:port]`.
+fn parse_destination(dest: &str, allow_port: bool) -> Option<(Option<String>, String, Option<u16>)> {
+    let (dest, allow_port) = match dest.strip_prefix("ssh://") {
+        Some(rest) => (rest, true),
+        None => (dest, allow_port),
+    };
+    let (user, rest) = match dest.rsplit_once('@') {
+        Some((user, rest)) => (Some(user.to_string()), rest),
+        None => (None, dest),
+    };
+    let (host, port) = match rest.rsplit_once(':') {
+        Some((host, port)) if allow_port && !host.contains(':') => (host, port.parse().ok()),
+        _ => (rest, None),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    (!host.is_empty()).then(|| (user.filter(|u| !u.is_empty()), host.to_string(), port))
+}
+
+/// `-o User=bob` / `-o "Port 2222"`.
+fn ssh_config_option(value: &str, user: &mut Option<String>, port: &mut Option<u16>) {
+    let Some((key, val)) = value.split_once(['=', ' ']) else { return };
+    match key.trim().to_ascii_lowercase().as_str() {
+        "user" => *user = Some(val.trim().to_string()),
+        "port" => *port = val.trim().parse().ok(),
+        _ => {}
+    }
+}
+
+/// Target of an interactive `ssh` command line, or None for tunnels, control
+/// commands and remote commands without `-t`.
+fn parse_ssh(args: &[String]) -> Option<RemoteSession> {
+    let mut user = None;
+    let mut port = None;
+    let mut force_tty = false;
+    let mut destination = None;
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            destination = iter.next();
+            break;
+        }
+        let Some(flags) = arg.strip_prefix('-').filter(|f| !f.is_empty()) else {
+            destination = Some(arg);
+            break;
+        };
+        for (i, flag) in flags.char_indices() {
+            if SSH_NON_INTERACTIVE_FLAGS.contains(flag) {
+                return None;
+            }
+            force_tty |= flag == 't';
+            if SSH_VALUE_OPTIONS.contains(flag) {
+                let inline = &flags[i + flag.len_utf8()..];
+                let value = if inline.is_empty() { iter.next().cloned() } else { Some(inline.to_string()) };
+                match (flag, value) {
+                    ('l', Some(v)) => user = Some(v),
+                    ('p', Some(v)) => port = v.parse().ok(),
+                    ('o', Some(v)) => ssh_config_option(&v, &mut user, &mut port),
+                    _ => {}
+                }
+                break;
+            }
+        }
+    }
+    if iter.next().is_some() && !force_tty {
+        // `ssh host uptime`: a remote command, not a remote shell
+        return None;
+    }
+    let (dest_user, host, dest_port) = parse_destination(destination?, false)?;
+    Some(RemoteSession {
+        program: "ssh".to_string(),
+        host,
+        user: dest_user.or(user),
+        port: dest_port.or(port),
+        ..Default::default()
+    })
+}
+
+/// Destination and port of a command line that takes `[user@]host` after options,
+/// skipping the values of `value_options`.
+fn parse_after_options(
+    program: &str,
+    args: &[String],
+    value_options: &[&str],
+    allow_port: bool,
+) -> Option<RemoteSession> {
+    let mut port = None;
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            continue;
+        }
+        if let Some(value) = arg.strip_prefix("--port=") {
+            port = value.parse().ok();
+        } else if value_options.contains(&arg.as_str()) {
+            let value = iter.next();
+            if matches!(arg.as_str(), "-p" | "--port") {
+                port = value.and_then(|v| v.parse().ok());
+            }
+        } else if !arg.starts_with('-') {
+            let (user, host, dest_port) = parse_destination(arg, allow_port)?;
+            return Some(RemoteSession {
+                program: program.to_string(),
+                host,
+                user,
+                port: dest_port.or(port),
+                ..Default::default()
+            });
+        }
+    }
+    None
+}
+
+/// Target of a remote client command line: `ssh`, `mosh` (or the `mosh-client`
+/// it execs, which keeps the original arguments after `-#`) and `et`.
+fn parse_client(name: &str, args: &[String]) -> Option<RemoteSession> {
+    match name {
+        "ssh" => parse_ssh(args),
+        "mosh" => parse_after_options("mosh", args, MOSH_VALUE_OPTIONS, false),
+        "mosh-client" => {
+            // argv[0] is "mosh-client -# <original mosh arguments> |", then ip and port
+            let words: Vec<&str> = args.iter().flat_map(|a| a.split_whitespace()).collect();
+            let start = words.iter().position(|w| *w == "-#")?;
+            let original: Vec<String> = std::iter::once("mosh")
+                .chain(words[start + 1..].iter().copied().take_while(|w| *w != "|"))
+                .map(String::from)
+                .collect();
+            parse_after_options("mosh", &original, MOSH_VALUE_OPTIONS, false)
+        }
+        "et" => parse_after_options("et", args, ET_VALUE_OPTIONS, true),
+        _ => None,
+    }
+}
+
+/// The interactive remote session running in the shell `shell_pid`, or None
+/// when the shell's foreground process is not ssh, mosh or et.
+pub fn detect(shell_pid: i32) -> Option<RemoteSession> {
+    let session = process::get_child_pids(shell_pid).into_iter().find_map(|pid| {
+        let mut name = process::get_process_name(pid)?;
+        let mut args = process::get_process_args(pid);
+        // mosh is a perl script until it execs mosh-client: perl /usr/bin/mosh host
+        if name.starts_with("perl") && args.get(1).is_some_and(|a| a == "mosh" || a.ends_with("/mosh")) {
+            args.remove(0);
+            name = "mosh".to_string();
+        }
+        parse_client(&name, &args)
+    })?;
+    eprintln!("[remote] {} session to {}", session.program, session.target());
+    Some(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn target(name: &str, line: &str) -> Option<(String, Option<u16>)> {
+        parse_client(name, &args(line)).map(|s| (s.target(), s.port))
+    }
+
+    #[test]
+    fn test_parse_ssh_command_lines() {
+        assert_eq!(target("ssh", "ssh deploy@web-1"), Some(("deploy@web-1".into(), None)));
+        assert_eq!(target("ssh", "ssh -p 2222 -i ~/.ssh/id web-1"), Some(("web-1".into(), Some(2222))));
+        assert_eq!(target("ssh", "ssh -vp2200 -l admin db"), Some(("admin@db".into(), Some(2200))));
+        assert_eq!(target("ssh", "ssh -o User=ops -o Port=22 -J bastion ssh://10.0.0.5:2022"), Some(("ops@10.0.0.5".into(), Some(2022))));
+        assert_eq!(target("ssh", "ssh -t host tmux attach"), Some(("host".into(), None)));
+        // A remote command, a tunnel, a control command: no remote shell
+        assert_eq!(target("ssh", "ssh host uptime"), None);
+        assert_eq!(target("ssh", "ssh -N -L 8080:localhost:80 host"), None);
+        assert_eq!(target("ssh", "ssh -O exit host"), None);
+    }
+
+    #[test]
+    fn test_parse_mosh_and_et() {
+        assert_eq!(target("mosh", "mosh --ssh=ssh -p 60001 pi@raspberry"), Some(("pi@raspberry".into(), Some(60001))));
+        assert_eq!(target("mosh", "mosh --predict always box -- tmux"), Some(("box".into(), None)));
+        let client = vec!["mosh-client -# dev@vm |".to_string(), "10.1.2.3".into(), "60002".into()];
+        assert_eq!(parse_client("mosh-client", &client).map(|s| s.target()).as_deref(), Some("dev@vm"));
+        assert_eq!(target("et", "et -t 8080:80 me@devbox:8080"), Some(("me@devbox".into(), Some(8080))));
+        assert_eq!(target("zsh", "zsh"), None);
+    }
+
+    #[test]
+    fn test_infer_from_ubuntu_login() {
+        let mut session = parse_client("ssh", &args("ssh web-1")).unwrap();
+        session.infer_from_output(
+            "me@laptop ~ % uname\nDarwin\nme@laptop ~ % ssh web-1\n\
+             Welcome to Ubuntu 22.04.3 LTS (GNU/Linux 5.15.0-91-generic x86_64)\n\
+             Last login: Mon Oct 12 09:14:02 2026\n\
+             deploy@web-1:~$ cd /srv/app\n\
+             deploy@web-1:/srv/app$ ",
+        );
+        assert_eq!(session.os.as_deref(), Some("Ubuntu 22.04.3 LTS"));
+        assert_eq!(session.shell.as_deref(), Some("bash"));
+        assert_eq!(session.cwd.as_deref(), Some("/srv/app"));
+        assert_eq!(session.target(), "deploy@web-1");
+        assert_eq!(
+            session.describe()[0],
+            "Remote session: ssh to deploy@web-1 (commands run on the remote host, not this machine)"
+        );
+    }
+
+    #[test]
+    fn test_infer_from_other_prompts() {
+        let mut rhel = RemoteSession { program: "ssh".into(), host: "db".into(), ..Default::default() };
+        rhel.infer_from_output("$ ssh db\nRed Hat Enterprise Linux 9.3\n[admin@db data]$ ");
+        assert_eq!(rhel.os.as_deref(), Some("Red Hat Enterprise Linux 9.3"));
+        assert_eq!((rhel.shell.as_deref(), rhel.cwd.as_deref()), (Some("bash"), None));
+
+        let mut zsh = RemoteSession { program: "ssh".into(), host: "mini".into(), ..Default::default() };
+        zsh.infer_from_output("ssh mini\nme@mini ~/src % ");
+        assert_eq!((zsh.shell.as_deref(), zsh.cwd.as_deref()), (Some("zsh"), Some("~/src")));
+        assert_eq!(zsh.os, None);
+    }
+}
@@ -0,0 +1,316 @@
+//! Language REPLs running in the terminal: python, node, psql, mysql, sqlite3,
+//! redis-cli, irb and ghci.
+//!
+//! While a REPL has the foreground, whatever is pasted is read by the
+//! interpreter, not the shell. A process name alone is not enough: `python
+//! manage.py runserver` and `psql -c ...` are not interactive, so the argv decides.
+
+use serde::{Deserialize, Serialize};
+
+use super::process;
+
+/// Python options that take a value.
+const PYTHON_VALUE_OPTIONS: &[&str] = &["-W", "-X", "--check-hash-based-pycs"];
+
+/// Node options that take a value.
+const NODE_VALUE_OPTIONS: &[&str] = &[
+    "-r", "--require", "--import", "--loader", "--experimental-loader", "-C", "--conditions",
+    "--inspect-port", "--title",
+];
+
+/// redis-cli options that take a value.
+const REDIS_VALUE_OPTIONS: &[&str] = &[
+    "-h", "-p", "-s", "-a", "-u", "-n", "-r", "-i", "-d", "-D", "--user", "--pass", "--cacert",
+    "--cert", "--key", "--sni",
+];
+
+/// sqlite3 options that take a value.
+const SQLITE_VALUE_OPTIONS: &[&str] = &[
+    "-cmd", "-separator", "-newline", "-nullvalue", "-vfs", "-mmap", "-pagecache", "-lookaside",
+    "-maxsize",
+];
+
+/// REPLs that some other interpreter runs as a script: `ruby /usr/bin/irb`.
+const SCRIPT_REPLS: &[&str] = &[
+    "irb", "pry", "ipython", "bpython", "ptpython", "pgcli", "mycli", "litecli", "iredis",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplKind {
+    Python,
+    Node,
+    Postgres,
+    Mysql,
+    Sqlite,
+    Redis,
+    Ruby,
+    Haskell,
+}
+
+impl ReplKind {
+    /// Language or dialect the REPL reads, for the prompt.
+    pub fn language(self) -> &'static str {
+        match self {
+            Self::Python => "Python",
+            Self::Node => "JavaScript (Node.js)",
+            Self::Postgres => "PostgreSQL",
+            Self::Mysql => "MySQL",
+            Self::Sqlite => "SQLite",
+            Self::Redis => "Redis",
+            Self::Ruby => "Ruby",
+            Self::Haskell => "Haskell",
+        }
+    }
+
+    /// How input for this REPL is written: syntax, client commands, what to avoid.
+    pub fn input_rules(self) -> &'static str {
+        match self {
+            Self::Python => {
+                "Output Python for the interactive interpreter. Prefer a single statement or expression; \
+                 end compound statements with a blank line. Use os, shutil, pathlib or subprocess instead \
+                 of shell commands."
+            }
+            Self::Node => {
+                "Output JavaScript for the Node.js REPL. Use require() or await import(); static import \
+                 statements are not allowed. Top-level await works. Use fs or child_process instead of \
+                 shell commands."
+            }
+            Self::Postgres => {
+                "Output SQL in the PostgreSQL dialect, ending each statement with a semicolon. psql \
+                 meta-commands such as \\dt, \\d table and \\x are allowed."
+            }
+            Self::Mysql => {
+                "Output SQL in the MySQL dialect, ending each statement with a semicolon. Client \
+                 statements such as SHOW TABLES and USE db are allowed."
+            }
+            Self::Sqlite => {
+                "Output SQL in the SQLite dialect, ending each statement with a semicolon. Dot-commands \
+                 such as .tables and .schema are allowed."
+            }
+            Self::Redis => {
+                "Output Redis commands as typed at the redis-cli prompt (GET key, HGETALL key), one per \
+                 line, without a redis-cli prefix. Prefer SCAN over KEYS."
+            }
+            Self::Ruby => "Output Ruby for irb. In a Rails console, ActiveRecord models are loaded.",
+            Self::Haskell => {
+                "Output Haskell for GHCi: expressions, let bindings, or GHCi commands such as :t, :i \
+                 and :load."
+            }
+        }
+    }
+
+    /// Whether input is SQL, for the safety analysis.
+    pub fn is_sql(self) -> bool {
+        matches!(self, Self::Postgres | Self::Mysql | Self::Sqlite)
+    }
+}
+
+/// A REPL with the terminal's foreground.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplSession {
+    pub kind: ReplKind,
+    /// Process as seen: "python3.12", "psql", "ruby bin/rails console"
+    pub program: String,
+}
+
+impl ReplSession {
+    /// Context line for the AI prompt.
+    pub fn describe(&self) -> String {
+        format!(
+            "REPL: {} ({}); input goes to the interpreter, not the shell",
+            self.kind.language(),
+            self.program
+        )
+    }
+}
+
+/// `python3.12` -> `python`, `ghc-9.4.7` -> `ghc`, `/usr/bin/sqlite3` -> `sqlite`.
+fn base_program(name: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    let name = name.strip_suffix(".exe").unwrap_or(name).to_ascii_lowercase();
+    name.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.' || c == '-').to_string()
+}
+
+/// Whether `names` appear among the options, as `-c`, `--command` or `--command=...`.
+fn has_option(args: &[String], names: &[&str]) -> bool {
+    args.iter().any(|arg| {
+        names
+            .iter()
+            .any(|name| arg == name || arg.strip_prefix(name).is_some_and(|rest| rest.starts_with('=')))
+    })
+}
+
+/// Positional arguments, skipping options and the values of `value_options`.
+fn positionals<'a>(args: &'a [String], value_options: &[&str]) -> Vec<&'a String> {
+    let mut out = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            out.extend(iter.by_ref());
+            break;
+        }
+        if value_options.contains(&arg.as_str()) {
+            iter.next();
+        } else if !arg.starts_with('-') {
+            out.push(arg);
+        }
+    }
+    out
+}
+
+/// Python-style interpreters: interactive unless given a script, `-c` or `-m`,
+/// and always with `-i`.
+fn interpreter_is_interactive(args: &[String], value_options: &[&str], one_shot: &[&str]) -> bool {
+    // Options after the script belong to the script
+    let script = args.iter().position(|arg| !arg.starts_with('-'));
+    let own = &args[..script.unwrap_or(args.len())];
+    if has_option(own, &["-i", "--interactive"]) {
+        return true;
+    }
+    !has_option(own, one_shot) && positionals(args, value_options).is_empty()
+}
+
+/// Classify a process by name and argv (argv[0] included). `args` is empty when
+/// the platform does not expose them; then only programs that are always
+/// interactive count.
+fn classify(name: &str, args: &[String]) -> Option<ReplKind> {
+    let mut program = base_program(name);
+    let mut rest: &[String] = args.get(1..).unwrap_or_default();
+    if args.is_empty() {
+        return match program.as_str() {
+            "irb" | "pry" => Some(ReplKind::Ruby),
+            "ghci" => Some(ReplKind::Haskell),
+            _ => None,
+        };
+    }
+
+    // Interpreter running a script: a REPL written in it, a Rails or Django console
+    if matches!(program.as_str(), "ruby" | "python" | "perl" | "node") {
+        if let Some(script) = rest.first().filter(|arg| !arg.starts_with('-')) {
+            let script_name = base_program(script);
+            let command = rest.get(1).map(String::as_str);
+            if SCRIPT_REPLS.contains(&script_name.as_str()) {
+                program = script_name;
+                rest = &rest[1..];
+            } else if script_name == "rails" && matches!(command, Some("c" | "console")) {
+                return Some(ReplKind::Ruby);
+            } else if script_name == "manage.py" && matches!(command, Some("shell" | "shell_plus")) {
+                return Some(ReplKind::Python);
+            }
+        }
+    }
+
+    match program.as_str() {
+        "python" | "ipython" | "bpython" | "ptpython" => {
+            interpreter_is_interactive(rest, PYTHON_VALUE_OPTIONS, &["-c", "-m"]).then_some(ReplKind::Python)
+        }
+        "node" => {
+            let one_shot = ["-e", "--eval", "-p", "--print", "--test"];
+            interpreter_is_interactive(rest, NODE_VALUE_OPTIONS, &one_shot).then_some(ReplKind::Node)
+        }
+        "psql" | "pgcli" => {
+            let one_shot = ["-c", "--command", "-f", "--file", "-l", "--list", "-V", "--version", "--help"];
+            (!has_option(rest, &one_shot)).then_some(ReplKind::Postgres)
+        }
+        "mysql" | "mariadb" | "mycli" => {
+            (!has_option(rest, &["-e", "--execute", "-V", "--version", "--help"])).then_some(ReplKind::Mysql)
+        }
+        // sqlite3 db.sqlite "SELECT ..." runs the statement and exits
+        "sqlite" | "litecli" => (positionals(rest, SQLITE_VALUE_OPTIONS).len() < 2).then_some(ReplKind::Sqlite),
+        // redis-cli GET key runs one command
+        "redis-cli" | "valkey-cli" | "iredis" => {
+            positionals(rest, REDIS_VALUE_OPTIONS).is_empty().then_some(ReplKind::Redis)
+        }
+        "irb" | "pry" => Some(ReplKind::Ruby),
+        "ghci" => Some(ReplKind::Haskell),
+        // ghci, `stack ghci` and `cabal repl` all end up as ghc --interactive
+        "ghc" => has_option(rest, &["--interactive"]).then_some(ReplKind::Haskell),
+        _ => None,
+    }
+}
+
+/// Find a REPL among the shell's children, or their children for launchers
+/// such as `stack ghci` and `manage.py dbshell`.
+pub fn detect(shell_pid: i32) -> Option<ReplSession> {
+    let classify_pid = |pid: i32| {
+        let name = process::get_process_name(pid)?;
+        let args = process::get_process_args(pid);
+        let kind = classify(&name, &args)?;
+        let program = match args.get(1) {
+            Some(script) if matches!(base_program(&name).as_str(), "ruby" | "python" | "perl" | "node") => {
+                let script = script.rsplit('/').next().unwrap_or(script);
+                let command = args.get(2).filter(|_| matches!(script, "rails" | "manage.py"));
+                match command {
+                    Some(command) => format!("{} {} {}", name, script, command),
+                    None if SCRIPT_REPLS.contains(&base_program(script).as_str()) => script.to_string(),
+                    None => name,
+                }
+            }
+            _ => name,
+        };
+        Some(ReplSession { kind, program })
+    };
+    let children = process::get_child_pids(shell_pid);
+    let session = children
+        .iter()
+        .find_map(|&pid| classify_pid(pid))
+        .or_else(|| {
+            children
+                .iter()
+                .flat_map(|&pid| process::get_child_pids(pid))
+                .find_map(classify_pid)
+        })?;
+    eprintln!("[repl] {} REPL: {}", session.kind.language(), session.program);
+    Some(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(line: &str) -> Option<ReplKind> {
+        let args: Vec<String> = line.split_whitespace().map(String::from).collect();
+        classify(&args[0], &args)
+    }
+
+    #[test]
+    fn test_interpreters_without_a_script_are_repls() {
+        assert_eq!(kind("python3.12"), Some(ReplKind::Python));
+        assert_eq!(kind("/usr/bin/python3 -q -X dev"), Some(ReplKind::Python));
+        assert_eq!(kind("python -i analysis.py"), Some(ReplKind::Python));
+        assert_eq!(kind("python manage.py runserver"), None);
+        assert_eq!(kind("python -m http.server"), None);
+        assert_eq!(kind("python script.py -i"), None);
+        assert_eq!(kind("node --require dotenv/config"), Some(ReplKind::Node));
+        assert_eq!(kind("node server.js"), None);
+        assert_eq!(kind("node -e console.log(1)"), None);
+    }
+
+    #[test]
+    fn test_database_clients() {
+        assert_eq!(kind("psql -h db -U app appdb"), Some(ReplKind::Postgres));
+        assert_eq!(kind("psql -c select"), None);
+        assert_eq!(kind("psql --file=migrate.sql"), None);
+        assert_eq!(kind("mysql -u root shop"), Some(ReplKind::Mysql));
+        assert_eq!(kind("mysql -e show"), None);
+        assert_eq!(kind("sqlite3 app.db"), Some(ReplKind::Sqlite));
+        assert_eq!(kind("sqlite3 app.db .tables"), None);
+        assert_eq!(kind("redis-cli -h cache -n 2"), Some(ReplKind::Redis));
+        assert_eq!(kind("redis-cli -h cache GET key"), None);
+    }
+
+    #[test]
+    fn test_scripts_and_launchers() {
+        assert_eq!(kind("ruby /usr/bin/irb"), Some(ReplKind::Ruby));
+        assert_eq!(kind("ruby bin/rails console"), Some(ReplKind::Ruby));
+        assert_eq!(kind("ruby bin/rails server"), None);
+        assert_eq!(kind("python3 manage.py shell"), Some(ReplKind::Python));
+        assert_eq!(kind("python3 /usr/bin/ipython3"), Some(ReplKind::Python));
+        assert_eq!(kind("ghc-9.4.7 --interactive -isrc"), Some(ReplKind::Haskell));
+        assert_eq!(kind("ghc -O2 Main.hs"), None);
+        // Without argv only always-interactive programs count
+        assert_eq!(classify("ghci", &[]), Some(ReplKind::Haskell));
+        assert_eq!(classify("python", &[]), None);
+    }
+}
@@ -0,0 +1,357 @@
+//! Recent commands from the shell's history file.
+//!
+//! When the terminal's text cannot be read and no shell hook is installed, the
+//! history file still shows what the user has been running. Only the tail of the
+//! file is read, every command passes through `filter_sensitive`, and the path is
+//! resolved the way each shell does it: the shell's environment first (`HISTFILE`,
+//! `XDG_DATA_HOME`, `APPDATA`), then the default location.
+//!
+//! `HISTFILE` is usually a shell variable rather than an exported one, so a custom
+//! path set in an rc file without `export` is missed and the default is tried.
+//! Shells append on exit (bash) or per command (zsh `INC_APPEND_HISTORY`, fish,
+//! PSReadLine), so a bash session's own commands may not be there yet.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use super::{filter, hooks, process};
+
+/// Only the end of a history file is read.
+const READ_LIMIT: u64 = 64 * 1024;
+
+/// Commands included in the context.
+pub const MAX_COMMANDS: usize = 15;
+
+/// Longest command kept; longer ones are cut.
+const MAX_COMMAND_CHARS: usize = 300;
+
+/// One command from the history file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShellCommand {
+    pub command: String,
+    /// Unix seconds when the command was run, for formats that record it
+    pub timestamp: Option<u64>,
+}
+
+/// History file for `shell`, or None for shells without a supported format.
+fn history_path(shell: &str, var: &dyn Fn(&str) -> Option<String>, home: &Path) -> Option<PathBuf> {
+    match shell {
+        "bash" => Some(var("HISTFILE").map_or_else(|| home.join(".bash_history"), PathBuf::from)),
+        "zsh" => Some(var("HISTFILE").map(PathBuf::from).unwrap_or_else(|| {
+            let dir = var("ZDOTDIR").map_or_else(|| home.to_path_buf(), PathBuf::from);
+            dir.join(".zsh_history")
+        })),
+        "fish" => {
+            let data = var("XDG_DATA_HOME").map_or_else(|| home.join(".local").join("share"), PathBuf::from);
+            // $fish_history selects the session name; "default" maps to "fish"
+            let session = var("fish_history")
+                .filter(|s| s != "default")
+                .unwrap_or_else(|| "fish".to_string());
+            Some(data.join("fish").join(format!("{}_history", session)))
+        }
+        "powershell" | "pwsh" => {
+            let dir = match var("APPDATA") {
+                Some(appdata) => PathBuf::from(appdata).join("Microsoft").join("Windows").join("PowerShell"),
+                None => var("XDG_DATA_HOME")
+                    .map_or_else(|| home.join(".local").join("share"), PathBuf::from)
+                    .join("powershell"),
+            };
+            Some(dir.join("PSReadLine").join("ConsoleHost_history.txt"))
+        }
+        _ => None,
+    }
+}
+
+/// Undo zsh's metafication: bytes 0x83 (Meta) followed by `c` encode `c ^ 0x20`.
+fn unmetafy(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut iter = bytes.iter();
+    while let Some(&b) = iter.next() {
+        if b == 0x83 {
+            if let Some(&next) = iter.next() {
+                out.push(next ^ 0x20);
+            }
+        } else {
+            out.push(b);
+        }
+    }
+    out
+}
+
+/// bash: one command per line, optionally preceded by a `#<epoch>` line when
+/// `HISTTIMEFORMAT` is set.
+fn parse_bash(text: &str) -> Vec<ShellCommand> {
+    let mut commands = Vec::new();
+    let mut timestamp = None;
+    for line in text.lines() {
+        if let Some(ts) = line.strip_prefix('#').and_then(|t| t.parse::<u64>().ok()) {
+            timestamp = Some(ts);
+            continue;
+        }
+        if !line.trim().is_empty() {
+            commands.push(ShellCommand { command: line.to_string(), timestamp: timestamp.take() });
+        }
+    }
+    commands
+}
+
+/// zsh: plain lines, or `: <epoch>:<duration>;<command>` with `EXTENDED_HISTORY`.
+/// Multi-line commands continue with a trailing backslash.
+fn parse_zsh(text: &str) -> Vec<ShellCommand> {
+    let mut commands: Vec<ShellCommand> = Vec::new();
+    let mut continuing = false;
+    for line in text.lines() {
+        if continuing {
+            if let Some(last) = commands.last_mut() {
+                last.command.push('\n');
+                last.command.push_str(line.strip_suffix('\\').unwrap_or(line));
+            }
+            continuing = line.ends_with('\\');
+            continue;
+        }
+        let (timestamp, command) = match line
+            .strip_prefix(": ")
+            .and_then(|rest| rest.split_once(';'))
+            .and_then(|(meta, cmd)| Some((meta.split(':').next()?.trim().parse::<u64>().ok()?, cmd)))
+        {
+            Some((ts, cmd)) => (Some(ts), cmd),
+            None => (None, line),
+        };
+        continuing = command.ends_with('\\');
+        let command = command.strip_suffix('\\').unwrap_or(command);
+        if continuing || !command.trim().is_empty() {
+            commands.push(ShellCommand { command: command.to_string(), timestamp });
+        }
+    }
+    commands
+}
+
+/// fish: a YAML-like list of `- cmd: <command>` entries with `when: <epoch>`.
+/// Newlines and backslashes in the command are escaped as `\n` and `\\`.
+fn parse_fish(text: &str) -> Vec<ShellCommand> {
+    let mut commands: Vec<ShellCommand> = Vec::new();
+    for line in text.lines() {
+        if let Some(cmd) = line.strip_prefix("- cmd: ") {
+            let mut command = String::with_capacity(cmd.len());
+            let mut chars = cmd.chars();
+            while let Some(c) = chars.next() {
+                match (c, chars.clone().next()) {
+                    ('\\', Some('n')) => {
+                        command.push('\n');
+                        chars.next();
+                    }
+                    ('\\', Some('\\')) => {
+                        command.push('\\');
+                        chars.next();
+                    }
+                    _ => command.push(c),
+                }
+            }
+            commands.push(ShellCommand { command, timestamp: None });
+        } else if let Some(when) = line.trim_start().strip_prefix("when: ") {
+            if let Some(last) = commands.last_mut() {
+                last.timestamp = when.trim().parse().ok();
+            }
+        }
+    }
+    commands
+}
+
+/// PSReadLine: one command per line; a trailing backtick continues it on the next.
+fn parse_psreadline(text: &str) -> Vec<ShellCommand> {
+    let mut commands: Vec<ShellCommand> = Vec::new();
+    let mut continuing = false;
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+        match commands.last_mut() {
+            Some(last) if continuing => {
+                last.command.push('\n');
+                last.command.push_str(line);
+            }
+            _ if line.trim().is_empty() => {}
+            _ => commands.push(ShellCommand { command: line.to_string(), timestamp: None }),
+        }
+        continuing = line.ends_with('`');
+    }
+    commands
+}
+
+/// Parse the tail of a history file. Text before the first line break may be a
+/// partial entry cut off by tail reading and is dropped when the file was cut.
+fn parse(shell: &str, text: &str, truncated: bool) -> Vec<ShellCommand> {
+    let text = match (truncated, text.find('\n')) {
+        (true, Some(i)) => &text[i + 1..],
+        _ => text,
+    };
+    match shell {
+        "bash" => parse_bash(text),
+        "zsh" => parse_zsh(text),
+        "fish" => {
+            // Resynchronise on the first complete entry
+            let start = if truncated { text.find("- cmd: ").unwrap_or(text.len()) } else { 0 };
+            parse_fish(&text[start..])
+        }
+        "powershell" | "pwsh" => parse_psreadline(text),
+        _ => Vec::new(),
+    }
+}
+
+/// The last `limit` distinct commands, oldest first, filtered for secrets.
+fn select_recent(commands: Vec<ShellCommand>, limit: usize) -> Vec<ShellCommand> {
+    let mut recent: Vec<ShellCommand> = Vec::new();
+    for entry in commands.into_iter().rev() {
+        if recent.len() >= limit {
+            break;
+        }
+        if recent.iter().any(|r| r.command == entry.command) {
+            continue;
+        }
+        recent.push(entry);
+    }
+    recent.reverse();
+    for entry in &mut recent {
+        let mut command = filter::filter_sensitive(entry.command.trim());
+        if command.chars().count() > MAX_COMMAND_CHARS {
+            command = command.chars().take(MAX_COMMAND_CHARS).collect::<String>() + "...";
+        }
+        entry.command = command;
+    }
+    recent
+}
+
+/// Recent commands from the history file of `shell` running as `shell_pid`.
+pub fn recent_commands(shell: &str, shell_pid: Option<i32>) -> Vec<ShellCommand> {
+    let env: HashMap<String, String> = shell_pid.map(process::get_process_env).unwrap_or_default();
+    let var = |key: &str| -> Option<String> {
+        env.get(key)
+            .cloned()
+            .or_else(|| std::env::var(key).ok())
+            .filter(|v| !v.is_empty())
+    };
+    let Some(home) = var("HOME").or_else(|| var("USERPROFILE")).map(PathBuf::from) else {
+        return Vec::new();
+    };
+    let Some(path) = history_path(shell, &var, &home) else {
+        return Vec::new();
+    };
+    let Some(len) = std::fs::metadata(&path).ok().map(|m| m.len()) else {
+        return Vec::new();
+    };
+    let Some(bytes) = hooks::read_tail_bytes(&path, READ_LIMIT) else {
+        return Vec::new();
+    };
+    let bytes = if shell == "zsh" { unmetafy(&bytes) } else { bytes };
+    let text = String::from_utf8_lossy(&bytes);
+
+    select_recent(parse(shell, &text, len > READ_LIMIT), MAX_COMMANDS)
+}
+
+/// Render commands for the AI prompt, with their age when known.
+pub fn render(commands: &[ShellCommand]) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    commands
+        .iter()
+        .map(|c| {
+            let command = c.command.replace('\n', "\n  ");
+            match c.timestamp.map(|ts| now.saturating_sub(ts)) {
+                Some(age) => format!("[{}] {}", format_age(age), command),
+                None => command,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86_399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86_400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bash_with_timestamps() {
+        let text = "ls\n#1700000000\ncargo build\n\n#1700000060\ngit status\n";
+        assert_eq!(
+            parse_bash(text),
+            vec![
+                ShellCommand { command: "ls".into(), timestamp: None },
+                ShellCommand { command: "cargo build".into(), timestamp: Some(1_700_000_000) },
+                ShellCommand { command: "git status".into(), timestamp: Some(1_700_000_060) },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_zsh_extended_and_multiline() {
+        let text = ": 1700000000:0;make test\n: 1700000100:3;for f in *; do\\\n  echo $f\\\ndone\nplain\n";
+        let commands = parse_zsh(text);
+        assert_eq!(commands.len(), 3);
+        assert_eq!(commands[0].timestamp, Some(1_700_000_000));
+        assert_eq!(commands[1].command, "for f in *; do\n  echo $f\ndone");
+        assert_eq!(commands[2], ShellCommand { command: "plain".into(), timestamp: None });
+    }
+
+    #[test]
+    fn test_unmetafy() {
+        // "é" is 0xC3 0xA9; zsh stores 0xA9 as Meta, 0xA9 ^ 0x20
+        assert_eq!(unmetafy(&[b'c', b'd', b' ', 0xC3, 0x83, 0xA9 ^ 0x20]), "cd é".as_bytes());
+    }
+
+    #[test]
+    fn test_parse_fish() {
+        let text = "when: 1\n- cmd: echo a\\\\b\\nc\n  when: 1700000000\n  paths:\n    - a\n- cmd: ls\n  when: 1700000005\n";
+        let commands = parse("fish", &format!("  partial\n{}", text), true);
+        assert_eq!(
+            commands,
+            vec![
+                ShellCommand { command: "echo a\\b\nc".into(), timestamp: Some(1_700_000_000) },
+                ShellCommand { command: "ls".into(), timestamp: Some(1_700_000_005) },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_psreadline_continuation() {
+        let commands = parse_psreadline("Get-ChildItem `\r\n  -Recurse\r\ncd C:\\src\r\n");
+        assert_eq!(commands.len(), 2);
+        assert_eq!(commands[0].command, "Get-ChildItem `\n  -Recurse");
+    }
+
+    #[test]
+    fn test_select_recent_dedupes_and_filters() {
+        let commands = ["ls", "export API_KEY=sk-abcdefghijklmnopqrstuvwxyz123456", "ls", "make"]
+            .iter()
+            .map(|c| ShellCommand { command: c.to_string(), timestamp: None })
+            .collect();
+        let recent = select_recent(commands, 3);
+        assert_eq!(recent.len(), 3);
+        assert!(!recent[0].command.contains("sk-abcdef"));
+        assert_eq!(recent[1].command, "ls");
+        assert_eq!(recent[2].command, "make");
+    }
+
+    #[test]
+    fn test_history_path_resolution() {
+        let home = Path::new("/home/u");
+        let env: HashMap<&str, &str> = [("HISTFILE", "/tmp/h"), ("XDG_DATA_HOME", "/data")].into();
+        let var = |k: &str| env.get(k).map(|v| v.to_string());
+        assert_eq!(history_path("bash", &var, home), Some(PathBuf::from("/tmp/h")));
+        assert_eq!(history_path("fish", &var, home), Some(PathBuf::from("/data/fish/fish_history")));
+        let none = |_: &str| None;
+        assert_eq!(history_path("zsh", &none, home), Some(PathBuf::from("/home/u/.zsh_history")));
+        assert_eq!(history_path("nu", &none, home), None);
+    }
+}
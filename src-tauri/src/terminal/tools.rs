@@ -0,0 +1,331 @@
+//! Inventory of the command-line tools installed on the shell's PATH.
+//!
+//! The model cannot know whether `rg`, `jq` or GNU `sed` exist on this machine,
+//! and suggesting a missing tool (or GNU-only flags to BSD `sed`) wastes a
+//! round-trip. Detection looks up a curated list of CLIs on the PATH of the
+//! captured shell, which is cheap enough to do synchronously. Versions and
+//! GNU/BSD flavors need one process per tool, so they are probed on a background
+//! thread and cached per PATH; until the first probe finishes the inventory
+//! lists presence only.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::process;
+
+/// Tools worth telling the model about, with the arguments that print their
+/// version. Alternatives are executable names some distributions use instead.
+const CURATED: &[(&str, &[&str], &[&str])] = &[
+    ("rg", &[], &["--version"]),
+    ("fd", &["fdfind"], &["--version"]),
+    ("jq", &[], &["--version"]),
+    ("yq", &[], &["--version"]),
+    ("fzf", &[], &["--version"]),
+    ("bat", &["batcat"], &["--version"]),
+    ("eza", &[], &["--version"]),
+    ("sed", &[], &["--version"]),
+    ("grep", &[], &["--version"]),
+    ("awk", &[], &["--version"]),
+    ("find", &[], &["--version"]),
+    ("xargs", &[], &["--version"]),
+    ("ls", &[], &["--version"]),
+    ("tar", &[], &["--version"]),
+    ("curl", &[], &["--version"]),
+    ("wget", &[], &["--version"]),
+    ("git", &[], &["--version"]),
+    ("gh", &[], &["--version"]),
+    ("docker", &[], &["--version"]),
+    ("podman", &[], &["--version"]),
+    ("kubectl", &[], &["version", "--client"]),
+    ("helm", &[], &["version", "--short"]),
+    ("terraform", &[], &["--version"]),
+    ("python3", &[], &["--version"]),
+    ("node", &[], &["--version"]),
+];
+
+/// Inventories older than this are re-probed in the background.
+const CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Upper bound for one version probe.
+const PROBE_TIMEOUT: Duration = Duration::from_millis(1500);
+
+static VERSION_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\d+)\.(\d+)").unwrap());
+
+/// One installed tool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolInfo {
+    /// Curated name, e.g. "fd"
+    pub name: String,
+    /// Executable actually found when it differs from `name`, e.g. "fdfind"
+    pub binary: Option<String>,
+    /// "major.minor" from the version output
+    pub version: Option<String>,
+    /// "GNU", "BSD" or "BusyBox" for coreutils-style tools; the implementation
+    /// for tools with look-alikes ("podman" behind `docker`, "mikefarah" or "python" yq)
+    pub flavor: Option<String>,
+}
+
+/// Curated tools present on, and missing from, one PATH.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolInventory {
+    /// PATH the inventory was taken from, for checking generated commands
+    pub search_path: String,
+    pub installed: Vec<ToolInfo>,
+    pub missing: Vec<String>,
+    /// False until the background probe has filled in versions and flavors
+    pub probed: bool,
+}
+
+impl ToolInventory {
+    /// Sentences for the system prompt.
+    pub fn describe(&self) -> String {
+        let installed: Vec<String> = self
+            .installed
+            .iter()
+            .map(|t| {
+                let mut label = match t.flavor.as_deref() {
+                    Some(f @ ("GNU" | "BSD" | "BusyBox")) => format!("{} {}", f, t.name),
+                    _ => t.name.clone(),
+                };
+                if let Some(ref binary) = t.binary {
+                    label.push_str(&format!(" (as {})", binary));
+                }
+                if let Some(ref version) = t.version {
+                    label.push_str(&format!(" {}", version));
+                }
+                match t.flavor.as_deref() {
+                    Some("GNU" | "BSD" | "BusyBox") | None => {}
+                    Some(other) => label.push_str(&format!(" ({})", other)),
+                }
+                label
+            })
+            .collect();
+        let mut text = format!(" Installed tools: {}.", installed.join(", "));
+        if !self.missing.is_empty() {
+            text.push_str(&format!(
+                " Not installed: {}. Do not use tools that are not installed; use the installed \
+                 alternatives and match their flavor's flags (GNU vs BSD).",
+                self.missing.join(", ")
+            ));
+        }
+        text
+    }
+}
+
+/// Full path of `name` if it is an executable file in one of the PATH directories.
+pub fn find_on_path(name: &str, search_path: &str) -> Option<PathBuf> {
+    std::env::split_paths(search_path)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .find_map(|dir| executable_in(&dir, name))
+}
+
+#[cfg(unix)]
+fn executable_in(dir: &Path, name: &str) -> Option<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+    let path = dir.join(name);
+    let meta = std::fs::metadata(&path).ok()?;
+    (meta.is_file() && meta.permissions().mode() & 0o111 != 0).then_some(path)
+}
+
+#[cfg(not(unix))]
+fn executable_in(dir: &Path, name: &str) -> Option<PathBuf> {
+    ["exe", "cmd", "bat", "com"]
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", name, ext)))
+        .find(|path| path.is_file())
+}
+
+/// Presence-only inventory: a PATH lookup per tool, no processes spawned.
+fn scan(search_path: &str) -> ToolInventory {
+    let mut inventory = ToolInventory {
+        search_path: search_path.to_string(),
+        ..Default::default()
+    };
+    for (name, alternatives, _) in CURATED {
+        let found = std::iter::once(*name)
+            .chain(alternatives.iter().copied())
+            .find(|bin| find_on_path(bin, search_path).is_some());
+        match found {
+            Some(bin) => inventory.installed.push(ToolInfo {
+                name: name.to_string(),
+                binary: (bin != *name).then(|| bin.to_string()),
+                version: None,
+                flavor: None,
+            }),
+            None => inventory.missing.push(name.to_string()),
+        }
+    }
+    inventory
+}
+
+/// Flavor of a tool from its `--version` output, or from the lack of one:
+/// BSD tools on macOS reject `--version`.
+fn flavor_of(name: &str, output: Option<&str>) -> Option<String> {
+    let flavor = match (name, output) {
+        (_, Some(out)) if out.contains("BusyBox") => "BusyBox",
+        ("docker", Some(out)) if out.to_lowercase().contains("podman") => "podman",
+        ("yq", Some(out)) if out.contains("mikefarah") => "mikefarah",
+        ("yq", Some(_)) => "python",
+        ("awk", Some(out)) if out.contains("GNU Awk") => "GNU",
+        ("awk", Some(out)) if out.contains("mawk") => "mawk",
+        ("tar", Some(out)) if out.contains("bsdtar") => "BSD",
+        // macOS grep reports "BSD grep, GNU compatible"
+        ("sed" | "grep" | "awk" | "find" | "xargs" | "ls", Some(out)) if out.contains("BSD") => "BSD",
+        ("sed" | "grep" | "awk" | "find" | "xargs" | "ls" | "tar", Some(out)) if out.contains("GNU") => "GNU",
+        ("sed" | "grep" | "awk" | "find" | "xargs" | "ls", None) if cfg!(target_os = "macos") => "BSD",
+        _ => return None,
+    };
+    Some(flavor.to_string())
+}
+
+/// "major.minor" from the first version-looking number in the output. Some
+/// distribution packages report a placeholder "0.0.0", which is dropped.
+fn version_of(output: &str) -> Option<String> {
+    VERSION_RE
+        .captures(output)
+        .map(|c| format!("{}.{}", &c[1], &c[2]))
+        .filter(|v| v != "0.0")
+}
+
+/// Fill in versions and flavors. Spawns one process per installed tool.
+fn probe(mut inventory: ToolInventory) -> ToolInventory {
+    for tool in &mut inventory.installed {
+        let Some((_, _, args)) = CURATED.iter().find(|(name, _, _)| *name == tool.name) else {
+            continue;
+        };
+        let binary = tool.binary.as_deref().unwrap_or(&tool.name);
+        let output = find_on_path(binary, &inventory.search_path).and_then(|path| {
+            let mut cmd = Command::new(path);
+            cmd.args(*args).env("PATH", &inventory.search_path);
+            process::run_bounded(cmd, PROBE_TIMEOUT)
+        });
+        tool.version = output.as_deref().and_then(version_of);
+        tool.flavor = flavor_of(&tool.name, output.as_deref());
+    }
+    inventory.probed = true;
+    inventory
+}
+
+struct CachedInventory {
+    inventory: ToolInventory,
+    at: Instant,
+}
+
+static CACHE: Lazy<Mutex<Option<CachedInventory>>> = Lazy::new(|| Mutex::new(None));
+static REFRESHING: AtomicBool = AtomicBool::new(false);
+
+/// Start a background probe of `search_path` unless one is already running.
+fn refresh_in_background(search_path: String) {
+    if REFRESHING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    std::thread::spawn(move || {
+        let inventory = probe(scan(&search_path));
+        if let Ok(mut cache) = CACHE.lock() {
+            *cache = Some(CachedInventory { inventory, at: Instant::now() });
+        }
+        REFRESHING.store(false, Ordering::SeqCst);
+    });
+}
+
+/// Tool inventory for the shell with the given PID. Returns the cached probe
+/// for the shell's PATH when there is one, refreshing it in the background once
+/// stale; otherwise a presence-only scan while the probe runs.
+pub fn inventory(shell_pid: Option<i32>) -> Option<ToolInventory> {
+    let search_path = shell_pid
+        .and_then(|pid| process::get_process_env(pid).remove("PATH"))
+        .or_else(|| std::env::var("PATH").ok())
+        .filter(|p| !p.is_empty())?;
+
+    let cached = CACHE.lock().ok().and_then(|cache| {
+        cache
+            .as_ref()
+            .filter(|c| c.inventory.search_path == search_path)
+            .map(|c| (c.inventory.clone(), c.at.elapsed() > CACHE_TTL))
+    });
+    match cached {
+        Some((inventory, stale)) => {
+            if stale {
+                refresh_in_background(search_path);
+            }
+            Some(inventory)
+        }
+        None => {
+            let inventory = scan(&search_path);
+            refresh_in_background(search_path);
+            Some(inventory)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flavor_detection() {
+        assert_eq!(flavor_of("sed", Some("sed (GNU sed) 4.9")).as_deref(), Some("GNU"));
+        assert_eq!(flavor_of("grep", Some("grep (BSD grep, GNU compatible) 2.6.0-FreeBSD")).as_deref(), Some("BSD"));
+        assert_eq!(flavor_of("ls", Some("BusyBox v1.36.1")).as_deref(), Some("BusyBox"));
+        assert_eq!(flavor_of("docker", Some("podman version 4.9.3")).as_deref(), Some("podman"));
+        assert_eq!(
+            flavor_of("yq", Some("yq (https://github.com/mikefarah/yq/) version v4.40.5")).as_deref(),
+            Some("mikefarah")
+        );
+        assert_eq!(flavor_of("rg", Some("ripgrep 14.1.0")), None);
+    }
+
+    #[test]
+    fn test_version_of() {
+        assert_eq!(version_of("ripgrep 14.1.0 (rev e50df40a19)").as_deref(), Some("14.1"));
+        assert_eq!(version_of("Client Version: v1.29.2").as_deref(), Some("1.29"));
+        assert_eq!(version_of("jq-1.7.1").as_deref(), Some("1.7"));
+        assert_eq!(version_of("yq 0.0.0"), None);
+        assert_eq!(version_of("no digits"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_finds_executables_and_alternatives() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("cmdk-tools-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, mode) in [("rg", 0o755), ("fdfind", 0o755), ("jq", 0o644)] {
+            let path = dir.join(name);
+            std::fs::write(&path, "#!/bin/sh\n").unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+        }
+        let inventory = scan(dir.to_str().unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let names: Vec<&str> = inventory.installed.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["rg", "fd"]);
+        assert_eq!(inventory.installed[1].binary.as_deref(), Some("fdfind"));
+        // Not executable
+        assert!(inventory.missing.contains(&"jq".to_string()));
+    }
+
+    #[test]
+    fn test_describe() {
+        let inventory = ToolInventory {
+            search_path: String::new(),
+            installed: vec![
+                ToolInfo { name: "sed".into(), binary: None, version: Some("4.9".into()), flavor: Some("GNU".into()) },
+                ToolInfo { name: "fd".into(), binary: Some("fdfind".into()), version: None, flavor: None },
+                ToolInfo { name: "docker".into(), binary: None, version: Some("4.9".into()), flavor: Some("podman".into()) },
+            ],
+            missing: vec!["jq".into()],
+            probed: true,
+        };
+        let text = inventory.describe();
+        assert!(text.starts_with(" Installed tools: GNU sed 4.9, fd (as fdfind), docker 4.9 (podman)."));
+        assert!(text.contains("Not installed: jq."));
+    }
+}
@@ -0,0 +1,453 @@
+//! Display server detection and the active window's PID on Wayland.
+//!
+//! Wayland has no `_NET_ACTIVE_WINDOW`: each compositor exposes the focused
+//! window its own way. sway (and i3) answer GET_TREE on the i3 IPC socket,
+//! Hyprland answers `j/activewindow` on its request socket, KWin runs a
+//! one-shot script that reports the PID back over D-Bus, and GNOME Shell needs
+//! the Window Calls extension (or Shell.Eval in unsafe mode).
+
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde_json::Value;
+
+/// Upper bound for any single compositor round trip. The hotkey handler waits
+/// on this before showing the overlay.
+const IPC_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisplayServer {
+    X11,
+    Wayland(Compositor),
+    Unknown,
+}
+
+/// The Wayland compositor, with the IPC socket where it has one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Compositor {
+    /// sway or another compositor speaking i3 IPC (SWAYSOCK / I3SOCK).
+    Sway(PathBuf),
+    /// Hyprland request socket (`.socket.sock`).
+    Hyprland(PathBuf),
+    KWin,
+    GnomeShell,
+    Other,
+}
+
+pub fn detect_display_server() -> DisplayServer {
+    let var = |name: &str| std::env::var(name).ok();
+    // GDK_BACKEND=x11 overrides everything (XWayland path)
+    if var("GDK_BACKEND").as_deref() == Some("x11") {
+        return DisplayServer::X11;
+    }
+    // Check for Wayland indicators
+    if var("WAYLAND_DISPLAY").is_some() && var("XDG_SESSION_TYPE").as_deref() == Some("wayland") {
+        return DisplayServer::Wayland(compositor_from_env(var));
+    }
+    // Default to X11 if DISPLAY is set
+    if var("DISPLAY").is_some() {
+        return DisplayServer::X11;
+    }
+    DisplayServer::Unknown
+}
+
+/// Pick the compositor from the session environment. The IPC sockets are the
+/// most specific signal; XDG_CURRENT_DESKTOP covers the D-Bus compositors.
+fn compositor_from_env(var: impl Fn(&str) -> Option<String>) -> Compositor {
+    if let Some(sock) = var("SWAYSOCK").or_else(|| var("I3SOCK")) {
+        return Compositor::Sway(PathBuf::from(sock));
+    }
+    if let Some(signature) = var("HYPRLAND_INSTANCE_SIGNATURE") {
+        // Hyprland 0.40+ keeps its sockets under XDG_RUNTIME_DIR, older releases in /tmp
+        let runtime = var("XDG_RUNTIME_DIR")
+            .map(|dir| Path::new(&dir).join("hypr").join(&signature).join(".socket.sock"))
+            .filter(|path| path.exists());
+        let legacy = Path::new("/tmp/hypr").join(&signature).join(".socket.sock");
+        return Compositor::Hyprland(runtime.unwrap_or(legacy));
+    }
+    let desktop = var("XDG_CURRENT_DESKTOP").unwrap_or_default().to_lowercase();
+    if desktop.split(':').any(|d| d == "kde") {
+        Compositor::KWin
+    } else if desktop.split(':').any(|d| d == "gnome") {
+        Compositor::GnomeShell
+    } else {
+        Compositor::Other
+    }
+}
+
+/// PID of the focused window as reported by the compositor.
+pub fn active_window_pid(compositor: &Compositor) -> Result<i32, String> {
+    match compositor {
+        Compositor::Sway(socket) => sway_focused_pid(socket),
+        Compositor::Hyprland(socket) => hyprland_active_pid(socket),
+        Compositor::KWin => kwin_active_pid(),
+        Compositor::GnomeShell => gnome_active_pid(),
+        Compositor::Other => Err("no active-window IPC for this compositor".to_string()),
+    }
+}
+
+/// Move focus to the window of `pid`. Only the IPC compositors take focus
+/// requests from clients; KWin and GNOME Shell refocus the previous window
+/// themselves once the overlay hides.
+pub fn focus_window(compositor: &Compositor, pid: i32) -> Result<(), String> {
+    match compositor {
+        Compositor::Sway(socket) => {
+            let body = i3_request(socket, I3_RUN_COMMAND, format!("[pid={}] focus", pid).as_bytes())?;
+            let outcomes: Vec<Value> = serde_json::from_slice(&body).map_err(|e| e.to_string())?;
+            match outcomes.iter().find(|o| o["success"].as_bool() != Some(true)) {
+                Some(failed) => Err(failed["error"].as_str().unwrap_or("focus failed").to_string()),
+                None => Ok(()),
+            }
+        }
+        Compositor::Hyprland(socket) => {
+            let reply = hyprland_request(socket, &format!("dispatch focuswindow pid:{}", pid))?;
+            if reply.trim() == "ok" {
+                Ok(())
+            } else {
+                Err(reply.trim().to_string())
+            }
+        }
+        _ => Err("compositor does not take focus requests".to_string()),
+    }
+}
+
+fn connect(socket: &Path) -> Result<UnixStream, String> {
+    let stream = UnixStream::connect(socket)
+        .map_err(|e| format!("connect {} failed: {}", socket.display(), e))?;
+    stream.set_read_timeout(Some(IPC_TIMEOUT)).map_err(|e| e.to_string())?;
+    stream.set_write_timeout(Some(IPC_TIMEOUT)).map_err(|e| e.to_string())?;
+    Ok(stream)
+}
+
+fn positive_pid(value: &Value) -> Option<i32> {
+    value.as_i64().filter(|pid| *pid > 0).map(|pid| pid as i32)
+}
+
+// ---------------------------------------------------------------------------
+// sway / i3 IPC
+// ---------------------------------------------------------------------------
+
+const I3_MAGIC: &[u8; 6] = b"i3-ipc";
+const I3_RUN_COMMAND: u32 = 0;
+const I3_GET_TREE: u32 = 4;
+
+/// One i3 IPC request: `"i3-ipc" <len u32> <type u32> <payload>`, native endian.
+fn i3_request(socket: &Path, kind: u32, payload: &[u8]) -> Result<Vec<u8>, String> {
+    let mut stream = connect(socket)?;
+    let mut message = Vec::with_capacity(14 + payload.len());
+    message.extend_from_slice(I3_MAGIC);
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&kind.to_ne_bytes());
+    message.extend_from_slice(payload);
+    stream.write_all(&message).map_err(|e| e.to_string())?;
+
+    let mut header = [0u8; 14];
+    stream.read_exact(&mut header).map_err(|e| e.to_string())?;
+    if &header[..6] != I3_MAGIC {
+        return Err("not an i3 IPC reply".to_string());
+    }
+    let len = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]) as usize;
+    let mut body = vec![0u8; len];
+    stream.read_exact(&mut body).map_err(|e| e.to_string())?;
+    Ok(body)
+}
+
+/// Focused window PID from sway's layout tree. i3 itself has no `pid` on X11
+/// windows, so this only answers for Wayland-native windows there.
+pub fn sway_focused_pid(socket: &Path) -> Result<i32, String> {
+    let body = i3_request(socket, I3_GET_TREE, b"")?;
+    let tree: Value = serde_json::from_slice(&body).map_err(|e| e.to_string())?;
+    focused_pid(&tree).ok_or_else(|| "no focused window with a pid".to_string())
+}
+
+fn focused_pid(node: &Value) -> Option<i32> {
+    if node["focused"].as_bool() == Some(true) {
+        return positive_pid(&node["pid"]);
+    }
+    ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node[*key].as_array())
+        .flatten()
+        .find_map(focused_pid)
+}
+
+// ---------------------------------------------------------------------------
+// Hyprland
+// ---------------------------------------------------------------------------
+
+/// One request on Hyprland's request socket, which serves a single request per
+/// connection and closes after replying.
+fn hyprland_request(socket: &Path, request: &str) -> Result<String, String> {
+    let mut stream = connect(socket)?;
+    stream.write_all(request.as_bytes()).map_err(|e| e.to_string())?;
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).map_err(|e| e.to_string())?;
+    Ok(reply)
+}
+
+/// Focused window PID from Hyprland's `activewindow` request.
+pub fn hyprland_active_pid(socket: &Path) -> Result<i32, String> {
+    let reply = hyprland_request(socket, "j/activewindow")?;
+    let window: Value = serde_json::from_str(&reply)
+        .map_err(|_| format!("unexpected reply: {}", reply.trim()))?;
+    positive_pid(&window["pid"]).ok_or_else(|| "no active window".to_string())
+}
+
+// ---------------------------------------------------------------------------
+// KWin and GNOME Shell (D-Bus)
+// ---------------------------------------------------------------------------
+
+const KWIN_PLUGIN: &str = "cmdk-active-window";
+const PID_REPORT_PATH: &str = "/ActiveWindowPid";
+const PID_REPORT_IFACE: &str = "com.lakshmanturlapati.cmdk.ActiveWindowPid";
+
+/// Receives the PID the KWin script sends back with `callDBus`.
+struct PidReport {
+    tx: std::sync::mpsc::Sender<i32>,
+}
+
+#[zbus::interface(name = "com.lakshmanturlapati.cmdk.ActiveWindowPid")]
+impl PidReport {
+    fn report(&self, pid: i32) {
+        let _ = self.tx.send(pid);
+    }
+}
+
+/// KWin script that reports the active window's PID to `service`. KWin 6 calls
+/// it `activeWindow`, KWin 5 `activeClient`.
+fn kwin_script(service: &str) -> String {
+    format!(
+        "const w = workspace.activeWindow || workspace.activeClient;\n\
+         callDBus(\"{}\", \"{}\", \"{}\", \"Report\", w ? w.pid : 0);\n",
+        service, PID_REPORT_PATH, PID_REPORT_IFACE
+    )
+}
+
+/// Load a one-shot KWin script over org.kde.kwin.Scripting and wait for it to
+/// call back with the active window's PID.
+fn kwin_active_pid() -> Result<i32, String> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let conn = zbus::blocking::connection::Builder::session()
+        .and_then(|b| b.serve_at(PID_REPORT_PATH, PidReport { tx }))
+        .and_then(|b| b.build())
+        .map_err(|e| format!("D-Bus session failed: {}", e))?;
+    let service = conn
+        .unique_name()
+        .ok_or("D-Bus connection has no unique name")?
+        .to_string();
+
+    // KWin runs whatever the file holds: write it fresh and owner-only in the
+    // private scratch directory, never through a path another user could plant
+    let script_path = super::hooks::scratch_dir()
+        .ok_or("no private directory for the KWin script")?
+        .join(format!("{}-{}.js", KWIN_PLUGIN, std::process::id()));
+    let _ = std::fs::remove_file(&script_path);
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&script_path)
+            .and_then(|mut file| file.write_all(kwin_script(&service).as_bytes()))
+            .map_err(|e| e.to_string())?;
+    }
+
+    // A script left behind by an earlier run would make loadScript return -1
+    let _ = kwin_scripting(&conn, "unloadScript", &(KWIN_PLUGIN,));
+    let result = kwin_scripting(&conn, "loadScript", &(&*script_path.to_string_lossy(), KWIN_PLUGIN))
+        .and_then(|reply| reply.body().deserialize::<i32>().map_err(|e| e.to_string()))
+        .and_then(|id| {
+            if id < 0 {
+                return Err("KWin refused to load the script".to_string());
+            }
+            // KWin 6 registers scripts at /Scripting/Script<id>, KWin 5 at /<id>
+            [format!("/Scripting/Script{}", id), format!("/{}", id)]
+                .iter()
+                .find_map(|path| {
+                    conn.call_method(Some("org.kde.KWin"), path.as_str(), Some("org.kde.kwin.Script"), "run", &())
+                        .ok()
+                })
+                .ok_or_else(|| "could not run the KWin script".to_string())?;
+            rx.recv_timeout(IPC_TIMEOUT)
+                .map_err(|_| "KWin script did not report back".to_string())
+        });
+    let _ = kwin_scripting(&conn, "unloadScript", &(KWIN_PLUGIN,));
+    let _ = std::fs::remove_file(&script_path);
+
+    result.and_then(|pid| if pid > 0 { Ok(pid) } else { Err("no active window".to_string()) })
+}
+
+fn kwin_scripting<B>(conn: &zbus::blocking::Connection, method: &str, body: &B) -> Result<zbus::Message, String>
+where
+    B: serde::Serialize + zbus::zvariant::DynamicType,
+{
+    conn.call_method(Some("org.kde.KWin"), "/Scripting", Some("org.kde.kwin.Scripting"), method, body)
+        .map_err(|e| e.to_string())
+}
+
+/// Focused window PID on GNOME Shell. Mutter keeps window PIDs to itself, so
+/// this needs the Window Calls extension, or Shell.Eval on shells running in
+/// unsafe mode (development setups, GNOME 40 and older).
+fn gnome_active_pid() -> Result<i32, String> {
+    let conn = zbus::blocking::Connection::session()
+        .map_err(|e| format!("D-Bus session failed: {}", e))?;
+
+    let listed = conn
+        .call_method(
+            Some("org.gnome.Shell"),
+            "/org/gnome/Shell/Extensions/Windows",
+            Some("org.gnome.Shell.Extensions.Windows"),
+            "List",
+            &(),
+        )
+        .map_err(|e| e.to_string())
+        .and_then(|reply| reply.body().deserialize::<String>().map_err(|e| e.to_string()));
+    if let Ok(json) = listed {
+        return window_calls_focused_pid(&json);
+    }
+
+    let reply = conn
+        .call_method(
+            Some("org.gnome.Shell"),
+            "/org/gnome/Shell",
+            Some("org.gnome.Shell"),
+            "Eval",
+            &("global.display.focus_window ? global.display.focus_window.get_pid() : 0",),
+        )
+        .map_err(|e| format!("Window Calls extension not installed, Shell.Eval failed: {}", e))?;
+    let (ok, output): (bool, String) = reply.body().deserialize().map_err(|e| e.to_string())?;
+    match output.trim().parse::<i32>() {
+        Ok(pid) if ok && pid > 0 => Ok(pid),
+        _ => Err("Shell.Eval is disabled (install the Window Calls extension)".to_string()),
+    }
+}
+
+/// The focused entry of the Window Calls `List` JSON array.
+fn window_calls_focused_pid(json: &str) -> Result<i32, String> {
+    let windows: Vec<Value> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    windows
+        .iter()
+        .find(|w| w["focus"].as_bool() == Some(true))
+        .and_then(|w| positive_pid(&w["pid"]))
+        .ok_or_else(|| "no focused window".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+
+    /// Serve one connection on a fresh socket with `respond`, like a compositor would.
+    fn fake_socket(name: &str, respond: fn(UnixStream)) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("cmdk-wayland-{}-{}.sock", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        std::thread::spawn(move || {
+            if let Ok((stream, _)) = listener.accept() {
+                respond(stream);
+            }
+        });
+        path
+    }
+
+    #[test]
+    fn test_compositor_from_env() {
+        let env = |pairs: &'static [(&'static str, &'static str)]| {
+            move |name: &str| pairs.iter().find(|(k, _)| *k == name).map(|(_, v)| v.to_string())
+        };
+        assert_eq!(
+            compositor_from_env(env(&[("SWAYSOCK", "/run/user/1000/sway-ipc.sock"), ("XDG_CURRENT_DESKTOP", "sway")])),
+            Compositor::Sway(PathBuf::from("/run/user/1000/sway-ipc.sock"))
+        );
+        assert_eq!(
+            compositor_from_env(env(&[("HYPRLAND_INSTANCE_SIGNATURE", "abc_123")])),
+            Compositor::Hyprland(PathBuf::from("/tmp/hypr/abc_123/.socket.sock"))
+        );
+        assert_eq!(compositor_from_env(env(&[("XDG_CURRENT_DESKTOP", "KDE")])), Compositor::KWin);
+        assert_eq!(compositor_from_env(env(&[("XDG_CURRENT_DESKTOP", "ubuntu:GNOME")])), Compositor::GnomeShell);
+        assert_eq!(compositor_from_env(env(&[("XDG_CURRENT_DESKTOP", "niri")])), Compositor::Other);
+    }
+
+    #[test]
+    fn test_sway_get_tree() {
+        let socket = fake_socket("sway", |mut stream| {
+            let mut header = [0u8; 14];
+            stream.read_exact(&mut header).unwrap();
+            assert_eq!(&header[..6], I3_MAGIC);
+            assert_eq!(u32::from_ne_bytes(header[10..14].try_into().unwrap()), I3_GET_TREE);
+            let tree = br#"{"id":1,"focused":false,"nodes":[{"id":2,"nodes":[
+                {"id":3,"focused":false,"pid":100,"nodes":[]},
+                {"id":4,"focused":false,"nodes":[],"floating_nodes":[{"id":5,"focused":true,"pid":4242}]}
+            ]}]}"#;
+            let mut reply = I3_MAGIC.to_vec();
+            reply.extend_from_slice(&(tree.len() as u32).to_ne_bytes());
+            reply.extend_from_slice(&I3_GET_TREE.to_ne_bytes());
+            reply.extend_from_slice(tree);
+            stream.write_all(&reply).unwrap();
+        });
+        assert_eq!(sway_focused_pid(&socket), Ok(4242));
+        let _ = std::fs::remove_file(&socket);
+    }
+
+    #[test]
+    fn test_hyprland_activewindow() {
+        let socket = fake_socket("hypr", |mut stream| {
+            let mut request = [0u8; 14];
+            stream.read_exact(&mut request).unwrap();
+            assert_eq!(&request, b"j/activewindow");
+            stream.write_all(br#"{"address":"0x5a1e","class":"kitty","pid":3131}"#).unwrap();
+        });
+        assert_eq!(hyprland_active_pid(&socket), Ok(3131));
+        let _ = std::fs::remove_file(&socket);
+
+        // Empty workspace: Hyprland answers with an empty object
+        let socket = fake_socket("hypr-empty", |mut stream| {
+            let mut request = [0u8; 14];
+            stream.read_exact(&mut request).unwrap();
+            stream.write_all(b"{}").unwrap();
+        });
+        assert!(hyprland_active_pid(&socket).is_err());
+        let _ = std::fs::remove_file(&socket);
+    }
+
+    #[test]
+    fn test_focus_window() {
+        let socket = fake_socket("sway-focus", |mut stream| {
+            let mut header = [0u8; 14];
+            stream.read_exact(&mut header).unwrap();
+            assert_eq!(u32::from_ne_bytes(header[10..14].try_into().unwrap()), I3_RUN_COMMAND);
+            let mut command = vec![0u8; u32::from_ne_bytes(header[6..10].try_into().unwrap()) as usize];
+            stream.read_exact(&mut command).unwrap();
+            assert_eq!(command, b"[pid=4242] focus");
+            let outcome = br#"[{"success":true}]"#;
+            let mut reply = I3_MAGIC.to_vec();
+            reply.extend_from_slice(&(outcome.len() as u32).to_ne_bytes());
+            reply.extend_from_slice(&I3_RUN_COMMAND.to_ne_bytes());
+            reply.extend_from_slice(outcome);
+            stream.write_all(&reply).unwrap();
+        });
+        assert_eq!(focus_window(&Compositor::Sway(socket.clone()), 4242), Ok(()));
+        let _ = std::fs::remove_file(&socket);
+
+        let socket = fake_socket("hypr-focus", |mut stream| {
+            let mut request = [0u8; 29];
+            stream.read_exact(&mut request).unwrap();
+            assert_eq!(&request, b"dispatch focuswindow pid:3131");
+            stream.write_all(b"ok").unwrap();
+        });
+        assert_eq!(focus_window(&Compositor::Hyprland(socket.clone()), 3131), Ok(()));
+        let _ = std::fs::remove_file(&socket);
+        assert!(focus_window(&Compositor::KWin, 1).is_err());
+    }
+
+    #[test]
+    fn test_window_calls_list() {
+        let json = r#"[{"id":1,"wm_class":"firefox","pid":900,"focus":false},
+                       {"id":2,"wm_class":"org.gnome.Ptyxis","pid":1500,"focus":true}]"#;
+        assert_eq!(window_calls_focused_pid(json), Ok(1500));
+        assert!(window_calls_focused_pid("[]").is_err());
+    }
+}
@@ -2,7 +2,7 @@ import { useEffect, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import { Store } from "@tauri-apps/plugin-store";
-import { useOverlayStore, ModelWithMeta } from "@/store";
+import { useOverlayStore, ModelWithMeta, PolicyView } from "@/store";
 import { useKeyboard } from "@/hooks/useKeyboard";
 import { useWindowAutoSize } from "@/hooks/useWindowAutoSize";
 import { useDrag } from "@/hooks/useDrag";
@@ -49,6 +49,13 @@ function App() {
     loadPersistedHotkey();
   }, []); // eslint-disable-line react-hooks/exhaustive-deps
 
+  // On startup: load the administrator policy so locked settings render read-only
+  useEffect(() => {
+    invoke<PolicyView>("get_policy")
+      .then((policy) => useOverlayStore.getState().setPolicy(policy))
+      .catch((err) => console.error("Failed to load policy:", err));
+  }, []);
+
   // On startup: check onboarding completion status and resume or skip accordingly
   useEffect(() => {
     const checkOnboarding = async () => {
@@ -18,8 +18,17 @@ export function DestructiveBadge() {
     return () => clearTimeout(id);
   }, []);
 
-  // Eagerly load explanation on mount
+  // Eagerly load explanation on mount. Analyzer findings (e.g. SQL) already name
+  // the affected objects, so use them directly instead of asking the model.
   useEffect(() => {
+    const specific = useOverlayStore
+      .getState()
+      .safetyReport?.findings.filter((f) => f.category !== "pattern" && f.category !== "tools");
+    if (specific && specific.length > 0) {
+      setDestructiveExplanation(specific.map((f) => f.message).join(". "));
+      return;
+    }
+
     const ch = new Channel<string>();
     ch.onmessage = (explanation: string) => {
       setDestructiveExplanation(explanation);
@@ -1,6 +1,6 @@
 import { useState } from "react";
 import { Store } from "@tauri-apps/plugin-store";
-import { useOverlayStore, PROVIDERS } from "@/store";
+import { useOverlayStore, PROVIDERS, isProviderAllowed } from "@/store";
 import { ProviderIcon } from "@/components/icons/ProviderIcon";
 
 interface StepProviderSelectProps {
@@ -9,6 +9,7 @@ interface StepProviderSelectProps {
 
 export function StepProviderSelect({ onNext }: StepProviderSelectProps) {
   const setSelectedProvider = useOverlayStore((s) => s.setSelectedProvider);
+  const policy = useOverlayStore((s) => s.policy);
   const [chosen, setChosen] = useState<string | null>(null);
 
   const handleSelect = async (providerId: string) => {
@@ -32,7 +33,7 @@ export function StepProviderSelect({ onNext }: StepProviderSelectProps) {
         </p>
 
         <div className="flex flex-col gap-1.5 mt-1">
-          {PROVIDERS.map((provider) => {
+          {PROVIDERS.filter((provider) => isProviderAllowed(policy, provider.id)).map((provider) => {
             const isSelected = chosen === provider.id;
             return (
               <button
@@ -2,7 +2,7 @@ import { useEffect, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import * as Tooltip from "@radix-ui/react-tooltip";
 import { ShieldAlert } from "lucide-react";
-import { useOverlayStore, resolveBadge } from "@/store";
+import { useOverlayStore, resolveBadge, remoteTarget, REPL_LANGUAGES } from "@/store";
 import { isWindows, isLinux } from "@/utils/platform";
 import { CommandInput } from "./CommandInput";
 import { ResultsArea } from "./ResultsArea";
@@ -28,7 +28,13 @@ export function Overlay({ onSubmit }: OverlayProps) {
   const isDestructive = useOverlayStore((s) => s.isDestructive);
   const destructiveDismissed = useOverlayStore((s) => s.destructiveDismissed);
   const pasteHint = useOverlayStore((s) => s.pasteHint);
+  const provenanceWarning = useOverlayStore((s) => s.provenanceWarning);
+  const destructiveExplanation = useOverlayStore((s) => s.destructiveExplanation);
+  const forceExplanations = useOverlayStore((s) => s.policy?.force_explanations ?? false);
   const badgeText = resolveBadge(appContext);
+  const remote = appContext?.terminal?.remote ?? null;
+  const container = remote ? null : appContext?.terminal?.container ?? null;
+  const repl = appContext?.terminal?.repl ?? null;
   const [animPhase, setAnimPhase] = useState<AnimationPhase>("hidden");
 
   useEffect(() => {
@@ -139,6 +145,32 @@ export function Overlay({ onSubmit }: OverlayProps) {
                   {pasteHint}
                 </div>
               )}
+              {repl && displayMode === "result" && (
+                <div className="text-[11px] text-sky-400/70 font-mono px-1">
+                  {REPL_LANGUAGES[repl.kind]} input for {repl.program}, not a shell command
+                </div>
+              )}
+              {remote && displayMode === "result" && (
+                <div className="text-[11px] text-sky-400/70 font-mono px-1">
+                  Runs on {remoteTarget(remote)} (remote host), not this machine
+                </div>
+              )}
+              {container && displayMode === "result" && (
+                <div className="text-[11px] text-sky-400/70 font-mono px-1">
+                  Runs inside {container.program === "kubectl" ? "pod" : "container"} {container.name}
+                  {container.distro ? ` (${container.distro})` : ""}, not on this machine
+                </div>
+              )}
+              {provenanceWarning && (
+                <div className="text-[11px] text-amber-400/70 font-mono px-1">
+                  {provenanceWarning}
+                </div>
+              )}
+              {forceExplanations && isDestructive && displayMode === "result" && destructiveExplanation && (
+                <div className="text-[11px] text-red-400/80 font-mono px-1">
+                  {destructiveExplanation}
+                </div>
+              )}
               {/* Badge stays visible in ALL display modes below input/output */}
               {mode === "command" && (
                 <div className="flex items-center gap-2 min-h-[20px]">
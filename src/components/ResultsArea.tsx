@@ -1,4 +1,5 @@
 import { useState, type ReactNode } from "react";
+import { invoke } from "@tauri-apps/api/core";
 import { useOverlayStore } from "@/store";
 
 /** Minimal shell highlighting: flags (yellow), strings (green), everything else white */
@@ -30,6 +31,9 @@ export function ResultsArea() {
   const isStreaming = useOverlayStore((state) => state.isStreaming);
   const displayMode = useOverlayStore((state) => state.displayMode);
   const streamError = useOverlayStore((state) => state.streamError);
+  const fixDiagnosis = useOverlayStore((state) => state.fixDiagnosis);
+  const safetyReport = useOverlayStore((state) => state.safetyReport);
+  const missingTools = safetyReport?.findings.filter((f) => f.category === "tools") ?? [];
   const openSettings = useOverlayStore((state) => state.openSettings);
 
   const [copiedVisible, setCopiedVisible] = useState(false);
@@ -37,12 +41,17 @@ export function ResultsArea() {
   const handleClick = () => {
     // Click-to-copy only available in result mode (not during streaming)
     if (displayMode !== "result" || !streamingText) return;
-    navigator.clipboard.writeText(streamingText).then(() => {
-      setCopiedVisible(true);
-      setTimeout(() => setCopiedVisible(false), 1500);
-    }).catch((err) => {
-      console.error("[ResultsArea] clipboard copy failed:", err);
-    });
+    // Secret placeholders are substituted with the real values only in Rust
+    invoke<string>("restore_secrets", { text: streamingText })
+      .catch(() => streamingText)
+      .then((text) => navigator.clipboard.writeText(text))
+      .then(() => {
+        setCopiedVisible(true);
+        setTimeout(() => setCopiedVisible(false), 1500);
+      })
+      .catch((err) => {
+        console.error("[ResultsArea] clipboard copy failed:", err);
+      });
   };
 
   return (
@@ -65,6 +74,9 @@ export function ResultsArea() {
         </div>
       ) : (
         <div className="relative">
+          {fixDiagnosis && displayMode === "result" && (
+            <p className="text-white/60 text-xs mb-2">{fixDiagnosis}</p>
+          )}
           <div
             className={[
               "max-h-[60vh]",
@@ -85,6 +97,11 @@ export function ResultsArea() {
               )}
             </pre>
           </div>
+          {missingTools.length > 0 && displayMode === "result" && (
+            <p className="text-amber-400/70 text-xs mt-2">
+              {missingTools.map((f) => f.message).join(". ")}
+            </p>
+          )}
           {copiedVisible && (
             <span className="absolute bottom-0 right-0 text-[10px] text-white/50 pointer-events-none">
               Copied to clipboard
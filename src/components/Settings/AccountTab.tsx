@@ -2,7 +2,7 @@ import { useEffect, useRef, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { Store } from "@tauri-apps/plugin-store";
 import { Eye, EyeOff, Check, X, Loader2, AlertCircle, ChevronDown } from "lucide-react";
-import { useOverlayStore, PROVIDERS, ModelWithMeta } from "@/store";
+import { useOverlayStore, PROVIDERS, ModelWithMeta, isProviderAllowed } from "@/store";
 import { ProviderIcon } from "@/components/icons/ProviderIcon";
 
 export function AccountTab() {
@@ -13,6 +13,7 @@ export function AccountTab() {
   const setModels = useOverlayStore((s) => s.setModels);
   const selectedProvider = useOverlayStore((s) => s.selectedProvider);
   const setSelectedProvider = useOverlayStore((s) => s.setSelectedProvider);
+  const policy = useOverlayStore((s) => s.policy);
 
   const [inputValue, setInputValue] = useState("");
   const [baseUrlInput, setBaseUrlInput] = useState("");
@@ -291,7 +292,7 @@ export function AccountTab() {
           </button>
           {dropdownOpen && (
             <div className="absolute top-full left-0 right-0 mt-1 z-50 bg-[#2a2a2c]/95 backdrop-blur-xl border border-white/10 rounded-lg overflow-y-auto max-h-60">
-              {PROVIDERS.map((p) => (
+              {PROVIDERS.filter((p) => isProviderAllowed(policy, p.id)).map((p) => (
                 <button
                   key={p.id}
                   type="button"
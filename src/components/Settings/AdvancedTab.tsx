@@ -1,8 +1,27 @@
+import { useEffect, useState } from "react";
 import { Store } from "@tauri-apps/plugin-store";
 import { invoke } from "@tauri-apps/api/core";
 import { useOverlayStore } from "@/store";
 import { RotateCcw, Trash2 } from "lucide-react";
 
+type PrivacyLevel = "off" | "secrets" | "standard" | "strict";
+
+const PRIVACY_LEVELS: { id: PrivacyLevel; label: string; description: string }[] = [
+  { id: "off", label: "Off", description: "Secrets are still redacted; custom rules are skipped" },
+  { id: "secrets", label: "Secrets", description: "API keys, tokens and passwords are redacted" },
+  { id: "standard", label: "Standard", description: "Also hides email addresses and your home directory" },
+  { id: "strict", label: "Strict", description: "Also hides usernames, hostnames and internal IPs" },
+];
+
+type HookShell = "bash" | "zsh" | "fish";
+
+interface HookInstall {
+  shell: HookShell;
+  path: string;
+  rc_file: string;
+  source_line: string;
+}
+
 export function AdvancedTab() {
   const turnLimit = useOverlayStore((state) => state.turnLimit);
   const setTurnLimit = useOverlayStore((state) => state.setTurnLimit);
@@ -42,7 +61,11 @@ export function AdvancedTab() {
     (state) => state.setAutoPasteEnabled
   );
 
+  const policy = useOverlayStore((state) => state.policy);
+  const destructiveLocked = policy?.locked.destructiveDetectionEnabled !== undefined;
+
   const handleToggleDestructive = async () => {
+    if (destructiveLocked) return;
     const newValue = !destructiveDetectionEnabled;
     setDestructiveDetectionEnabled(newValue);
     try {
@@ -54,6 +77,87 @@ export function AdvancedTab() {
     }
   };
 
+  // Production name patterns are read by the backend safety analyzer straight
+  // from the settings store, so they only live in local state here.
+  const [productionPatterns, setProductionPatterns] = useState("*prod*");
+
+  useEffect(() => {
+    Store.load("settings.json")
+      .then((store) => store.get<string[]>("productionPatterns"))
+      .then((patterns) => {
+        if (patterns) setProductionPatterns(patterns.join(", "));
+      })
+      .catch((err) => console.error("[advanced] Failed to load productionPatterns:", err));
+  }, []);
+
+  const handleProductionPatternsBlur = async () => {
+    const patterns = productionPatterns
+      .split(",")
+      .map((p) => p.trim())
+      .filter((p) => p.length > 0);
+    try {
+      const store = await Store.load("settings.json");
+      await store.set("productionPatterns", patterns);
+      await store.save();
+    } catch (err) {
+      console.error("[advanced] Failed to persist productionPatterns:", err);
+    }
+  };
+
+  // Privacy settings are also read by the backend from the settings store when
+  // the overlay opens, so they only live in local state here.
+  const [privacyLevel, setPrivacyLevel] = useState<PrivacyLevel>("secrets");
+  const [privacyRules, setPrivacyRules] = useState("");
+
+  useEffect(() => {
+    Store.load("settings.json")
+      .then(async (store) => {
+        const level = await store.get<PrivacyLevel>("privacyLevel");
+        const rules = await store.get<string[]>("privacyRules");
+        if (level) setPrivacyLevel(level);
+        if (rules) setPrivacyRules(rules.join("\n"));
+      })
+      .catch((err) => console.error("[advanced] Failed to load privacy settings:", err));
+  }, []);
+
+  const handlePrivacyLevelChange = async (level: PrivacyLevel) => {
+    setPrivacyLevel(level);
+    try {
+      const store = await Store.load("settings.json");
+      await store.set("privacyLevel", level);
+      await store.save();
+    } catch (err) {
+      console.error("[advanced] Failed to persist privacyLevel:", err);
+    }
+  };
+
+  const handlePrivacyRulesBlur = async () => {
+    const rules = privacyRules
+      .split("\n")
+      .map((r) => r.trim())
+      .filter((r) => r.length > 0);
+    try {
+      const store = await Store.load("settings.json");
+      await store.set("privacyRules", rules);
+      await store.save();
+    } catch (err) {
+      console.error("[advanced] Failed to persist privacyRules:", err);
+    }
+  };
+
+  const [hookInstall, setHookInstall] = useState<HookInstall | null>(null);
+  const [hookError, setHookError] = useState<string | null>(null);
+
+  const handleInstallHook = async (shell: HookShell) => {
+    try {
+      setHookInstall(await invoke<HookInstall>("install_shell_hook", { shell }));
+      setHookError(null);
+    } catch (err) {
+      setHookInstall(null);
+      setHookError(String(err));
+    }
+  };
+
   const handleToggleAutoPaste = async () => {
     const newValue = !autoPasteEnabled;
     setAutoPasteEnabled(newValue);
@@ -76,9 +180,10 @@ export function AdvancedTab() {
         <button
           aria-label="Toggle destructive command detection"
           onClick={handleToggleDestructive}
+          disabled={destructiveLocked}
           className={`relative w-8 h-4 rounded-full transition-colors duration-200 ${
             destructiveDetectionEnabled ? "bg-red-500/60" : "bg-white/10"
-          }`}
+          } ${destructiveLocked ? "opacity-50" : ""}`}
         >
           <div
             className={`absolute top-0.5 w-3 h-3 rounded-full bg-white transition-transform duration-200 ${
@@ -87,6 +192,72 @@ export function AdvancedTab() {
           />
         </button>
       </div>
+      <div className="flex flex-col gap-1">
+        <span className="text-white/70 text-xs">Production name patterns</span>
+        <input
+          type="text"
+          aria-label="Production name patterns"
+          value={productionPatterns}
+          onChange={(e) => setProductionPatterns(e.target.value)}
+          onBlur={handleProductionPatternsBlur}
+          placeholder="*prod*, live-*"
+          className={[
+            "w-full bg-white/8 border border-white/10 rounded-lg",
+            "px-3 py-1.5 text-xs font-mono text-white placeholder-white/30",
+            "focus:outline-none focus:border-white/25 transition-colors",
+          ].join(" ")}
+          spellCheck={false}
+          autoComplete="off"
+        />
+        <p className="text-white/30 text-xs">
+          Kubernetes contexts, namespaces, AWS profiles and GCP projects matching these are treated as production
+        </p>
+      </div>
+
+      <p className="text-white/40 text-xs uppercase tracking-wider mt-2">
+        Privacy
+      </p>
+      <div className="flex flex-col gap-1">
+        <div className="flex gap-1">
+          {PRIVACY_LEVELS.map((level) => (
+            <button
+              key={level.id}
+              type="button"
+              onClick={() => handlePrivacyLevelChange(level.id)}
+              className={`flex-1 rounded px-2 py-1 text-xs transition-colors cursor-default ${
+                privacyLevel === level.id
+                  ? "bg-white/15 text-white"
+                  : "bg-white/5 text-white/50 hover:text-white/70"
+              }`}
+            >
+              {level.label}
+            </button>
+          ))}
+        </div>
+        <p className="text-white/30 text-xs">
+          {PRIVACY_LEVELS.find((l) => l.id === privacyLevel)?.description}
+        </p>
+      </div>
+      <div className="flex flex-col gap-1">
+        <span className="text-white/70 text-xs">Custom redaction rules</span>
+        <textarea
+          aria-label="Custom redaction rules"
+          value={privacyRules}
+          onChange={(e) => setPrivacyRules(e.target.value)}
+          onBlur={handlePrivacyRulesBlur}
+          placeholder={"CUST-\\d{6}\ninternal\\.example\\.com"}
+          rows={3}
+          className={[
+            "w-full bg-white/8 border border-white/10 rounded-lg resize-none",
+            "px-3 py-1.5 text-xs font-mono text-white placeholder-white/30",
+            "focus:outline-none focus:border-white/25 transition-colors",
+          ].join(" ")}
+          spellCheck={false}
+        />
+        <p className="text-white/30 text-xs">
+          One regular expression per line; matches are replaced before anything is sent
+        </p>
+      </div>
 
       <p className="text-white/40 text-xs uppercase tracking-wider mt-2">
         Terminal
@@ -110,6 +281,47 @@ export function AdvancedTab() {
       {!autoPasteEnabled && (
         <p className="text-amber-400/60 text-xs mt-1">Commands will not be pasted automatically</p>
       )}
+      {policy?.managed && (
+        <p className="text-white/40 text-xs mt-1">Some settings are managed by your administrator</p>
+      )}
+      {policy?.error && (
+        <p className="text-amber-400/60 text-xs mt-1">
+          Administrator policy could not be applied, restrictive defaults are in effect: {policy.error}
+        </p>
+      )}
+      <div className="flex flex-col gap-1">
+        <div className="flex items-center justify-between">
+          <span className="text-white/70 text-xs">Shell integration</span>
+          <div className="flex gap-1">
+            {(["bash", "zsh", "fish"] as HookShell[]).map((shell) => (
+              <button
+                key={shell}
+                type="button"
+                onClick={() => handleInstallHook(shell)}
+                className={`rounded px-2 py-0.5 text-xs transition-colors cursor-default ${
+                  hookInstall?.shell === shell
+                    ? "bg-white/15 text-white"
+                    : "bg-white/5 text-white/50 hover:text-white/70"
+                }`}
+              >
+                {shell}
+              </button>
+            ))}
+          </div>
+        </div>
+        {hookInstall && (
+          <>
+            <p className="text-white/30 text-xs">Add this line to {hookInstall.rc_file}:</p>
+            <code className="bg-white/8 rounded px-2 py-1 text-xs font-mono text-white/80 break-all select-text">
+              {hookInstall.source_line}
+            </code>
+          </>
+        )}
+        {hookError && <p className="text-red-400/70 text-xs">{hookError}</p>}
+        <p className="text-white/30 text-xs">
+          Reports commands, exit codes and the working directory from terminals cmd-k cannot read
+        </p>
+      </div>
 
       <p className="text-white/40 text-xs uppercase tracking-wider mt-2">
         Memory
@@ -17,13 +17,16 @@ export function useKeyboard(): void {
       }
 
       // Enter in result mode: execute the pasted command in terminal and dismiss.
-      // Guards: must have actual AI content (streamingText), no error, not destructive.
+      // Guards: must have actual AI content (streamingText), no error, not destructive,
+      // and not copied from instruction-like captured text.
       if (e.key === "Enter" && !e.shiftKey) {
         const state = useOverlayStore.getState();
         if (
           state.displayMode === "result" &&
           state.autoPasteEnabled &&
           !state.isDestructive &&
+          !state.commandUntrusted &&
+          !state.policy?.disable_auto_confirm &&
           !state.streamError &&
           state.streamingText.length > 0
         ) {
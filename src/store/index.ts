@@ -28,6 +28,113 @@ export interface TerminalContext {
   visible_output: string | null;
   running_process: string | null;
   is_wsl: boolean;
+  cloud: CloudContext | null;
+  git: GitContext | null;
+  recent_commands: ShellCommand[];
+  tools: ToolInventory | null;
+  aliases: ShellAliases | null;
+  multiplexer: MultiplexerPane | null;
+  remote: RemoteSession | null;
+  container: ContainerSession | null;
+  repl: ReplSession | null;
+}
+
+export interface ReplSession {
+  kind: "python" | "node" | "postgres" | "mysql" | "sqlite" | "redis" | "ruby" | "haskell";
+  program: string;
+}
+
+/** Display names of the REPL languages */
+export const REPL_LANGUAGES: Record<ReplSession["kind"], string> = {
+  python: "Python",
+  node: "Node.js",
+  postgres: "PostgreSQL",
+  mysql: "MySQL",
+  sqlite: "SQLite",
+  redis: "Redis",
+  ruby: "Ruby",
+  haskell: "GHCi",
+};
+
+export interface ContainerSession {
+  program: "docker" | "podman" | "kubectl" | "toolbox" | "distrobox";
+  name: string;
+  namespace: string | null;
+  container: string | null;
+  image: string | null;
+  distro: string | null;
+  os: string | null;
+  user: string | null;
+  shell: string | null;
+  cwd: string | null;
+}
+
+export interface RemoteSession {
+  program: "ssh" | "mosh" | "et";
+  host: string;
+  user: string | null;
+  port: number | null;
+  os: string | null;
+  shell: string | null;
+  cwd: string | null;
+}
+
+/** "user@host" (or just "host") of a remote session */
+export function remoteTarget(remote: RemoteSession): string {
+  return remote.user ? `${remote.user}@${remote.host}` : remote.host;
+}
+
+export interface MultiplexerPane {
+  kind: "tmux" | "screen" | "zellij";
+  session: string;
+  pane: string;
+}
+
+export interface ShellAliases {
+  aliases: [string, string][];
+  functions: string[];
+  source: string;
+}
+
+export interface ToolInfo {
+  name: string;
+  binary: string | null;
+  version: string | null;
+  flavor: string | null;
+}
+
+export interface ToolInventory {
+  search_path: string;
+  installed: ToolInfo[];
+  missing: string[];
+  probed: boolean;
+}
+
+export interface ShellCommand {
+  command: string;
+  timestamp: number | null;
+}
+
+export interface GitContext {
+  branch: string | null;
+  detached_at: string | null;
+  upstream: string | null;
+  ahead: number;
+  behind: number;
+  staged: number;
+  modified: number;
+  untracked: number;
+  conflicted: number;
+  operation: string | null;
+  remotes: [string, string][];
+}
+
+export interface CloudContext {
+  kube_context: string | null;
+  kube_namespace: string | null;
+  aws_profile: string | null;
+  aws_region: string | null;
+  gcloud_project: string | null;
 }
 
 export interface AppContext {
@@ -38,26 +145,41 @@ export interface AppContext {
   visible_text: string | null;
 }
 
-/** Resolve the badge text from AppContext using priority: WSL > shell > console > app name */
+/** Resolve the badge text from AppContext using priority: REPL > remote > container > WSL > shell > console > app name */
 export function resolveBadge(ctx: AppContext | null): string | null {
   if (!ctx) return null;
 
-  // Priority 0: WSL indicator (overrides shell type display)
+  // Priority 0: language REPL in the foreground (input goes to it, not the shell)
+  if (ctx.terminal?.repl) {
+    return `${REPL_LANGUAGES[ctx.terminal.repl.kind]} REPL`;
+  }
+
+  // Priority 1: remote session (commands run on another host)
+  if (ctx.terminal?.remote) {
+    return `${ctx.terminal.remote.program} ${remoteTarget(ctx.terminal.remote)}`;
+  }
+
+  // Priority 2: container the shell has entered
+  if (ctx.terminal?.container) {
+    return `${ctx.terminal.container.program} ${ctx.terminal.container.name}`;
+  }
+
+  // Priority 3: WSL indicator (overrides shell type display)
   if (ctx.terminal?.is_wsl) {
     return "WSL";
   }
 
-  // Priority 1: Shell type (from terminal or editor integrated terminal)
+  // Priority 4: Shell type (from terminal or editor integrated terminal)
   if (ctx.terminal?.shell_type) {
     return ctx.terminal.shell_type;
   }
 
-  // Priority 2: Console (browser has DevTools open)
+  // Priority 5: Console (browser has DevTools open)
   if (ctx.console_detected) {
     return "Console";
   }
 
-  // Priority 3: App name
+  // Priority 6: App name
   if (ctx.app_name) {
     return ctx.app_name;
   }
@@ -69,6 +191,7 @@ export interface TerminalContextSnapshot {
   cwd: string | null;
   shell_type: string | null;
   visible_output: string | null;
+  git?: GitContext | null;
 }
 
 export interface HistoryEntry {
@@ -84,6 +207,88 @@ export interface TurnMessage {
   content: string;
 }
 
+export interface PolicyView {
+  managed: boolean;
+  allowed_providers: string[] | null;
+  allowed_models: string[] | null;
+  disable_auto_confirm: boolean;
+  force_explanations: boolean;
+  locked: Record<string, unknown>;
+  /** Why the policy file could not be applied; the restrictive fallback is active. */
+  error: string | null;
+}
+
+/** Whether the administrator policy (if any) permits this provider. */
+export function isProviderAllowed(policy: PolicyView | null, providerId: string): boolean {
+  return !policy?.allowed_providers || policy.allowed_providers.includes(providerId);
+}
+
+export interface SafetyFinding {
+  category: string;
+  severity: "none" | "low" | "medium" | "high" | "critical";
+  message: string;
+}
+
+export interface GitLossReport {
+  repo: string;
+  uncommitted: string[];
+  untracked: string[];
+  unreachable_commits: string[];
+  overwritten_remote_commits: string[];
+  remote_branch: string | null;
+  dropped_stashes: string[];
+}
+
+export interface SafetyReport {
+  destructive: boolean;
+  severity: SafetyFinding["severity"];
+  findings: SafetyFinding[];
+  git_loss: GitLossReport | null;
+}
+
+export interface CommandProvenance {
+  copied_from_context: boolean;
+  matched_line: string | null;
+  in_untrusted_span: boolean;
+}
+
+export interface Redaction {
+  placeholder: string;
+  kind: string;
+  occurrences: number;
+}
+
+export interface RequestPreview {
+  provider: string;
+  model: string;
+  endpoint: string;
+  api_key_configured: boolean;
+  system_prompt: string;
+  history: TurnMessage[];
+  user_message: string;
+  redactions: Redaction[];
+  estimated_input_tokens: number;
+  estimated_cost: number | null;
+}
+
+/** Query shortcut that asks for a diagnosis of the last failed command */
+export function isFixQuery(query: string): boolean {
+  return /^\s*\/fix(\s|$)/.test(query);
+}
+
+/**
+ * Split a `/fix` response into its leading "# " diagnosis line and the
+ * corrected command that follows it.
+ */
+export function splitFixResponse(text: string): { diagnosis: string | null; command: string } {
+  const lines = text.trim().split("\n");
+  if (!lines[0]?.startsWith("#")) return { diagnosis: null, command: text.trim() };
+  return {
+    diagnosis: lines[0].replace(/^#\s*/, ""),
+    command: lines.slice(1).join("\n").trim(),
+  };
+}
+
 interface OverlayState {
   // Overlay visibility
   visible: boolean;
@@ -130,12 +335,19 @@ interface OverlayState {
   previousQuery: string;
   turnHistory: TurnMessage[];
   streamError: string | null;
+  // Diagnosis line of a /fix response, shown above the corrected command
+  fixDiagnosis: string | null;
 
   // Destructive command detection
   isDestructive: boolean;
   destructiveExplanation: string | null;
   destructiveDismissed: boolean;
   destructiveDetectionEnabled: boolean;
+  safetyReport: SafetyReport | null;
+
+  // Command provenance (copied from captured terminal/screen text)
+  provenanceWarning: string | null;
+  commandUntrusted: boolean;
 
   // Auto-paste preference
   autoPasteEnabled: boolean;
@@ -144,6 +356,10 @@ interface OverlayState {
   setAutoPasteEnabled: (enabled: boolean) => void;
   setPasteHint: (hint: string | null) => void;
 
+  // Administrator policy (read-only, loaded on startup)
+  policy: PolicyView | null;
+  setPolicy: (policy: PolicyView | null) => void;
+
   // Actions
   show: () => void;
   hide: () => void;
@@ -185,6 +401,7 @@ interface OverlayState {
   // Streaming actions
   appendToken: (token: string) => void;
   submitQuery: (query: string) => void;
+  previewRequest: (query: string) => Promise<RequestPreview>;
   cancelStreaming: () => void;
   returnToInput: () => void;
   setStreamError: (error: string | null) => void;
@@ -245,18 +462,27 @@ export const useOverlayStore = create<OverlayState>((set) => ({
   previousQuery: "",
   turnHistory: [],
   streamError: null,
+  fixDiagnosis: null,
 
   // Destructive command detection initial state
   isDestructive: false,
   destructiveExplanation: null,
   destructiveDismissed: false,
   destructiveDetectionEnabled: true,
+  safetyReport: null,
+
+  // Command provenance initial state
+  provenanceWarning: null,
+  commandUntrusted: false,
 
   // Auto-paste preference initial state
   autoPasteEnabled: true,
   isPasting: false,
   pasteHint: null,
 
+  // Administrator policy initial state
+  policy: null,
+
   show: () => {
     clearRevealTimer();
     set((state) => ({
@@ -280,10 +506,14 @@ export const useOverlayStore = create<OverlayState>((set) => ({
       displayMode: "input",
       previousQuery: "",
       streamError: null,
+      fixDiagnosis: null,
       // Reset destructive detection state on each overlay open
       isDestructive: false,
       destructiveExplanation: null,
       destructiveDismissed: false,
+      safetyReport: null,
+      provenanceWarning: null,
+      commandUntrusted: false,
       isPasting: false,
       pasteHint: null,
     }));
@@ -375,9 +605,13 @@ export const useOverlayStore = create<OverlayState>((set) => ({
       displayMode: "input",
       streamingText: "",
       streamError: null,
+      fixDiagnosis: null,
       isDestructive: false,
       destructiveExplanation: null,
       destructiveDismissed: false,
+      safetyReport: null,
+      provenanceWarning: null,
+      commandUntrusted: false,
       isPasting: false,
       pasteHint: null,
     }));
@@ -484,6 +718,7 @@ export const useOverlayStore = create<OverlayState>((set) => ({
       displayMode: "streaming",
       streamingText: "",
       streamError: null,
+      fixDiagnosis: null,
       previousQuery: query,
       inputValue: query,
       submitted: true,
@@ -491,6 +726,9 @@ export const useOverlayStore = create<OverlayState>((set) => ({
       isDestructive: false,
       destructiveExplanation: null,
       destructiveDismissed: false,
+      safetyReport: null,
+      provenanceWarning: null,
+      commandUntrusted: false,
     });
 
     (async () => {
@@ -560,6 +798,7 @@ export const useOverlayStore = create<OverlayState>((set) => ({
             cwd: appContext.terminal.cwd,
             shell_type: appContext.terminal.shell_type,
             visible_output: appContext.terminal.visible_output,
+            git: appContext.terminal.git,
           } : null;
 
           invoke("add_history_entry", {
@@ -584,34 +823,85 @@ export const useOverlayStore = create<OverlayState>((set) => ({
           useOverlayStore.getState().setWindowHistory([...currentHistory, historySync]);
         }
 
+        // A /fix response leads with a diagnosis line; only the command below it
+        // is checked, shown as the result, and pasted
+        let commandText = fullText;
+        if (isFixQuery(query) && history.length === 0) {
+          const { diagnosis, command } = splitFixResponse(fullText);
+          commandText = command;
+          set({ fixDiagnosis: diagnosis });
+        }
+
         // Destructive check BEFORE paste
         let destructive = false;
         const pasteState = useOverlayStore.getState();
-        if (pasteState.destructiveDetectionEnabled && fullText) {
+        if (pasteState.destructiveDetectionEnabled && commandText) {
+          try {
+            const report = await invoke<SafetyReport>("analyze_command", {
+              command: commandText,
+              cloud: appContext?.terminal?.cloud ?? null,
+              cwd:
+                appContext?.terminal?.is_wsl ||
+                appContext?.terminal?.remote ||
+                appContext?.terminal?.container
+                  ? null
+                  : appContext?.terminal?.cwd ?? null,
+              searchPath: appContext?.terminal?.tools?.search_path ?? null,
+              shell: appContext?.terminal?.remote
+                ? appContext.terminal.remote.shell
+                : appContext?.terminal?.container
+                  ? appContext.terminal.container.shell
+                  : appContext?.terminal?.shell_type ?? null,
+              aliases: appContext?.terminal?.repl ? null : appContext?.terminal?.aliases ?? null,
+              repl: appContext?.terminal?.repl?.kind ?? null,
+            });
+            destructive = report.destructive;
+            set({ safetyReport: report });
+          } catch (err) {
+            console.error("[store] analyze_command failed:", err);
+          }
+        }
+
+        // Provenance check: was the command lifted from captured text instead of the query?
+        let untrusted = false;
+        const contextText =
+          appContext?.terminal?.visible_output ?? appContext?.visible_text ?? null;
+        if (commandText && contextText) {
           try {
-            destructive = await invoke<boolean>("check_destructive", {
-              command: fullText,
+            const provenance = await invoke<CommandProvenance>("check_command_provenance", {
+              command: commandText,
+              query,
+              contextText,
             });
+            if (provenance.copied_from_context) {
+              untrusted = provenance.in_untrusted_span;
+              set({
+                commandUntrusted: untrusted,
+                provenanceWarning: untrusted
+                  ? "Command came from instruction-like text on screen \u2014 review before running"
+                  : "Command appears verbatim in terminal output \u2014 verify it matches your task",
+              });
+            }
           } catch (err) {
-            console.error("[store] check_destructive failed:", err);
+            console.error("[store] check_command_provenance failed:", err);
           }
         }
 
-        if (destructive) {
-          // Destructive: mark with badge, no paste
+        if (destructive || untrusted) {
+          // Destructive or injected: mark with badge, no paste
           set({
             isStreaming: false,
             displayMode: "result",
-            streamingText: fullText,
+            streamingText: commandText,
             turnHistory: trimmedHistory,
-            isDestructive: true,
+            isDestructive: destructive,
           });
-        } else if (fullText) {
+        } else if (commandText) {
           // Safe: paste to terminal, text already visible in overlay
           const afterCheck = useOverlayStore.getState();
           if (afterCheck.autoPasteEnabled) {
             set({ isPasting: true });
-            invoke<string>("paste_to_terminal", { command: fullText })
+            invoke<string>("paste_to_terminal", { command: commandText })
               .then((result) => {
                 if (result === "clipboard_hint") {
                   set({ pasteHint: "Copied to clipboard \u2014 press Ctrl+Shift+V to paste" });
@@ -631,7 +921,7 @@ export const useOverlayStore = create<OverlayState>((set) => ({
           set({
             isStreaming: false,
             displayMode: "result",
-            streamingText: fullText,
+            streamingText: commandText,
             turnHistory: trimmedHistory,
           });
         } else {
@@ -682,6 +972,19 @@ export const useOverlayStore = create<OverlayState>((set) => ({
     })();
   },
 
+  previewRequest: (query: string) => {
+    // Same arguments as stream_ai_response, minus the token channel
+    const state = useOverlayStore.getState();
+    const appContext = state.appContext;
+    return invoke<RequestPreview>("preview_request", {
+      provider: state.selectedProvider,
+      query,
+      model: state.selectedModel ?? "grok-3",
+      contextJson: appContext ? JSON.stringify(appContext) : "{}",
+      history: state.turnHistory,
+    });
+  },
+
   cancelStreaming: () => {
     clearRevealTimer();
     set({
@@ -714,4 +1017,7 @@ export const useOverlayStore = create<OverlayState>((set) => ({
   // Auto-paste preference action implementation
   setAutoPasteEnabled: (enabled) => set({ autoPasteEnabled: enabled }),
   setPasteHint: (hint) => set({ pasteHint: hint }),
+
+  // Administrator policy action implementation
+  setPolicy: (policy) => set({ policy }),
 }));